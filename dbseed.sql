@@ -2,6 +2,7 @@
 CREATE TABLE apikeys (
   id INT UNSIGNED NOT NULL AUTO_INCREMENT,
   apikey VARCHAR(45) NOT NULL,
+  strict BOOLEAN NOT NULL DEFAULT FALSE,
   PRIMARY KEY (id),
   UNIQUE KEY (apikey)
 );
@@ -18,6 +19,25 @@ CREATE TABLE currencies (
   FOREIGN KEY (apikey) REFERENCES apikeys (apikey)
 );
 
+/* One unit of currency_id is worth rate units of base_currency_id, as of time.  The rate is a decimal floating point number expressed as a significand and an exponent, like a distribution's amountbt and amount_exp. */
+CREATE TABLE prices (
+  id INT UNSIGNED NOT NULL AUTO_INCREMENT,
+  apikey VARCHAR(45) NOT NULL,
+  currency_id INT UNSIGNED NOT NULL,
+  base_currency_id INT UNSIGNED NOT NULL,
+  time VARCHAR(45) NOT NULL,
+  rate VARCHAR(40) CHARACTER SET ascii NOT NULL,
+  rate_exp TINYINT NOT NULL,
+
+  PRIMARY KEY (id, apikey),
+  FOREIGN KEY (apikey) REFERENCES apikeys (apikey),
+  FOREIGN KEY (currency_id, apikey) REFERENCES currencies (id, apikey),
+  FOREIGN KEY (base_currency_id, apikey) REFERENCES currencies (id, apikey),
+
+  CHECK( RATE REGEXP '^[0-9]*[1-9][0-9]*$' ),
+  CHECK( currency_id <> base_currency_id )
+);
+
 CREATE TABLE accounts (
   id INT UNSIGNED NOT NULL AUTO_INCREMENT,
   apikey VARCHAR(45) NOT NULL,
@@ -36,6 +56,19 @@ CREATE TABLE transactions (
   time VARCHAR(45) NOT NULL,
 
   PRIMARY KEY (id, apikey),
+  FULLTEXT KEY (notes),
+  FOREIGN KEY (apikey) REFERENCES apikeys (apikey)
+);
+
+/* A hash chain over the transactions and their distributions.  Each seal's hash covers its transaction, the transaction's distributions, and the hash of the prior seal of the same apikey.  There's deliberately no foreign key to the transaction, so that deleting a sealed transaction breaks the chain instead of being prevented. */
+CREATE TABLE seals (
+  id INT UNSIGNED NOT NULL AUTO_INCREMENT,
+  apikey VARCHAR(45) NOT NULL,
+  transaction_id INT UNSIGNED NOT NULL,
+  hash CHAR(64) CHARACTER SET ascii NOT NULL,
+
+  PRIMARY KEY (id, apikey),
+  UNIQUE KEY (transaction_id, apikey),
   FOREIGN KEY (apikey) REFERENCES apikeys (apikey)
 );
 
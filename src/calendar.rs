@@ -0,0 +1,175 @@
+use std::fmt;
+use std::str::FromStr;
+
+/*
+Transaction times are arbitrary strings, but they are usually ISO-8601.  In order to group transactions by day, week, month, quarter, or year, we need a modicum of date arithmetic.  This is just enough of it to do that, using the proleptic Gregorian calendar.
+
+The conversions between dates and day numbers are from http://howardhinnant.github.io/date_algorithms.html
+ */
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub struct Date {
+    pub year: i32,
+    pub month: u32,
+    pub day: u32,
+}
+
+impl Date {
+    /* Parse the leading YYYY, YYYY-MM, or YYYY-MM-DD of a time.  A missing month or day is 1, so "2020" is 2020-01-01.  Anything after the day, such as the time of day, is ignored.  Return None if the time doesn't start with a real date.
+     */
+    pub fn parse(time: &str) -> Option<Date> {
+        fn digits(s: &str, from: usize, len: usize) -> Option<u32> {
+            let d = s.get(from..from + len)?;
+            if d.chars().all(|c| c.is_ascii_digit()) {
+                d.parse().ok()
+            } else {
+                None
+            }
+        }
+
+        let year = digits(time, 0, 4)? as i32;
+        let month = match time.get(4..5) {
+            None => 1,
+            Some("-") => digits(time, 5, 2)?,
+            Some(_) => return None,
+        };
+        let day = match time.get(7..8) {
+            None => 1,
+            Some("-") => digits(time, 8, 2)?,
+            Some(_) => return None,
+        };
+
+        if !(1..=12).contains(&month) || day < 1 || day > days_in_month(year, month) {
+            return None;
+        }
+        Some(Date { year, month, day })
+    }
+
+    // The quantity of days since 1970-01-01.
+    pub fn to_days(&self) -> i64 {
+        let y = self.year as i64 - if self.month <= 2 { 1 } else { 0 };
+        let m = self.month as i64;
+        let era = (if y >= 0 { y } else { y - 399 }) / 400;
+        let yoe = y - era * 400;
+        let doy = (153 * (if m > 2 { m - 3 } else { m + 9 }) + 2) / 5 + self.day as i64 - 1;
+        let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+        era * 146097 + doe - 719468
+    }
+
+    // The date that is the given quantity of days since 1970-01-01.
+    pub fn from_days(days: i64) -> Date {
+        let z = days + 719468;
+        let era = (if z >= 0 { z } else { z - 146096 }) / 146097;
+        let doe = z - era * 146097;
+        let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+        let month = (if mp < 10 { mp + 3 } else { mp - 9 }) as u32;
+        let year = (yoe + era * 400 + if month <= 2 { 1 } else { 0 }) as i32;
+        Date { year, month, day }
+    }
+}
+
+// Format as YYYY-MM-DD.  Such strings sort in the same order as the dates.
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+pub fn is_leap_year(year: i32) -> bool {
+    (year % 4 == 0 && year % 100 != 0) || year % 400 == 0
+}
+
+pub fn days_in_month(year: i32, month: u32) -> u32 {
+    match month {
+        2 => {
+            if is_leap_year(year) {
+                29
+            } else {
+                28
+            }
+        }
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+// The length of a bucket of time.  Weeks start on Monday.
+#[derive(Debug, PartialEq)]
+pub enum Interval {
+    Day,
+    Week,
+    Month,
+    Quarter,
+    Year,
+}
+
+impl FromStr for Interval {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "day" => Ok(Interval::Day),
+            "week" => Ok(Interval::Week),
+            "month" => Ok(Interval::Month),
+            "quarter" => Ok(Interval::Quarter),
+            "year" => Ok(Interval::Year),
+            _ => Err(format!(
+                "{} is not an interval.  Please use day, week, month, quarter, or year.",
+                s
+            )),
+        }
+    }
+}
+
+// The first day of the bucket that contains the given date.
+pub fn bucket_start(date: &Date, interval: &Interval) -> Date {
+    match interval {
+        Interval::Day => *date,
+        Interval::Week => {
+            // 1970-01-01 was a Thursday, so Monday is day 0 of the week when we add 3.
+            let days = date.to_days();
+            Date::from_days(days - (days + 3).rem_euclid(7))
+        }
+        Interval::Month => Date { day: 1, ..*date },
+        Interval::Quarter => Date {
+            month: (date.month - 1) / 3 * 3 + 1,
+            day: 1,
+            ..*date
+        },
+        Interval::Year => Date {
+            month: 1,
+            day: 1,
+            ..*date
+        },
+    }
+}
+
+// The first day of the bucket that follows the bucket that contains the given date.
+pub fn next_bucket_start(date: &Date, interval: &Interval) -> Date {
+    let start = bucket_start(date, interval);
+    match interval {
+        Interval::Day => Date::from_days(start.to_days() + 1),
+        Interval::Week => Date::from_days(start.to_days() + 7),
+        Interval::Month | Interval::Quarter => {
+            let months = if *interval == Interval::Month { 1 } else { 3 };
+            if start.month + months > 12 {
+                Date {
+                    year: start.year + 1,
+                    month: start.month + months - 12,
+                    day: 1,
+                }
+            } else {
+                Date {
+                    month: start.month + months,
+                    ..start
+                }
+            }
+        }
+        Interval::Year => Date {
+            year: start.year + 1,
+            ..start
+        },
+    }
+}
@@ -0,0 +1,131 @@
+use crate::idlist::id_placeholders;
+use rocket_contrib::databases::mysql;
+use rocket_contrib::databases::mysql::prelude::GenericConnection;
+use std::collections::HashMap;
+use std::str::FromStr;
+
+/*
+Categories form a hierarchy, via parent_id, and they tag accounts.  These functions find the descendants of a category and build the SQL that finds the accounts tagged with given categories.
+ */
+
+/* Find the parent of every category of an apikey.  A category that has no parent maps to None.
+ */
+pub fn category_parents<C: GenericConnection>(
+    conn: &mut C,
+    apikey: &String,
+) -> Result<HashMap<u32, Option<u32>>, mysql::Error> {
+    let vec: Vec<(u32, Option<u32>)> = conn
+        .prep_exec(
+            "SELECT id, parent_id FROM categories WHERE apikey = :apikey",
+            vec![apikey.clone()],
+        )?
+        .map(|x| x.unwrap())
+        .map(|row| rocket_contrib::databases::mysql::from_row(row))
+        .collect();
+    Ok(vec.into_iter().collect())
+}
+
+/* Given the parents of every category, as per category_parents, find the given category and all of its descendants.  We visit each category no more than once, so a cycle in the hierarchy can't make this loop forever.
+ */
+pub fn category_descendants(parents: &HashMap<u32, Option<u32>>, category_id: u32) -> Vec<u32> {
+    let mut ret_val = vec![category_id];
+    let mut i = 0;
+    while i < ret_val.len() {
+        let id = ret_val[i];
+        let mut children: Vec<u32> = parents
+            .iter()
+            .filter(|(child, parent)| **parent == Some(id) && !ret_val.contains(child))
+            .map(|(child, _)| *child)
+            .collect();
+        children.sort();
+        ret_val.append(&mut children);
+        i = i + 1;
+    }
+    ret_val
+}
+
+// How shall an account match a list of categories?  Any means that it's tagged with at least one of them.  All means that it's tagged with every one of them.
+#[derive(Debug, PartialEq)]
+pub enum CategoryMatch {
+    Any,
+    All,
+}
+
+impl FromStr for CategoryMatch {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "any" => Ok(CategoryMatch::Any),
+            "all" => Ok(CategoryMatch::All),
+            _ => Err(format!("{} is not a match.  Please use any or all.", s)),
+        }
+    }
+}
+
+/* Given groups of category_id, such as a category and its descendants, build a subquery that produces the account_id of all accounts that are tagged with at least one category of _any_ group, or of _every_ group, as per the given CategoryMatch.  But reject any account that is tagged with any category of the excluded groups.  Push the apikey and the category_id onto the given param stack.
+
+A group of one category is merely a special case of the above.  So groups of one category each, matching all of them, give us the accounts that are tagged with _all_ of the given categories.
+
+WARNING! The params must be pushed in the same order that they are used in the query, so call this at the right time.
+ */
+pub fn category_filter_subquery(
+    apikey: &String,
+    groups: &[Vec<u32>],
+    category_match: &CategoryMatch,
+    exclude: &[Vec<u32>],
+    params: &mut Vec<String>,
+) -> String {
+    let all: Vec<u32> = groups.iter().flatten().cloned().collect();
+    params.push(apikey.clone());
+    let mut q = format!(
+        "
+            ( SELECT account_id
+              FROM accounts_categories
+              WHERE apikey = :apikey AND category_id IN ({})",
+        id_placeholders("category_id", &all, params)
+    );
+
+    let excluded: Vec<u32> = exclude.iter().flatten().cloned().collect();
+    if !excluded.is_empty() {
+        params.push(apikey.clone());
+        q.push_str(&format!(
+            "
+              AND account_id NOT IN (
+                SELECT account_id
+                FROM accounts_categories
+                WHERE apikey = :apikey AND category_id IN ({})
+              )",
+            id_placeholders("exclude_category_id", &excluded, params)
+        ));
+    }
+
+    q.push_str(
+        "
+              GROUP BY account_id",
+    );
+    if *category_match == CategoryMatch::All {
+        let having: Vec<String> = groups
+            .iter()
+            .enumerate()
+            .map(|(i, group)| {
+                format!(
+                    "SUM(category_id IN ({})) > 0",
+                    id_placeholders(&format!("group_{}", i), group, params)
+                )
+            })
+            .collect();
+        q.push_str(&format!(
+            "
+              HAVING {}",
+            having.join(" AND ")
+        ));
+    }
+
+    q.push_str(
+        "
+            )
+        ",
+    );
+    q
+}
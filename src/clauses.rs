@@ -0,0 +1,70 @@
+use crate::idlist::{IdList, id_placeholders};
+use rocket::http::RawStr;
+
+/*
+Several routes filter transactions the same way.  These functions build the SQL for those filters, as clauses suitable for inclusion in a WHERE clause.  They never place a param into the SQL as-is.  Instead they use placeholders and push the params onto the given param stack.
+ */
+
+/* Given optional time_start and time_stop params, build a clause that filters transactions by time, suitable for inclusion in a WHERE clause, and push the params onto the given param stack.  The clause expects the transactions table to be aliased as tx.
+
+WARNING! The params must be pushed in the same order that they are used in the query, so call this at the right time.
+
+Omitting both params gives us no filter at all.  Setting only time_stop gives us a balance as of a certain time, which is what we want for balance sheet items.  Setting both gives us the change in balance during a time period, which is what we want for income statement items.
+ */
+pub fn time_clause(
+    time_start: Option<&RawStr>,
+    time_stop: Option<&RawStr>,
+    params: &mut Vec<String>,
+) -> String {
+    match time_start {
+        None => match time_stop {
+            None => String::from(""),
+            Some(time_stop) => {
+                params.push(time_stop.html_escape().to_mut().clone());
+                String::from("AND tx.time <= :time_stop")
+            }
+        },
+        Some(time_start) => match time_stop {
+            None => {
+                params.push(time_start.html_escape().to_mut().clone());
+                String::from("AND :time_start <= tx.time")
+            }
+            Some(time_stop) => {
+                params.push(time_start.html_escape().to_mut().clone());
+                params.push(time_stop.html_escape().to_mut().clone());
+                String::from("AND :time_start <= tx.time AND tx.time <= :time_stop")
+            }
+        },
+    }
+}
+
+/* Given optional lists of category_id, build a clause that filters transactions by the categories that tag them, as per /trancats, suitable for inclusion in a WHERE clause, and push the params onto the given param stack.  If there are included categories, keep only the transactions tagged with any of them.  Reject the transactions tagged with any of the excluded categories.  The clause expects the transactions table to be aliased as tx.
+
+Omitting both lists gives us no filter at all.
+
+WARNING! The params must be pushed in the same order that they are used in the query, so call this at the right time.
+ */
+pub fn trancat_clause(
+    apikey: &String,
+    include: &Option<IdList>,
+    exclude: &Option<IdList>,
+    params: &mut Vec<String>,
+) -> String {
+    let mut clause = String::new();
+    for (list, name, not) in [(include, "trancat_id", ""), (exclude, "exclude_trancat_id", "NOT ")].iter() {
+        if let Some(ids) = list {
+            params.push(apikey.clone());
+            clause.push_str(&format!(
+                "
+            AND tx.id {}IN (
+                SELECT transaction_id
+                FROM transactions_categories
+                WHERE apikey = :apikey AND category_id IN ({})
+            )",
+                not,
+                id_placeholders(name, &ids.0, params)
+            ));
+        }
+    }
+    clause
+}
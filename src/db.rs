@@ -1,6 +1,7 @@
 use crate::dfp::dfp::DFP;
-use rocket::http::Status;
-use rocket::request::FromForm;
+use crate::idlist::IdList;
+use rocket::http::{RawStr, Status};
+use rocket::request::{FromForm, FromFormValue};
 use rocket_contrib::database;
 use rocket_contrib::databases::mysql;
 use rocket_contrib::json::JsonValue;
@@ -93,18 +94,46 @@ pub struct Acctcat2 {
 }
 
 #[derive(Deserialize)] // A test parses a response into this struct.
+#[derive(FromForm)] // POST /distributions/backfill_amounts.
 pub struct Apikey {
     pub apikey: String,
 }
 
+#[derive(FromForm)] // PUT /apikeys.
+pub struct ApikeyStrict {
+    pub apikey: String,
+    pub strict: bool,
+}
+
 #[derive(Clone, Deserialize)] // A test parses a response into this struct.
-#[derive(FromForm)] // PUT /categories.
 #[derive(Serialize)] // We send these as a json result.
 pub struct Category {
     pub id: u32,
     pub apikey: String,
     pub symbol: String,
     pub title: String,
+    pub parent_id: Option<u32>,
+}
+
+/* PUT /categories receives this.  If parent_id is absent then the parent of the category is left alone.  Send parent_id=none to remove the parent.  Receive parent_id as a Result so that we can explain a malformed value.
+ */
+#[derive(FromForm)] // PUT /categories.
+pub struct CategoryUpdate {
+    pub id: u32,
+    pub apikey: String,
+    pub symbol: String,
+    pub title: String,
+    pub parent_id: Option<Result<ParentId, String>>,
+}
+
+// GET /categories/tree produces a forest of these.  Please see crate::routz::category::get_categories_tree.
+#[derive(Deserialize)] // A test parses a response into this struct.
+#[derive(Serialize)] // We send these as a json result.
+pub struct CategoryNode {
+    pub id: u32,
+    pub symbol: String,
+    pub title: String,
+    pub children: Vec<CategoryNode>,
 }
 
 #[derive(FromForm)] // POST /categories.
@@ -112,7 +141,95 @@ pub struct CategoryShort {
     pub apikey: String,
     pub symbol: String,
     pub title: String,
+    pub parent_id: Option<u32>,
+}
+
+#[derive(Deserialize)] // A test parses a response into this struct.
+pub struct CategoryTree {
+    pub tree: Vec<CategoryNode>,
+}
+
+// The new parent_id of a category, as given to PUT /categories.  Please see CategoryUpdate.
+pub enum ParentId {
+    Clear,
+    Id(u32),
+}
+
+impl<'v> FromFormValue<'v> for ParentId {
+    type Error = String;
+
+    fn from_form_value(form_value: &'v RawStr) -> Result<Self, Self::Error> {
+        match form_value.as_str() {
+            "none" => Ok(ParentId::Clear),
+            s => s
+                .parse()
+                .map(ParentId::Id)
+                .map_err(|_| format!("parent_id {} is neither a category id nor none.", s)),
+        }
+    }
+}
+
+/* GET /category_dist_sums optionally receives these params.  Please see crate::routz::get_category_dist_sums::get_category_dist_sums.
+
+The match param is a keyword, so we can't name a field after it.  Rename it instead.
+ */
+#[derive(FromForm)] // GET /category_dist_sums.
+pub struct CategoryDistSumsParams<'f> {
+    pub time_start: Option<&'f RawStr>,
+    pub time_stop: Option<&'f RawStr>,
+    pub decorate: Option<&'f RawStr>,
+    pub format: Option<&'f RawStr>,
+    pub convert_to: Option<&'f RawStr>,
+    pub valuation_time: Option<&'f RawStr>,
+    pub descendants: Option<&'f RawStr>,
+    pub exclude_category_id: Option<Result<IdList, String>>,
+    pub trancat_id: Option<Result<IdList, String>>,
+    pub exclude_trancat_id: Option<Result<IdList, String>>,
+    #[form(field = "match")]
+    pub category_match: Option<&'f RawStr>,
+}
+
+// POST /transactions/compound receives a transaction, its distributions, and its categories all at once.
+#[derive(Deserialize)] // POST /transactions/compound.
+#[derive(Serialize)] // A test sends this as the body of a request.
+pub struct CompoundDistribution {
+    pub account_id: u32,
+    pub amount: i64,
+    pub amount_exp: i8,
+    pub amountbt: String,
+}
+
+#[derive(Deserialize)] // POST /transactions/compound.
+#[derive(Serialize)] // A test sends this as the body of a request.
+pub struct CompoundTransaction {
+    pub apikey: String,
+    pub notes: String,
+    pub time: String,
+    pub distributions: Vec<CompoundDistribution>,
+    pub category_ids: Vec<u32>,
+}
+
+// PUT /transactions/compound replaces the notes, time, distributions, and categories of an existing transaction all at once.
+#[derive(Deserialize)] // PUT /transactions/compound.
+#[derive(Serialize)] // A test sends this as the body of a request.
+pub struct CompoundTransactionUpdate {
+    pub id: u32,
+    pub apikey: String,
+    pub notes: String,
+    pub time: String,
+    pub distributions: Vec<CompoundDistribution>,
+    pub category_ids: Vec<u32>,
+}
+
+// The ids of all the records created by POST or PUT /transactions/compound.
+#[derive(Deserialize)] // A test parses a response into this struct.
+#[derive(Serialize)] // We send these as a json result.
+pub struct CompoundTransactionIds {
+    pub transaction_id: u64,
+    pub distribution_ids: Vec<u64>,
+    pub trancat_ids: Vec<u64>,
 }
+
 #[derive(Clone, Deserialize)] // A test parses a response into this struct.
 #[derive(FromForm)] // PUT /currencies.
 #[derive(Serialize)] // We send these as a json result.
@@ -172,8 +289,10 @@ pub struct DistributionJoined {
     pub tx_time: String,
 }
 
+#[derive(Deserialize)] // A test parses a response into this struct.
 #[derive(FromForm)] // POST /distributions.
 #[derive(Debug)]
+#[derive(Serialize)] // GET /allocation sends these as a json result.
 pub struct DistributionShort {
     pub account_id: u32,
     pub amount: i64,
@@ -183,6 +302,93 @@ pub struct DistributionShort {
     pub transaction_id: u32,
 }
 
+// The outcome of POST /distributions/backfill_amounts.  We can't fix a distribution whose amountbt is not an integer, so list those instead.
+#[derive(Deserialize)] // A test parses a response into this struct.
+#[derive(Serialize)] // We send these as a json result.
+pub struct DistributionBackfill {
+    pub updated: u32,
+    pub bad_amountbt: Vec<u32>,
+}
+
+/* One unit of currency_id is worth rate units of base_currency_id, as of time.  The rate is a decimal floating point number expressed as a significand and an exponent.
+ */
+#[derive(Clone, Deserialize)] // A test parses a response into this struct.
+#[derive(FromForm)] // PUT /prices.
+#[derive(Serialize)] // We send these as a json result.
+pub struct Price {
+    pub id: u32,
+    pub apikey: String,
+    pub currency_id: u32,
+    pub base_currency_id: u32,
+    pub time: String,
+    pub rate: String,
+    pub rate_exp: i8,
+}
+
+#[derive(FromForm)] // POST /prices.
+pub struct PriceShort {
+    pub apikey: String,
+    pub currency_id: u32,
+    pub base_currency_id: u32,
+    pub time: String,
+    pub rate: String,
+    pub rate_exp: i8,
+}
+
+// GET /linter returns a collection of findings.  Please see crate::linter.
+#[derive(Clone, Debug, Deserialize, PartialEq)] // A test parses a response into this struct.
+#[derive(Serialize)] // We send these as a json result.
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+#[derive(Deserialize)] // A test parses a response into this struct.
+#[derive(Serialize)] // We send these as a json result.
+pub struct Finding {
+    pub rule: String,
+    pub severity: Severity,
+    pub table: String,
+    pub id: u32,
+    pub message: String,
+    pub sums: Vec<CurrencySum>,
+}
+
+#[derive(Deserialize)] // A test parses a response into this struct.
+#[derive(Serialize)] // We send these as a json result.
+pub struct LinterRule {
+    pub id: String,
+    pub severity: Severity,
+    pub description: String,
+    pub fixable: bool,
+}
+
+#[derive(FromForm)] // POST /linter/fix.
+pub struct LinterFix {
+    pub apikey: String,
+    pub rules: Option<String>,
+    pub apply: Option<bool>,
+}
+
+// A fixable linter rule produces these.  Please see crate::linter.
+#[derive(Deserialize)] // A test parses a response into this struct.
+#[derive(Serialize)] // We send these as a json result.
+pub struct Mutation {
+    pub rule: String,
+    pub action: MutationAction,
+    pub table: String,
+    pub id: u32,
+}
+
+// What a Mutation does to its record.
+#[derive(Debug, PartialEq)]
+#[derive(Deserialize)] // A test parses a response into this struct.
+#[derive(Serialize)] // We send these as a json result.
+pub enum MutationAction {
+    Delete,
+}
+
 // A linter will return a collection of id.
 #[derive(Deserialize)] // A test parses a response into this struct.
 #[derive(Serialize)] // We send these as a json result.
@@ -199,6 +405,16 @@ pub struct LinterLong {
     pub title: String,
 }
 
+// A linter that examines transactions will return the suspicious transactions, along with the sum of their distributions within each currency.
+#[derive(Deserialize)] // A test parses a response into this struct.
+#[derive(Serialize)] // We send these as a json result.
+pub struct LinterTransaction {
+    pub id: u32,
+    pub notes: String,
+    pub time: String,
+    pub sums: Vec<CurrencySum>,
+}
+
 #[derive(Deserialize)] // A test parses a response into this struct.
 #[derive(Serialize)] // We send these as a json result.
 pub struct Ping {
@@ -239,6 +455,22 @@ pub struct Trancat2 {
     pub category_symbol: String,
 }
 
+// GET /seals/verify reports the first seal whose hash can't be reproduced, if any.  Please see crate::routz::seal.
+#[derive(Deserialize)] // A test parses a response into this struct.
+#[derive(Serialize)] // We send these as a json result.
+pub struct BrokenSeal {
+    pub seal_id: u32,
+    pub transaction_id: u32,
+    pub reason: String,
+}
+
+#[derive(Deserialize)] // A test parses a response into this struct.
+#[derive(Serialize)] // We send these as a json result.
+pub struct SealVerification {
+    pub seal_cnt: u32,
+    pub broken: Option<BrokenSeal>,
+}
+
 #[derive(Clone, Deserialize)] // A test parses a response into this struct.
 #[derive(FromForm)] // PUT /transactions.
 #[derive(Serialize)] // We send these as a json result.
@@ -249,6 +481,30 @@ pub struct Transaction {
     pub time: String,
 }
 
+// GET /transactions returns a page of these, when the caller asks for paging.
+#[derive(Deserialize)] // A test parses a response into this struct.
+#[derive(Serialize)] // We send these as a json result.
+pub struct TransactionPage {
+    pub transactions: Vec<Transaction>,
+    pub next_cursor: Option<String>,
+}
+
+// GET /transactions optionally receives these filters.  Please see crate::routz::transaction::get_transactions.
+#[derive(FromForm)] // GET /transactions.
+pub struct TransactionSearch {
+    pub time_start: Option<String>,
+    pub time_stop: Option<String>,
+    pub notes: Option<String>,
+    pub fulltext: Option<String>,
+    pub account_id: Option<Result<IdList, String>>,
+    pub category_id: Option<Result<IdList, String>>,
+    pub amount_min: Option<String>,
+    pub amount_max: Option<String>,
+    pub sort: Option<String>,
+    pub cursor: Option<String>,
+    pub limit: Option<String>,
+}
+
 #[derive(FromForm)] // POST /transactions.
 pub struct TransactionShort {
     pub apikey: String,
@@ -256,9 +512,31 @@ pub struct TransactionShort {
     pub time: String,
 }
 
+// The sum of the distributions of a transaction, within a single currency, that should be zero but isn't.
+#[derive(Deserialize)] // A test parses a response into this struct.
+#[derive(Serialize)] // We send these as a json result.
+pub struct UnbalancedTransaction {
+    pub transaction_id: u32,
+    pub currency_id: u32,
+    pub sum: DFP,
+}
+
 /*
 2. account_dist_sum and category_dist_sums produce a variety of outputs related to the sum of all distributions for accounts, as well as optional decorations.
  */
+
+// GET /account_dist_sum optionally receives these params.  Please see crate::routz::get_account_dist_sum::get_account_dist_sum.
+#[derive(FromForm)] // GET /account_dist_sum.
+pub struct AccountDistSumParams<'f> {
+    pub time_start: Option<&'f RawStr>,
+    pub time_stop: Option<&'f RawStr>,
+    pub format: Option<&'f RawStr>,
+    pub convert_to: Option<&'f RawStr>,
+    pub valuation_time: Option<&'f RawStr>,
+    pub trancat_id: Option<Result<IdList, String>>,
+    pub exclude_trancat_id: Option<Result<IdList, String>>,
+}
+
 #[derive(Deserialize)] // A test parses a response into this struct.
 #[derive(Serialize)] // We send these as a json result.
 pub struct AccountCurrency {
@@ -306,6 +584,13 @@ pub struct BalanceResultDecorated {
     pub sum: DFP,
 }
 
+#[derive(Deserialize)] // A test parses a response into this struct.
+#[derive(Serialize)] // We send these as a json result.
+pub struct CurrencySum {
+    pub currency_id: u32,
+    pub sum: DFP,
+}
+
 #[derive(Deserialize)] // A test parses a response into this struct.
 #[derive(Serialize)] // We send these as a json result.
 pub struct CurrencySymbol {
@@ -328,6 +613,123 @@ pub struct SumsDecorated {
     pub sums: Vec<BalanceResultDecorated>,
 }
 
+/*
+2.1 The reports are built from the same sums, organized in a variety of ways.
+ */
+#[derive(Deserialize)] // A test parses a response into this struct.
+#[derive(Serialize)] // We send these as a json result.
+pub struct TrialBalance {
+    pub currencies: Vec<TrialBalanceCurrency>,
+}
+
+#[derive(Deserialize)] // A test parses a response into this struct.
+#[derive(Serialize)] // We send these as a json result.
+pub struct TrialBalanceAccount {
+    pub account_id: u32,
+    pub title: String,
+    pub sum: DFP,
+}
+
+#[derive(Deserialize)] // A test parses a response into this struct.
+#[derive(Serialize)] // We send these as a json result.
+pub struct TrialBalanceCurrency {
+    pub currency: CurrencySymbol,
+    pub accounts: Vec<TrialBalanceAccount>,
+    pub total: DFP,
+}
+
+// GET /account_register optionally receives these params.  Please see crate::routz::get_account_register::get_account_register.
+#[derive(FromForm)] // GET /account_register.
+pub struct AccountRegisterParams<'f> {
+    pub time_start: Option<&'f RawStr>,
+    pub time_stop: Option<&'f RawStr>,
+    pub offset: Option<&'f RawStr>,
+    pub limit: Option<&'f RawStr>,
+    pub format: Option<&'f RawStr>,
+}
+
+/* An account register.  The entries are a single page of the distributions of an account, each followed by the running balance of the account.  The opening balance is the balance as of the start of the period and the closing balance is the balance as of the end of the period, regardless of the page.
+ */
+#[derive(Deserialize)] // A test parses a response into this struct.
+#[derive(Serialize)] // We send these as a json result.
+pub struct AccountRegister {
+    pub account_id: u32,
+    pub opening_balance: DFP,
+    pub entries: Vec<AccountRegisterEntry>,
+    pub closing_balance: DFP,
+    pub offset: u32,
+    pub limit: u32,
+    pub entry_cnt: u32, // The quantity of entries in the period, on all pages.
+}
+
+#[derive(Deserialize)] // A test parses a response into this struct.
+#[derive(Serialize)] // We send these as a json result.
+pub struct AccountRegisterEntry {
+    pub distribution_id: u32,
+    pub transaction_id: u32,
+    pub time: String,
+    pub notes: String,
+    pub amount: DFP,
+    pub balance: DFP,
+}
+
+// GET /balance_series optionally receives these params.  Please see crate::routz::get_balance_series::get_balance_series.
+#[derive(FromForm)] // GET /balance_series.
+pub struct BalanceSeriesParams<'f> {
+    pub account_id: Option<&'f RawStr>,
+    pub category_id: Option<Result<IdList, String>>,
+    pub cumulative: Option<&'f RawStr>,
+    pub format: Option<&'f RawStr>,
+}
+
+/* A balance series.  Each account has one point per bucket of time, in chronological order.  The start of each bucket is inclusive and the stop is exclusive.
+ */
+#[derive(Deserialize)] // A test parses a response into this struct.
+#[derive(Serialize)] // We send these as a json result.
+pub struct BalanceSeries {
+    pub series: Vec<BalanceSeriesAccount>,
+}
+
+#[derive(Deserialize)] // A test parses a response into this struct.
+#[derive(Serialize)] // We send these as a json result.
+pub struct BalanceSeriesAccount {
+    pub account_id: u32,
+    pub points: Vec<BalanceSeriesPoint>,
+}
+
+#[derive(Deserialize)] // A test parses a response into this struct.
+#[derive(Serialize)] // We send these as a json result.
+pub struct BalanceSeriesPoint {
+    pub start: String,
+    pub stop: String,
+    pub sum: DFP,
+}
+
+/* A balance sheet or an income statement.  Each currency has one section per requested category, in the order requested, even if the section has no accounts.
+ */
+#[derive(Deserialize)] // A test parses a response into this struct.
+#[derive(Serialize)] // We send these as a json result.
+pub struct Report {
+    pub currencies: Vec<ReportCurrency>,
+}
+
+#[derive(Deserialize)] // A test parses a response into this struct.
+#[derive(Serialize)] // We send these as a json result.
+pub struct ReportCurrency {
+    pub currency: CurrencySymbol,
+    pub sections: Vec<ReportSection>,
+    pub total: DFP,
+}
+
+#[derive(Deserialize)] // A test parses a response into this struct.
+#[derive(Serialize)] // We send these as a json result.
+pub struct ReportSection {
+    pub section: String,
+    pub category_id: u32,
+    pub accounts: Vec<TrialBalanceAccount>,
+    pub subtotal: DFP,
+}
+
 // 3. The response types
 #[derive(Deserialize)] // A test parses a response into this struct.
 #[derive(Serialize)] // We send these as a json result.
@@ -382,6 +784,14 @@ pub enum GetDistributionResponse {
     Error(String),
 }
 
+#[derive(Deserialize)] // A test parses a response into this struct.
+#[derive(Serialize)] // We send these as a json result.
+#[serde(untagged)]
+pub enum GetAllocationResponse {
+    Many(Vec<DistributionShort>),
+    Error(String),
+}
+
 #[derive(Deserialize)] // A test parses a response into this struct.
 #[derive(Serialize)] // We send these as a json result.
 #[serde(untagged)]
@@ -391,6 +801,23 @@ pub enum GetDistributionJoinedResponse {
     Error(String),
 }
 
+#[derive(Deserialize)] // A test parses a response into this struct.
+#[derive(Serialize)] // We send these as a json result.
+#[serde(untagged)]
+pub enum GetLinterResponse {
+    Many(Vec<Finding>),
+    Error(String),
+}
+
+#[derive(Deserialize)] // A test parses a response into this struct.
+#[derive(Serialize)] // We send these as a json result.
+#[serde(untagged)]
+pub enum GetPriceResponse {
+    One(Price),
+    Many(Vec<Price>),
+    Error(String),
+}
+
 #[derive(Deserialize)] // A test parses a response into this struct.
 #[derive(Serialize)] // We send these as a json result.
 #[serde(untagged)]
@@ -406,6 +833,15 @@ pub enum GetTrancatResponse {
 pub enum GetTransactionResponse {
     One(Transaction),
     Many(Vec<Transaction>),
+    Page(TransactionPage),
+    Error(String),
+}
+
+#[derive(Deserialize)] // A test parses a response into this struct.
+#[derive(Serialize)] // We send these as a json result.
+#[serde(untagged)]
+pub enum GetUnbalancedResponse {
+    Many(Vec<UnbalancedTransaction>),
     Error(String),
 }
 
@@ -417,6 +853,30 @@ pub enum PostApikeysResponse {
     Error(String),
 }
 
+#[derive(Deserialize)] // A test parses a response into this struct.
+#[derive(Serialize)] // We send these as a json result.
+#[serde(untagged)]
+pub enum PostBackfillAmountsResponse {
+    Backfill(DistributionBackfill),
+    Error(String),
+}
+
+#[derive(Deserialize)] // A test parses a response into this struct.
+#[derive(Serialize)] // We send these as a json result.
+#[serde(untagged)]
+pub enum PostLinterFixResponse {
+    Many(Vec<Mutation>),
+    Error(String),
+}
+
+#[derive(Deserialize)] // A test parses a response into this struct.
+#[derive(Serialize)] // We send these as a json result.
+#[serde(untagged)]
+pub enum PostCompoundTransactionResponse {
+    Ids(CompoundTransactionIds),
+    Error(String),
+}
+
 pub struct ApiResponseOld {
     pub json: JsonValue,
     pub status: Status,
@@ -1,6 +1,11 @@
 pub mod dfp {
 
     use serde::{Deserialize, Serialize};
+    use serde_json::{json, Value};
+    use std::cmp::Ordering;
+    use std::convert::TryFrom;
+    use std::fmt;
+    use std::str::FromStr;
 
     #[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
     pub enum Sign {
@@ -9,13 +14,291 @@ pub mod dfp {
         Zero
     }
 
-    #[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+    #[derive(Clone, Debug, Deserialize, Serialize)]
+    #[serde(try_from = "DFPWire")]
     pub struct DFP {
         pub amount : Vec<u8>,
         pub exp : i8,
         pub sign : Sign
     }
 
+    /* Compare DFP by value rather than by representation.  For example [1], 3 and [0, 1], 2 both
+    represent 1000, so they are equal, even though their amount and exp are different.
+
+    Negative < Zero < Positive. If the signs are the same, we must compare the magnitudes. We do this by
+    first making the exponents the same, using dfp_norm_exp, so that we can then compare the digits using md_compare.
+    */
+    impl Ord for DFP {
+        fn cmp(&self, other: &Self) -> Ordering {
+
+            // A DFP with no non-zero digits is zero, no matter what its sign says.
+            fn rank(dfp :&DFP) -> i8 {
+                if md_stripz(&dfp.amount).len() == 0 {
+                    return 0
+                }
+                match dfp.sign {
+                    Sign::Negative => -1,
+                    Sign::Zero => 0,
+                    Sign::Positive => 1
+                }
+            }
+
+            let (r1, r2) = (rank(self), rank(other));
+            if r1 != r2 || r1 == 0 {
+                return r1.cmp(&r2)
+            }
+
+            let (n1, n2) = dfp_norm_exp(self.clone(), other.clone());
+            let magnitude = match md_compare(&md_stripz(&n1.amount), &md_stripz(&n2.amount)) {
+                -1 => Ordering::Less,
+                0 => Ordering::Equal,
+                _ => Ordering::Greater
+            };
+
+            if r1 < 0 {
+                magnitude.reverse()
+            } else {
+                magnitude
+            }
+        }
+    }
+
+    impl PartialOrd for DFP {
+        fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+            Some(self.cmp(other))
+        }
+    }
+
+    impl PartialEq for DFP {
+        fn eq(&self, other: &Self) -> bool {
+            self.cmp(other) == Ordering::Equal
+        }
+    }
+
+    impl Eq for DFP {}
+
+    /* When a result cannot be exactly expressed using the requested exponent, such as 1 / 3, we must
+    discard some digits.  The RoundingMode tells us what to do with the last digit that we keep.
+
+    HalfEven - Round to the nearest value. If exactly halfway, round to the nearest even digit. AKA banker's rounding.
+    HalfUp - Round to the nearest value.  If exactly halfway, round away from zero.
+    Floor - Round towards negative infinity.
+    Ceiling - Round towards positive infinity.
+    Truncate - Round towards zero.  In other words, simply discard the extra digits.
+    */
+    #[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+    pub enum RoundingMode {
+        HalfEven,
+        HalfUp,
+        Floor,
+        Ceiling,
+        Truncate
+    }
+
+    /* Parsing a DFP from human-readable decimal text, such as "-1234.56" or "1.5e-8", can fail in
+    a variety of ways.  Unlike dfp_from_string_exp, which cheerfully ignores anything that isn't a
+    digit, we want to tell the caller exactly what's wrong with the input.
+    */
+    #[derive(Clone, Debug, PartialEq)]
+    pub enum ParseDFPError {
+        Empty,                  // There are no digits at all, such as "" or "-".
+        InvalidCharacter(char), // Something that does not belong, such as the 2nd '-' in "12-3".
+        MultipleDecimalPoints,  // Such as "1.2.3".
+        MissingExponent,        // There is an 'e' but no exponent digits, such as "1.5e".
+        ExponentOutOfRange,     // The value is too large or too small to be expressed using an i8 exponent.
+    }
+
+    impl fmt::Display for ParseDFPError {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            match self {
+                ParseDFPError::Empty => write!(f, "The number does not contain any digits."),
+                ParseDFPError::InvalidCharacter(c) => write!(f, "The number contains an invalid character '{}'.", c),
+                ParseDFPError::MultipleDecimalPoints => write!(f, "The number contains more than one decimal point."),
+                ParseDFPError::MissingExponent => write!(f, "The number does not contain any exponent digits."),
+                ParseDFPError::ExponentOutOfRange => write!(f, "The exponent of the number is out of range."),
+            }
+        }
+    }
+
+    impl std::error::Error for ParseDFPError {}
+
+    /* Parse human-readable decimal text into a normalized DFP.  We accept an optional sign, digits with
+    an optional decimal point, and an optional exponent introduced by 'e' or 'E'.  For example:
+    "-1234.5600", "+7", ".5", "1.5e-8" and "15E-9" are all acceptable.
+    */
+    impl FromStr for DFP {
+        type Err = ParseDFPError;
+
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+
+            // 1. Split off the exponent, if any.
+            let (mantissa, exponent) = match s.find(|c| c == 'e' || c == 'E') {
+                Some(i) => (&s[..i], Some(&s[i + 1..])),
+                None => (s, None)
+            };
+
+            // 2. Deal with the sign of the mantissa.
+            let (negative, mantissa) = if mantissa.starts_with('-') {
+                (true, &mantissa[1..])
+            } else if mantissa.starts_with('+') {
+                (false, &mantissa[1..])
+            } else {
+                (false, mantissa)
+            };
+
+            // 3. Collect the digits, MSD first, and count how many of them follow the decimal point.
+            let mut digits :Vec<u8> = Vec::new();
+            let mut frac_cnt :i32 = 0;
+            let mut seen_point = false;
+            for c in mantissa.chars() {
+                match c {
+                    '0'..='9' => {
+                        digits.push(c as u8 - b'0');
+                        if seen_point {
+                            frac_cnt = frac_cnt + 1;
+                        }
+                    }
+                    '.' => {
+                        if seen_point {
+                            return Err(ParseDFPError::MultipleDecimalPoints)
+                        }
+                        seen_point = true;
+                    }
+                    _ => return Err(ParseDFPError::InvalidCharacter(c))
+                }
+            }
+
+            if digits.len() == 0 {
+                return Err(ParseDFPError::Empty)
+            }
+
+            // 4. Parse the exponent, if any.  An exponent that is absurdly long is certainly out of range.
+            let e :i32 = match exponent {
+                None => 0,
+                Some(x) => {
+                    let unsigned = x.trim_start_matches(|c| c == '-' || c == '+');
+                    if unsigned.len() == 0 {
+                        return Err(ParseDFPError::MissingExponent)
+                    }
+                    if x.len() - unsigned.len() > 1 {
+                        return Err(ParseDFPError::InvalidCharacter(x.chars().nth(1).unwrap()))
+                    }
+                    if let Some(c) = unsigned.chars().find(|c| !c.is_ascii_digit()) {
+                        return Err(ParseDFPError::InvalidCharacter(c))
+                    }
+                    match x.parse::<i32>() {
+                        Ok(n) => n,
+                        Err(_) => return Err(ParseDFPError::ExponentOutOfRange)
+                    }
+                }
+            };
+
+            // 5. Build the significand in the usual LSD first order and remove any MSD and LSD zeros.  Do the
+            // LSD part by hand because the exponent might temporarily exceed the range of an i8.
+            digits.reverse();
+            let mut amount = md_stripz(&digits);
+            if amount.len() == 0 {
+                return Ok(DFP { amount: vec![], exp: 0, sign: Sign::Zero })
+            }
+
+            let mut exp :i64 = e as i64 - frac_cnt as i64;
+            let lsd_zeros = amount.iter().take_while(|d| **d == 0).count();
+            amount.drain(..lsd_zeros);
+            exp = exp + lsd_zeros as i64;
+
+            if exp < i8::MIN as i64 || exp > i8::MAX as i64 {
+                return Err(ParseDFPError::ExponentOutOfRange)
+            }
+
+            let sign = if negative { Sign::Negative } else { Sign::Positive };
+            Ok(DFP { amount: amount, exp: exp as i8, sign: sign })
+        }
+    }
+
+    /* Format a DFP as canonical decimal text, without an exponent.  For example 1.5e-8 becomes
+    "0.000000015" and 12e2 becomes "1200".  Because we normalize first, there will never be any
+    trailing zeros after the decimal point.
+    */
+    impl fmt::Display for DFP {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+
+            let n = dfp_norm(self.clone());
+            let digits :String = md_stripz(&n.amount).iter().rev().map(|d| (b'0' + d) as char).collect();
+            if n.sign == Sign::Zero || digits.len() == 0 {
+                return write!(f, "0")
+            }
+
+            let sign = if n.sign == Sign::Negative { "-" } else { "" };
+
+            if n.exp >= 0 {
+                write!(f, "{}{}{}", sign, digits, "0".repeat(n.exp as usize))
+            } else {
+                let k = -(n.exp as i32) as usize;
+                if digits.len() > k {
+                    let (int_part, frac_part) = digits.split_at(digits.len() - k);
+                    write!(f, "{}{}.{}", sign, int_part, frac_part)
+                } else {
+                    write!(f, "{}0.{}{}", sign, "0".repeat(k - digits.len()), digits)
+                }
+            }
+        }
+    }
+
+    /* A DFP can be sent over the wire in a variety of formats.  For example, -1234.56 can be sent as:
+
+    Digits - {"amount":[6,5,4,3,2,1],"exp":-2,"sign":"Negative"}.  This is the original format and remains the default.
+    Decimal - "-1234.56"
+    Significand - {"significand":"-123456","exp":-2}
+
+    We serialize using whichever format the client asks for, via dfp_to_json, and we deserialize any of them.
+    */
+    #[derive(Clone, Debug, PartialEq)]
+    pub enum DFPFormat {
+        Digits,
+        Decimal,
+        Significand
+    }
+
+    impl FromStr for DFPFormat {
+        type Err = String;
+
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            match s {
+                "digits" => Ok(DFPFormat::Digits),
+                "decimal" => Ok(DFPFormat::Decimal),
+                "significand" => Ok(DFPFormat::Significand),
+                _ => Err(format!("The format '{}' is not one of digits, decimal, or significand.", s))
+            }
+        }
+    }
+
+    // Serde tries these in order and uses the first one that fits.
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum DFPWire {
+        Digits { amount: Vec<u8>, exp: i8, sign: Sign },
+        Decimal(String),
+        Significand { significand: String, exp: i8 }
+    }
+
+    impl TryFrom<DFPWire> for DFP {
+        type Error = ParseDFPError;
+
+        fn try_from(wire: DFPWire) -> Result<Self, Self::Error> {
+            match wire {
+                DFPWire::Digits { amount, exp, sign } => Ok(DFP { amount: amount, exp: exp, sign: sign }),
+                DFPWire::Decimal(s) => s.parse::<DFP>(),
+                DFPWire::Significand { significand, exp } => {
+                    // The significand must be an integer, so don't let a decimal point or exponent sneak in.
+                    if let Some(c) = significand.chars().find(|c| *c == '.' || *c == 'e' || *c == 'E') {
+                        return Err(ParseDFPError::InvalidCharacter(c))
+                    }
+                    format!("{}e{}", significand, exp).parse::<DFP>()
+                }
+            }
+        }
+    }
+
     /* Given a &DFP return a new DFP that is the absolute value of the original. */
     pub fn dfp_abs(dfp :&DFP) -> DFP {
 
@@ -29,6 +312,28 @@ pub mod dfp {
     /* Given two DFP, add them together and return a new DFP as the sum. */
     pub fn dfp_add( dfp1 : DFP, dfp2 : DFP) -> DFP {
 
+        // Most real-world amounts fit comfortably into an i128, so try that first.
+        match (dfp_to_i128(&dfp1), dfp_to_i128(&dfp2)) {
+            (Some(i1), Some(i2)) => {
+                let exp = if dfp1.exp < dfp2.exp { dfp1.exp } else { dfp2.exp };
+                let sum = i128_rescale(i1, dfp1.exp, exp)
+                    .and_then(|a| i128_rescale(i2, dfp2.exp, exp).and_then(|b| a.checked_add(b)));
+                if let Some(sum) = sum {
+                    return dfp_from_i128(sum, exp)
+                }
+            }
+            _ => {}
+        }
+
+        // Something overflowed so do it the hard way.
+        dfp_add_md( dfp1, dfp2)
+    }
+
+    /* Given two DFP, add them together, digit by digit, and return a new DFP as the sum.  This works
+    for any DFP, no matter how many digits, but is much slower than using an i128.
+    */
+    fn dfp_add_md( dfp1 : DFP, dfp2 : DFP) -> DFP {
+
         // Both of these now have the same exp.  We can freely use n1.exp to build a return value with
         // the correct exp.
         let (n1, n2) = dfp_norm_exp( dfp1, dfp2);
@@ -92,10 +397,141 @@ pub mod dfp {
         }
     }
 
+    /* Given an amount, allocate it across a collection of weights and return a Vec of shares, one for each weight, in the same order.
+
+    For example, allocating 100 across the weights 1, 1, 1 using exp 0 yields 34, 33, 33.  The shares are expressed using
+    the given exp and always add up to exactly the amount, after rounding said amount to exp using HalfEven. Each share
+    is first computed by truncating its exact proportion of the amount.  The leftover units of 10^exp are then handed out,
+    one per share, to the shares with the largest truncated remainders.  Ties go to the share that appears first.  In this
+    way the result is deterministic.
+
+    The weights must not be negative and they must not add up to zero.  If they do, or if there are no weights at all, return None.
+    Also return None if any of the intermediate products overflow.  Please see dfp_mul.
+    */
+    pub fn dfp_allocate( amount : DFP, weights : &Vec<DFP>, exp : i8) -> Option<Vec<DFP>> {
+
+        let zero = DFP { amount: vec![], exp: 0, sign: Sign::Zero };
+
+        // 1. Validate the weights and find their total.
+        if weights.len() == 0 || weights.iter().any(|w| *w < zero) {
+            return None
+        }
+        let total = weights.iter().fold(zero.clone(), |acc, w| dfp_add(acc, w.clone()));
+        if total == zero {
+            return None
+        }
+
+        // 2. Compute the truncated shares as well as how much each share was truncated by.  The exact share is
+        // (target * w) / total so we can compare the remainders using only their numerators.
+        let target = dfp_round(amount, exp, RoundingMode::HalfEven);
+        let mut shares :Vec<DFP> = Vec::new();
+        let mut remainders :Vec<DFP> = Vec::new();
+        for w in weights {
+            let numerator = dfp_mul(target.clone(), w.clone())?;
+            let share = dfp_div(numerator.clone(), total.clone(), exp, RoundingMode::Truncate)?;
+            remainders.push(dfp_abs(&dfp_sub(numerator, dfp_mul(share.clone(), total.clone())?)));
+            shares.push(share);
+        }
+
+        // 3. Hand out the leftover units, largest remainder first.  This is a stable sort so ties retain their original order.
+        let mut order :Vec<usize> = (0..weights.len()).collect();
+        order.sort_by(|a, b| remainders[*b].cmp(&remainders[*a]));
+
+        let allocated = shares.iter().fold(zero.clone(), |acc, s| dfp_add(acc, s.clone()));
+        let mut leftover = dfp_sub(target.clone(), allocated);
+        let unit = DFP { amount: vec![1], exp: exp, sign: target.sign.clone() };
+        for i in order {
+            if leftover == zero {
+                break;
+            }
+            shares[i] = dfp_add(shares[i].clone(), unit.clone());
+            leftover = dfp_sub(leftover, unit.clone());
+        }
+
+        Some(shares)
+    }
+
+    /* Given two DFP, divide the dividend by the divisor and return a new DFP as the quotient.
+
+    Division frequently produces results that cannot be exactly expressed using any quantity of digits,
+    such as 1 / 3.  Therefore the caller must specify the exponent of the result as well as a RoundingMode
+    that tells us what to do with the digits that don't fit.  For example, given an exp of -2, 1 / 3 = 0.33.
+
+    Division by zero is undefined so in that case return None.
+    */
+    pub fn dfp_div( dividend : DFP, divisor : DFP, exp : i8, mode : RoundingMode) -> Option<DFP> {
+
+        let den = md_stripz(&divisor.amount);
+        if divisor.sign == Sign::Zero || den.len() == 0 {
+            return None
+        }
+
+        let num = md_stripz(&dividend.amount);
+        if dividend.sign == Sign::Zero || num.len() == 0 {
+            return Some(DFP { amount: vec![], exp: 0, sign: Sign::Zero })
+        }
+
+        // 1. dividend / divisor = (num * 10^dividend.exp) / (den * 10^divisor.exp).  We want an integer
+        // quotient q such that q * 10^exp is our answer.  So shift the digits of num or den, as necessary,
+        // so that a plain integer division of num by den produces q directly.
+        let shift :i32 = dividend.exp as i32 - divisor.exp as i32 - exp as i32;
+        let (num, den) = if shift > 0 {
+            (md_shift(&num, shift as usize), den)
+        } else if shift < 0 {
+            (num, md_shift(&den, -shift as usize))
+        } else {
+            (num, den)
+        };
+
+        // 2. Now divide and examine the remainder in order to determine the correct rounding.
+        let sign = sign_product(&dividend.sign, &divisor.sign);
+        let (q, r) = md_divrem(&num, &den);
+        let half_cmp = md_compare(&md_add(r.clone(), r.clone()), &den);
+        let odd = q.len() > 0 && q[0] % 2 == 1;
+
+        let q = if round_away(&mode, &sign, odd, half_cmp, r.len() > 0) {
+            md_add(q, vec![1])
+        } else {
+            q
+        };
+
+        Some(dfp_norm(DFP { amount: q, exp: exp, sign: sign }))
+    }
+
+    /* Given two DFP, multiply them together and return a new DFP as the product.
+
+    The exponent of the product is the sum of the exponents of the factors.  If that doesn't fit in an i8 then the
+    product can't be expressed as a DFP, so return None.
+    */
+    pub fn dfp_mul( dfp1 : DFP, dfp2 : DFP) -> Option<DFP> {
+
+        let amount_zero = |dfp :&DFP| dfp.sign == Sign::Zero || md_stripz(&dfp.amount).len() == 0;
+        if amount_zero(&dfp1) || amount_zero(&dfp2) {
+            return Some(DFP { amount: vec![], exp: 0, sign: Sign::Zero })
+        }
+
+        let exp = dfp1.exp.checked_add(dfp2.exp)?;
+
+        // Try the fast path first. See dfp_add.
+        if let (Some(i1), Some(i2)) = (dfp_to_i128(&dfp1), dfp_to_i128(&dfp2)) {
+            if let Some(product) = i1.checked_mul(i2) {
+                return Some(dfp_from_i128(product, exp))
+            }
+        }
+
+        let amount = md_mul(&dfp1.amount, &dfp2.amount);
+        let sign = sign_product(&dfp1.sign, &dfp2.sign);
+
+        Some(dfp_norm(DFP { amount: amount, exp: exp, sign: sign }))
+    }
+
     /*
     Normalize a DFP. Given a DFP A, return a new DFP B that represents the same number such that the significand of DFP B has no LSD zeros.
 
     For example: [1], 3, [0,1], 2 and [0,0,1], 1 all represent 1000, but let's use the first choice as the normalized value.
+
+    Removing an LSD zero raises the exponent by one.  If the exponent is already i8::MAX then stop there, with the LSD zeros
+    that remain, rather than overflow.
     */
     fn dfp_norm( dfp : DFP) -> DFP {
         let ln = dfp.amount.len();
@@ -108,17 +544,24 @@ pub mod dfp {
                 dfp.clone()
             }
         } else { // ln > 1
-            if dfp.amount[0] == 0 {
-                let mut v1 = dfp.amount.clone();
-                let _ = v1.remove(0);
-                let new_dfp = DFP { amount: v1, exp: dfp.exp + 1, sign: dfp.sign };
-                dfp_norm( new_dfp)
-            } else {
-                dfp.clone()
+            match dfp.exp.checked_add(1) {
+                Some(exp) if dfp.amount[0] == 0 => {
+                    let mut v1 = dfp.amount.clone();
+                    let _ = v1.remove(0);
+                    let new_dfp = DFP { amount: v1, exp: exp, sign: dfp.sign };
+                    dfp_norm( new_dfp)
+                }
+                _ => dfp.clone()
             }
         }
     }
 
+    // Because DFP compare by value, tests that care about the exact representation must compare this instead.
+    #[cfg(test)]
+    fn repr( dfp : DFP) -> (Vec<u8>, i8, Sign) {
+        (dfp.amount, dfp.exp, dfp.sign)
+    }
+
     #[test]
     fn dfp_norm_test() {
 
@@ -127,15 +570,15 @@ pub mod dfp {
 
         input = DFP { amount: vec![], exp: 0, sign: Sign::Positive };
         output = DFP { amount: vec![], exp: 0, sign: Sign::Zero };
-        assert_eq!(dfp_norm(input), output);
+        assert_eq!(repr(dfp_norm(input)), repr(output));
 
         input = DFP { amount: vec![1], exp: 0, sign: Sign::Positive };
         output = DFP { amount: vec![1], exp: 0, sign: Sign::Positive };
-        assert_eq!(dfp_norm(input), output);
+        assert_eq!(repr(dfp_norm(input)), repr(output));
 
         input = DFP { amount: vec![0, 1], exp: 0, sign: Sign::Positive };
         output = DFP { amount: vec![1], exp: 1, sign: Sign::Positive };
-        assert_eq!(dfp_norm(input), output);
+        assert_eq!(repr(dfp_norm(input)), repr(output));
     }
 
     /*
@@ -181,28 +624,32 @@ pub mod dfp {
         input2 = DFP { amount: vec![1], exp: 0, sign: Sign::Negative };
         output1 = DFP { amount: vec![1], exp: 0, sign: Sign::Negative };
         output2 = DFP { amount: vec![1], exp: 0, sign: Sign::Negative };
-        assert_eq!((output1, output2),dfp_norm_exp(input1,input2));
+        let (n1, n2) = dfp_norm_exp(input1,input2);
+        assert_eq!((repr(output1), repr(output2)),(repr(n1), repr(n2)));
 
         // n1.exp > n2.exp
         input1 = DFP { amount: vec![1], exp: 1, sign: Sign::Positive };
         input2 = DFP { amount: vec![1], exp: 0, sign: Sign::Negative };
         output1 = DFP { amount: vec![0,1], exp: 0, sign: Sign::Positive };
         output2 = DFP { amount: vec![1], exp: 0, sign: Sign::Negative };
-        assert_eq!((output1, output2),dfp_norm_exp(input1,input2));
+        let (n1, n2) = dfp_norm_exp(input1,input2);
+        assert_eq!((repr(output1), repr(output2)),(repr(n1), repr(n2)));
 
         // n1.exp < n2.exp
         input1 = DFP { amount: vec![2], exp: 0, sign: Sign::Negative };
         input2 = DFP { amount: vec![1], exp: 1, sign: Sign::Positive };
         output1 = DFP { amount: vec![2], exp: 0, sign: Sign::Negative };
         output2 = DFP { amount: vec![0,1], exp: 0, sign: Sign::Positive };
-        assert_eq!((output1, output2),dfp_norm_exp(input1,input2));
+        let (n1, n2) = dfp_norm_exp(input1,input2);
+        assert_eq!((repr(output1), repr(output2)),(repr(n1), repr(n2)));
 
         // 1.2, 3
         input1 = DFP { amount: vec![2, 1], exp: -1, sign: Sign::Positive };
         input2 = DFP { amount: vec![3], exp: 0, sign: Sign::Positive };
         output1 = DFP { amount: vec![2, 1], exp: -1, sign: Sign::Positive };
         output2 = DFP { amount: vec![0, 3], exp: -1, sign: Sign::Positive };
-        assert_eq!((output1, output2),dfp_norm_exp(input1,input2));
+        let (n1, n2) = dfp_norm_exp(input1,input2);
+        assert_eq!((repr(output1), repr(output2)),(repr(n1), repr(n2)));
     }
 
     pub fn dfp_from_string_exp(s :&String, exp :i8) -> DFP {
@@ -275,6 +722,278 @@ pub mod dfp {
 
     }
 
+    /* Given an i128 significand and an exponent, return the equivalent normalized DFP. */
+    fn dfp_from_i128( n :i128, exp :i8) -> DFP {
+
+        if n == 0 {
+            return DFP { amount: vec![], exp: 0, sign: Sign::Zero }
+        }
+
+        let sign = if n < 0 { Sign::Negative } else { Sign::Positive };
+
+        // Go via u128 so that even i128::MIN has a magnitude.
+        let mut m :u128 = if n < 0 { (n as u128).wrapping_neg() } else { n as u128 };
+        let mut amount :Vec<u8> = Vec::new();
+        while m > 0 {
+            amount.push((m % 10) as u8);
+            m = m / 10;
+        }
+
+        dfp_norm(DFP { amount: amount, exp: exp, sign: sign })
+    }
+
+    /* Given a DFP, return its significand as an i128, or None if it won't fit.
+
+    Any DFP with no more than 38 digits will fit.  We also refuse any DFP with MSD zeros.  The digit-by-digit
+    arithmetic preserves them and we want the fast path to produce exactly the same results.
+    */
+    fn dfp_to_i128( dfp :&DFP) -> Option<i128> {
+
+        if dfp.sign == Sign::Zero {
+            return Some(0)
+        }
+
+        let ln = dfp.amount.len();
+        if ln > 38 || (ln > 0 && dfp.amount[ln - 1] == 0) {
+            return None
+        }
+
+        let mut n :i128 = 0;
+        for i in (0..ln).rev() {
+            n = n * 10 + dfp.amount[i] as i128;
+        }
+
+        if dfp.sign == Sign::Negative {
+            Some(-n)
+        } else {
+            Some(n)
+        }
+    }
+
+    #[test]
+    fn dfp_i128_test() {
+
+        let mut input;
+
+        input = DFP { amount: vec![], exp: 0, sign: Sign::Zero };
+        assert_eq!(dfp_to_i128(&input), Some(0));
+        assert_eq!(repr(dfp_from_i128(0, 5)), repr(input));
+
+        input = DFP { amount: vec![3, 2, 1], exp: -2, sign: Sign::Negative };
+        assert_eq!(dfp_to_i128(&input), Some(-123));
+        assert_eq!(repr(dfp_from_i128(-123, -2)), repr(input));
+
+        // from normalizes
+        input = DFP { amount: vec![2, 1], exp: 2, sign: Sign::Positive };
+        assert_eq!(repr(dfp_from_i128(1200, 0)), repr(input));
+
+        // MSD zeros and too many digits
+        input = DFP { amount: vec![1, 0], exp: 0, sign: Sign::Positive };
+        assert_eq!(dfp_to_i128(&input), None);
+        input = DFP { amount: vec![9; 39], exp: 0, sign: Sign::Positive };
+        assert_eq!(dfp_to_i128(&input), None);
+        input = DFP { amount: vec![9; 38], exp: 0, sign: Sign::Positive };
+        assert_eq!(dfp_to_i128(&input), Some(99999999999999999999999999999999999999));
+    }
+
+    /* The fast path of dfp_add must produce exactly the same representation as the digit-by-digit path,
+    including when it overflows and falls back to the digit-by-digit path.
+    */
+    #[test]
+    fn dfp_add_fast_test() {
+
+        let samples = vec![
+            DFP { amount: vec![], exp: 0, sign: Sign::Zero },
+            DFP { amount: vec![1], exp: 0, sign: Sign::Positive },
+            DFP { amount: vec![1], exp: 0, sign: Sign::Negative },
+            DFP { amount: vec![5], exp: 2, sign: Sign::Positive },
+            DFP { amount: vec![2, 1], exp: -1, sign: Sign::Negative },
+            DFP { amount: vec![9, 4], exp: 1, sign: Sign::Positive },
+            DFP { amount: vec![2, 6, 7, 8, 4, 0, 8, 0, 8, 1, 0, 2], exp: -8, sign: Sign::Positive },
+            DFP { amount: vec![9; 38], exp: 0, sign: Sign::Positive },
+            DFP { amount: vec![9; 38], exp: -5, sign: Sign::Negative },
+            DFP { amount: vec![7; 45], exp: 3, sign: Sign::Positive },
+            DFP { amount: vec![1], exp: 120, sign: Sign::Positive },
+            DFP { amount: vec![1], exp: -120, sign: Sign::Negative },
+        ];
+
+        for a in &samples {
+            for b in &samples {
+                assert_eq!(repr(dfp_add(a.clone(), b.clone())), repr(dfp_add_md(a.clone(), b.clone())));
+            }
+        }
+    }
+
+    /* Given a DFP, round it to the given exponent using the given RoundingMode and return a new, normalized, DFP.
+
+    For example, rounding 2.345 to exp -2 yields 2.34 using HalfEven but 2.35 using HalfUp.
+
+    If the DFP can already be expressed using the given exponent, such as 2.3 to exp -2, then there's nothing
+    to round and we merely return the original value, normalized.
+    */
+    pub fn dfp_round( dfp : DFP, exp : i8, mode : RoundingMode) -> DFP {
+
+        let n = dfp_norm(dfp);
+        if n.sign == Sign::Zero || n.exp >= exp {
+            return n
+        }
+
+        // 1. Split the digits into the digits to discard (the LSD) and the digits to keep (the MSD).
+        let k = (exp as i32 - n.exp as i32) as usize;
+        let ln = n.amount.len();
+        let (discard, keep) = if k >= ln {
+            (md_stripz(&n.amount), vec![])
+        } else {
+            (md_stripz(&n.amount[..k].to_vec()), n.amount[k..].to_vec())
+        };
+
+        // 2. Compare the discarded digits to one half of the last digit that we keep, 5 followed by k-1 zeros.
+        let half_cmp = md_compare(&discard, &md_shift(&vec![5], k - 1));
+        let odd = keep.len() > 0 && keep[0] % 2 == 1;
+
+        let keep = if round_away(&mode, &n.sign, odd, half_cmp, discard.len() > 0) {
+            md_add(keep, vec![1])
+        } else {
+            keep
+        };
+
+        dfp_norm(DFP { amount: keep, exp: exp, sign: n.sign })
+    }
+
+    /* Given a &DFP, return a json Value that represents it in the requested DFPFormat. */
+    pub fn dfp_to_json( dfp :&DFP, format :&DFPFormat) -> Value {
+        match format {
+            DFPFormat::Digits => json!(dfp),
+            DFPFormat::Decimal => json!(dfp.to_string()),
+            DFPFormat::Significand => {
+                let (significand, exp) = dfp_to_string_exp(dfp);
+                json!({"significand": significand, "exp": exp})
+            }
+        }
+    }
+
+    /* A value that contains DFP, and that can express all of them in a requested DFPFormat.  A response that contains
+    DFP implements this by serializing itself as usual and then replacing each of its DFP fields with the formatted
+    version.  Please see crate::format.
+    */
+    pub trait FormattedJson {
+        fn formatted(&self, format :&DFPFormat) -> Value;
+    }
+
+    impl FormattedJson for DFP {
+        fn formatted(&self, format :&DFPFormat) -> Value {
+            dfp_to_json(self, format)
+        }
+    }
+
+    impl<T: FormattedJson> FormattedJson for Option<T> {
+        fn formatted(&self, format :&DFPFormat) -> Value {
+            match self {
+                None => Value::Null,
+                Some(t) => t.formatted(format)
+            }
+        }
+    }
+
+    impl<T: FormattedJson> FormattedJson for Vec<T> {
+        fn formatted(&self, format :&DFPFormat) -> Value {
+            Value::Array(self.iter().map(|t| t.formatted(format)).collect())
+        }
+    }
+
+    /* Given a DFP, return a String of its significand, with a leading '-' if negative, as well as its exponent.  This is
+    the inverse of dfp_from_string_exp and is what we store in the db as a distribution's amountbt and amount_exp.
+    */
+    pub fn dfp_to_string_exp( dfp :&DFP) -> (String, i8) {
+
+        let n = dfp_norm(dfp.clone());
+        let digits :String = md_stripz(&n.amount).iter().rev().map(|d| (b'0' + d) as char).collect();
+        if n.sign == Sign::Zero || digits.len() == 0 {
+            return (String::from("0"), 0)
+        }
+
+        if n.sign == Sign::Negative {
+            (format!("-{}", digits), n.exp)
+        } else {
+            (digits, n.exp)
+        }
+    }
+
+    /* Given two DFP, subtract the second from the first and return a new DFP as the difference. */
+    pub fn dfp_sub( dfp1 : DFP, dfp2 : DFP) -> DFP {
+
+        // a - b == a + (-b)
+        let mut neg = dfp2.clone();
+        neg.sign = match dfp2.sign {
+            Sign::Positive => Sign::Negative,
+            Sign::Negative => Sign::Positive,
+            Sign::Zero => Sign::Zero
+        };
+        dfp_add( dfp1, neg)
+    }
+
+    /* Given a RoundingMode, the sign of the value to round, whether or not the last retained digit is odd,
+    how the discarded digits compare to one half of the last retained digit (-1, 0, 1, as per md_compare),
+    and whether or not any non-zero digits were discarded at all, determine whether or not the magnitude of the
+    retained digits should be incremented by one.
+    */
+    fn round_away( mode :&RoundingMode, sign :&Sign, odd :bool, half_cmp :i8, inexact :bool) -> bool {
+        if !inexact {
+            return false
+        }
+
+        match mode {
+            RoundingMode::HalfEven => half_cmp > 0 || (half_cmp == 0 && odd),
+            RoundingMode::HalfUp => half_cmp >= 0,
+            RoundingMode::Floor => *sign == Sign::Negative,
+            RoundingMode::Ceiling => *sign == Sign::Positive,
+            RoundingMode::Truncate => false
+        }
+    }
+
+    #[test]
+    fn round_away_test() {
+
+        // Nothing discarded, never round.
+        assert_eq!(round_away(&RoundingMode::HalfUp, &Sign::Positive, true, 1, false), false);
+        assert_eq!(round_away(&RoundingMode::Ceiling, &Sign::Positive, true, 1, false), false);
+
+        // Less than half.
+        assert_eq!(round_away(&RoundingMode::HalfEven, &Sign::Positive, true, -1, true), false);
+        assert_eq!(round_away(&RoundingMode::HalfUp, &Sign::Positive, true, -1, true), false);
+
+        // Exactly half.
+        assert_eq!(round_away(&RoundingMode::HalfEven, &Sign::Positive, false, 0, true), false);
+        assert_eq!(round_away(&RoundingMode::HalfEven, &Sign::Positive, true, 0, true), true);
+        assert_eq!(round_away(&RoundingMode::HalfUp, &Sign::Negative, false, 0, true), true);
+
+        // More than half.
+        assert_eq!(round_away(&RoundingMode::HalfEven, &Sign::Negative, false, 1, true), true);
+        assert_eq!(round_away(&RoundingMode::Truncate, &Sign::Negative, false, 1, true), false);
+
+        // Directed rounding depends only upon the sign.
+        assert_eq!(round_away(&RoundingMode::Floor, &Sign::Positive, false, 1, true), false);
+        assert_eq!(round_away(&RoundingMode::Floor, &Sign::Negative, false, -1, true), true);
+        assert_eq!(round_away(&RoundingMode::Ceiling, &Sign::Positive, false, -1, true), true);
+        assert_eq!(round_away(&RoundingMode::Ceiling, &Sign::Negative, false, 1, true), false);
+    }
+
+    // Given the signs of two DFP, return the sign of their product or quotient.
+    fn sign_product( s1 :&Sign, s2 :&Sign) -> Sign {
+        match (s1, s2) {
+            (Sign::Zero, _) => Sign::Zero,
+            (_, Sign::Zero) => Sign::Zero,
+            (Sign::Positive, Sign::Positive) => Sign::Positive,
+            (Sign::Negative, Sign::Negative) => Sign::Positive,
+            _ => Sign::Negative
+        }
+    }
+
+    // Given an i128 significand n with exponent from, return the equivalent significand using the lower exponent to, or None if it won't fit.
+    fn i128_rescale( n :i128, from :i8, to :i8) -> Option<i128> {
+        10i128.checked_pow((from as i32 - to as i32) as u32).and_then(|m| n.checked_mul(m))
+    }
+
     // Given two integers of a given base, and a prior carry (or zero if none) calculate their sum modulo base and return (sum, carry)
     fn dnc( i1 :u8, i2 :u8, carry :u8, base :u8) -> (u8, u8) {
         let sm = i1 + i2 + carry;
@@ -473,6 +1192,158 @@ pub mod dfp {
 
     }
 
+    /* Given two Vec<u8> num and den, perform a multi-digit long division of num by den and return (quotient, remainder).
+
+    den must not be zero and must not contain any MSD zeros because md_compare decides which number is larger by
+    looking at the lengths first.  Neither the quotient nor the remainder will contain any MSD zeros.
+    */
+    fn md_divrem( num :&Vec<u8>, den :&Vec<u8>) -> (Vec<u8>, Vec<u8>) {
+
+        let mut q :Vec<u8> = Vec::new(); // The quotient, built MSD first.
+        let mut r :Vec<u8> = Vec::new();
+
+        // Bring down the digits of num, one at a time, starting with the MSD.
+        for i in (0..num.len()).rev() {
+            r.insert(0, num[i]);
+            r = md_stripz(&r);
+
+            // How many times does den fit into the current remainder?  This will never be more than 9.
+            let mut digit :u8 = 0;
+            loop {
+                let cmp = md_compare(&r, den);
+                if cmp < 0 {
+                    break;
+                } else if cmp == 0 {
+                    // md_sub does not deal with top == bottom, so deal with it here.
+                    r = vec![];
+                    digit = digit + 1;
+                    break;
+                } else {
+                    r = md_stripz(&md_sub(&r, den));
+                    digit = digit + 1;
+                }
+            }
+            q.push(digit);
+        }
+
+        q.reverse();
+        (md_stripz(&q), r)
+    }
+
+    #[test]
+    fn md_divrem_test() {
+
+        let mut input1;
+        let mut input2;
+
+        input1 = vec![];
+        input2 = vec![3];
+        assert_eq!( md_divrem( &input1, &input2 ), (vec![], vec![]));
+
+        input1 = vec![2];
+        input2 = vec![3];
+        assert_eq!( md_divrem( &input1, &input2 ), (vec![], vec![2]));
+
+        input1 = vec![3];
+        input2 = vec![3];
+        assert_eq!( md_divrem( &input1, &input2 ), (vec![1], vec![]));
+
+        input1 = vec![0, 1];
+        input2 = vec![3];
+        assert_eq!( md_divrem( &input1, &input2 ), (vec![3], vec![1]));
+
+        input1 = vec![0, 0, 1];
+        input2 = vec![0, 1];
+        assert_eq!( md_divrem( &input1, &input2 ), (vec![0, 1], vec![]));
+
+        input1 = vec![4, 4, 1];
+        input2 = vec![2, 1];
+        assert_eq!( md_divrem( &input1, &input2 ), (vec![2, 1], vec![]));
+
+        input1 = vec![9, 9, 9, 9];
+        input2 = vec![3, 2, 1];
+        assert_eq!( md_divrem( &input1, &input2 ), (vec![1, 8], vec![6, 3]));
+    }
+
+    /* Given two Vec<u8> perform a multi-digit multiplication of them and return
+    the resulting Vec<u8> product.  The product will not contain any MSD zeros.
+    */
+    fn md_mul( v1 :&Vec<u8>, v2 :&Vec<u8>) -> Vec<u8> {
+
+        if v1.len() == 0 || v2.len() == 0 {
+            return vec![]
+        }
+
+        // 1. Accumulate the products of each pair of digits in the correct position without worrying
+        // about carries. A u32 is large enough to hold any plausible quantity of these.
+        let mut acc :Vec<u32> = vec![0; v1.len() + v2.len()];
+        for i in 0..v1.len() {
+            for j in 0..v2.len() {
+                acc[i + j] = acc[i + j] + v1[i] as u32 * v2[j] as u32;
+            }
+        }
+
+        // 2. Now propagate the carries.
+        let mut ret_val :Vec<u8> = Vec::new();
+        let mut carry :u32 = 0;
+        for a in acc {
+            let sm = a + carry;
+            ret_val.push((sm % 10) as u8);
+            carry = sm / 10;
+        }
+        while carry > 0 {
+            ret_val.push((carry % 10) as u8);
+            carry = carry / 10;
+        }
+
+        md_stripz(&ret_val)
+    }
+
+    #[test]
+    fn md_mul_test() {
+
+        let mut input1;
+        let mut input2;
+        let mut output :Vec<u8>;
+
+        input1 = vec![];
+        input2 = vec![1];
+        output = vec![];
+        assert_eq!( md_mul( &input1, &input2), output);
+
+        input1 = vec![0];
+        input2 = vec![1];
+        output = vec![];
+        assert_eq!( md_mul( &input1, &input2), output);
+
+        input1 = vec![3];
+        input2 = vec![2];
+        output = vec![6];
+        assert_eq!( md_mul( &input1, &input2), output);
+
+        input1 = vec![9];
+        input2 = vec![9];
+        output = vec![1, 8];
+        assert_eq!( md_mul( &input1, &input2), output);
+
+        input1 = vec![9, 9, 9];
+        input2 = vec![9, 9];
+        output = vec![1, 0, 9, 8, 9];
+        assert_eq!( md_mul( &input1, &input2), output);
+
+        input1 = vec![5, 2, 1];
+        input2 = vec![0, 1];
+        output = vec![0, 5, 2, 1];
+        assert_eq!( md_mul( &input1, &input2), output);
+    }
+
+    // Given a Vec<u8>, multiply it by 10^n by inserting n zeros as LSD.
+    fn md_shift( v :&Vec<u8>, n :usize) -> Vec<u8> {
+        let mut ret_val :Vec<u8> = vec![0; n];
+        ret_val.extend_from_slice(v);
+        ret_val
+    }
+
     // Given a Vec<u8>, remove all msd zeros, if any.
     fn md_stripz( v :&Vec<u8>) -> Vec<u8> {
 
@@ -566,7 +1437,7 @@ pub mod dfp {
     */
     fn md_sub1(top :Vec<u8>, bottom :Vec<u8>) -> Box<Vec<u8>> {
 
-        let n1 = *md_sub2(bottom, top.len() as u8);
+        let n1 = *md_sub2(bottom, top.len());
         let mut n2 = md_add(top,n1);
         let l2 = (*n2).len();
 
@@ -583,16 +1454,16 @@ pub mod dfp {
     This function will create a new Vec to hold the answer.  We will return said Vec in
     a Box.
      */
-    fn md_sub2( bottom :Vec<u8>, len :u8) -> Box<Vec<u8>> {
+    fn md_sub2( bottom :Vec<u8>, len :usize) -> Box<Vec<u8>> {
 
         let mut ret_val :Vec<u8> = Vec::new();
-        let lnb :u8 = bottom.len() as u8;
+        let lnb :usize = bottom.len();
         if len == 0 {
             Box::new(ret_val)
         } else {
             for i in 0..len {
                 if i < lnb {
-                    ret_val.push(9 - bottom[i as usize]);
+                    ret_val.push(9 - bottom[i]);
                 } else {
                     ret_val.push(9 );
                 }
@@ -669,6 +1540,13 @@ pub mod dfp {
         output = vec![9, 8, 4];
         assert_eq!( *md_sub(&input1, &input2), output);
 
+        // More digits than fit in a u8.  10^300 - 1
+        input1 = vec![0; 300];
+        input1.push(1);
+        input2 = vec![1];
+        output = vec![9; 300];
+        assert_eq!( *md_sub(&input1, &input2), output);
+
     }
 
 }
\ No newline at end of file
@@ -0,0 +1,154 @@
+use crate::db::{
+    AccountRegister, AccountRegisterEntry, AcctSum, ApiResponseOld, BalanceResultDecorated,
+    BalanceSeries, BalanceSeriesAccount, BalanceSeriesPoint, Report, ReportCurrency, ReportSection,
+    TrialBalance, TrialBalanceAccount, TrialBalanceCurrency,
+};
+use crate::dfp::dfp::{DFPFormat, FormattedJson};
+use rocket::http::{RawStr, Status};
+use rocket_contrib::json;
+use serde_json::Value;
+
+/*
+Several routes accept an optional format param of digits, decimal, or significand, that says how to express the DFP in the response.  Please see crate::dfp::dfp::DFPFormat.
+
+DFPFormat itself knows nothing about query params or responses, so we parse the param here.  We also say here how to express each response that contains DFP, by replacing its DFP fields, and only those, with their formatted versions.
+ */
+
+// Parse the optional format param into a DFPFormat, defaulting to Digits, or explain to the caller why we can't.
+pub fn parse_format(format: Option<&RawStr>) -> Result<DFPFormat, ApiResponseOld> {
+    match format {
+        None => Ok(DFPFormat::Digits),
+        Some(f) => f
+            .html_escape()
+            .to_mut()
+            .parse()
+            .map_err(|e: String| ApiResponseOld {
+                json: json!({ "error": e }),
+                status: Status::Ok,
+            }),
+    }
+}
+
+// Serialize the given response as usual, then replace each of the named fields with its formatted version.
+fn with_formatted<T: serde::Serialize>(t: &T, fields: Vec<(&str, Value)>) -> Value {
+    let mut v = serde_json::to_value(t).unwrap();
+    for (name, formatted) in fields {
+        v[name] = formatted;
+    }
+    v
+}
+
+impl FormattedJson for AcctSum {
+    fn formatted(&self, format: &DFPFormat) -> Value {
+        with_formatted(self, vec![("sum", self.sum.formatted(format))])
+    }
+}
+
+impl FormattedJson for BalanceResultDecorated {
+    fn formatted(&self, format: &DFPFormat) -> Value {
+        with_formatted(self, vec![("sum", self.sum.formatted(format))])
+    }
+}
+
+impl FormattedJson for TrialBalance {
+    fn formatted(&self, format: &DFPFormat) -> Value {
+        with_formatted(
+            self,
+            vec![("currencies", self.currencies.formatted(format))],
+        )
+    }
+}
+
+impl FormattedJson for TrialBalanceCurrency {
+    fn formatted(&self, format: &DFPFormat) -> Value {
+        with_formatted(
+            self,
+            vec![
+                ("accounts", self.accounts.formatted(format)),
+                ("total", self.total.formatted(format)),
+            ],
+        )
+    }
+}
+
+impl FormattedJson for TrialBalanceAccount {
+    fn formatted(&self, format: &DFPFormat) -> Value {
+        with_formatted(self, vec![("sum", self.sum.formatted(format))])
+    }
+}
+
+impl FormattedJson for AccountRegister {
+    fn formatted(&self, format: &DFPFormat) -> Value {
+        with_formatted(
+            self,
+            vec![
+                ("opening_balance", self.opening_balance.formatted(format)),
+                ("entries", self.entries.formatted(format)),
+                ("closing_balance", self.closing_balance.formatted(format)),
+            ],
+        )
+    }
+}
+
+impl FormattedJson for AccountRegisterEntry {
+    fn formatted(&self, format: &DFPFormat) -> Value {
+        with_formatted(
+            self,
+            vec![
+                ("amount", self.amount.formatted(format)),
+                ("balance", self.balance.formatted(format)),
+            ],
+        )
+    }
+}
+
+impl FormattedJson for BalanceSeries {
+    fn formatted(&self, format: &DFPFormat) -> Value {
+        with_formatted(self, vec![("series", self.series.formatted(format))])
+    }
+}
+
+impl FormattedJson for BalanceSeriesAccount {
+    fn formatted(&self, format: &DFPFormat) -> Value {
+        with_formatted(self, vec![("points", self.points.formatted(format))])
+    }
+}
+
+impl FormattedJson for BalanceSeriesPoint {
+    fn formatted(&self, format: &DFPFormat) -> Value {
+        with_formatted(self, vec![("sum", self.sum.formatted(format))])
+    }
+}
+
+impl FormattedJson for Report {
+    fn formatted(&self, format: &DFPFormat) -> Value {
+        with_formatted(
+            self,
+            vec![("currencies", self.currencies.formatted(format))],
+        )
+    }
+}
+
+impl FormattedJson for ReportCurrency {
+    fn formatted(&self, format: &DFPFormat) -> Value {
+        with_formatted(
+            self,
+            vec![
+                ("sections", self.sections.formatted(format)),
+                ("total", self.total.formatted(format)),
+            ],
+        )
+    }
+}
+
+impl FormattedJson for ReportSection {
+    fn formatted(&self, format: &DFPFormat) -> Value {
+        with_formatted(
+            self,
+            vec![
+                ("accounts", self.accounts.formatted(format)),
+                ("subtotal", self.subtotal.formatted(format)),
+            ],
+        )
+    }
+}
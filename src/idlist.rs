@@ -0,0 +1,50 @@
+use crate::db::ApiResponseOld;
+use rocket::http::{RawStr, Status};
+use rocket::request::FromFormValue;
+use rocket_contrib::json;
+
+/*
+Several routes accept a comma delimited list of id as a query param, such as category_id=1,2,3.  An IdList parses such a list, and every id must be a u32.  The db will never see a list that doesn't parse.
+
+Receive an IdList as Result<IdList, String>, or as Option<Result<IdList, String>> if it's optional, so that the route can explain a malformed list to the caller.  Please see bad_id_list.
+
+Even though every id is an integer, we never place them into a query as-is.  Please bind them using id_placeholders.
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct IdList(pub Vec<u32>);
+
+impl<'v> FromFormValue<'v> for IdList {
+    type Error = String;
+
+    fn from_form_value(form_value: &'v RawStr) -> Result<Self, Self::Error> {
+        let s = form_value.percent_decode().map_err(|e| e.to_string())?;
+        s.split(',')
+            .map(|id| id.trim().parse::<u32>())
+            .collect::<Result<Vec<u32>, _>>()
+            .map(IdList)
+            .map_err(|_| format!("{} is not a comma delimited list of id.", s))
+    }
+}
+
+/* Given a list of id, build a list of placeholders such as ":name_0, :name_1", suitable for an IN clause, and push the id onto the given param stack.
+
+WARNING! The params must be pushed in the same order that they are used in the query, so call this at the right time.
+ */
+pub fn id_placeholders(name: &str, ids: &[u32], params: &mut Vec<String>) -> String {
+    ids.iter()
+        .enumerate()
+        .map(|(i, id)| {
+            params.push(id.to_string());
+            format!(":{}_{}", name, i)
+        })
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+// Explain to the caller that the named param is not a proper IdList.
+pub fn bad_id_list(name: &str, e: String) -> ApiResponseOld {
+    ApiResponseOld {
+        json: json!({ "error": format!("{} {}", name, e) }),
+        status: Status::BadRequest,
+    }
+}
@@ -0,0 +1,351 @@
+use crate::clauses::time_clause;
+use crate::db::{Price, UnbalancedTransaction};
+use crate::dfp::dfp::{DFP, DFPFormat, FormattedJson, Sign, dfp_add, dfp_from_string_exp, dfp_mul};
+use crate::idlist::id_placeholders;
+use rocket::http::RawStr;
+use rocket_contrib::databases::mysql;
+use rocket_contrib::databases::mysql::prelude::GenericConnection;
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap};
+
+/*
+Double-entry bookkeeping requires that the distributions of each transaction sum to zero.  But it's meaningless to add Quatloos to Gold, so what we really need is that the distributions of each transaction sum to zero _within each currency_.
+
+An apikey can opt-in to strict mode.  If so, we reject any POST or PUT that would leave a transaction unbalanced.  Whether or not an apikey is strict, we can always ask which transactions are currently unbalanced.
+
+These functions work with anything that can execute a query, such as an ordinary connection or a db transaction.  This way we can check the balance of a transaction _after_ a write, but _before_ the commit.
+ */
+
+/* Sum the distributions of each account that has any, optionally filtered by time as per time_clause.  Accounts that have no distributions are absent from the result, so the caller should treat them as zero.
+ */
+pub fn account_sums<C: GenericConnection>(
+    conn: &mut C,
+    apikey: &String,
+    time_start: Option<&RawStr>,
+    time_stop: Option<&RawStr>,
+) -> Result<HashMap<u32, DFP>, mysql::Error> {
+    // WARNING! Push these in the same order they are used in the prep_exec function!
+    let mut params = Vec::new();
+    params.push(apikey.clone());
+    let time_clause = time_clause(time_start, time_stop, &mut params);
+
+    let rows: Vec<(u32, String, i8)> = conn
+        .prep_exec(
+            format!(
+                "
+                SELECT ds.account_id, ds.amountbt, ds.amount_exp
+                FROM distributions AS ds
+                JOIN transactions AS tx ON tx.id = ds.transaction_id
+                WHERE ds.apikey = :apikey
+                {}
+                ",
+                time_clause
+            ),
+            params,
+        )?
+        .map(|x| x.unwrap())
+        .map(|row| rocket_contrib::databases::mysql::from_row(row))
+        .collect();
+
+    let mut sums: HashMap<u32, DFP> = HashMap::new();
+    for (account_id, amountbt, amount_exp) in rows {
+        let sum = sums
+            .entry(account_id)
+            .or_insert(DFP { amount: vec![], exp: 0, sign: Sign::Zero });
+        *sum = dfp_add(sum.clone(), dfp_from_string_exp(&amountbt, amount_exp));
+    }
+
+    Ok(sums)
+}
+
+/* Find the most recent price of currency_id, in terms of base_currency_id, as of the given time.  That is, the price with the greatest time that is <= the given time.  If there are several such prices, the most recently created one wins.  If there is no given time, find the most recent price of all.
+ */
+pub fn latest_price<C: GenericConnection>(
+    conn: &mut C,
+    apikey: &String,
+    currency_id: &String,
+    base_currency_id: &String,
+    time: Option<&String>,
+) -> Result<Option<Price>, mysql::Error> {
+    // WARNING! Push these in the same order they are used in the prep_exec function!
+    let mut params = vec![apikey.clone(), currency_id.clone(), base_currency_id.clone()];
+    let time_clause = match time {
+        None => "",
+        Some(time) => {
+            params.push(time.clone());
+            "AND time <= :time"
+        }
+    };
+
+    let vec: Vec<Price> = conn
+        .prep_exec(
+            format!(
+                "
+                SELECT id, apikey, currency_id, base_currency_id, time, rate, rate_exp
+                FROM prices
+                WHERE apikey = :apikey
+                AND currency_id = :currency_id
+                AND base_currency_id = :base_currency_id
+                {}
+                ORDER BY time DESC, id DESC
+                LIMIT 1
+                ",
+                time_clause
+            ),
+            params,
+        )?
+        .map(|x| x.unwrap())
+        .map(|row| {
+            let (id, apikey, currency_id, base_currency_id, time, rate, rate_exp) =
+                rocket_contrib::databases::mysql::from_row(row);
+            Price {
+                id,
+                apikey,
+                currency_id,
+                base_currency_id,
+                time,
+                rate,
+                rate_exp,
+            }
+        })
+        .collect();
+
+    Ok(vec.get(0).cloned())
+}
+
+/* Convert sums into a reporting currency, using the most recent price as of a valuation time, or the most recent price of all if there is no valuation time.  A sum that's already in the reporting currency is unchanged.  We only use prices of the sum's currency in terms of the reporting currency, so if there is no such price, we can't convert the sum.
+
+Many sums will share a currency, so remember the rates that we find.
+ */
+pub struct Converter {
+    pub convert_to: u32,
+    pub valuation_time: Option<String>,
+    rates: HashMap<u32, Option<DFP>>, // currency_id -> rate
+}
+
+impl Converter {
+    pub fn new(convert_to: u32, valuation_time: Option<String>) -> Converter {
+        Converter {
+            convert_to,
+            valuation_time,
+            rates: HashMap::new(),
+        }
+    }
+
+    /* Build a Converter from the convert_to and valuation_time params, if the caller wants one.  The valuation time defaults to time_stop, because that's the time of a balance.
+     */
+    pub fn from_params(
+        convert_to: Option<&RawStr>,
+        valuation_time: Option<&RawStr>,
+        time_stop: Option<&RawStr>,
+    ) -> Result<Option<Converter>, String> {
+        let convert_to: u32 = match convert_to {
+            None => return Ok(None),
+            Some(c) => c
+                .html_escape()
+                .to_mut()
+                .parse()
+                .map_err(|_| String::from("convert_to must be a currency_id."))?,
+        };
+        let valuation_time = valuation_time
+            .or(time_stop)
+            .map(|t| t.html_escape().to_mut().clone());
+        Ok(Some(Converter::new(convert_to, valuation_time)))
+    }
+
+    // Express a converted sum, or the lack thereof, as json, using the given DFPFormat.
+    pub fn to_json(&self, converted: &Option<DFP>, format: &DFPFormat) -> Value {
+        json!({
+            "currency_id": self.convert_to,
+            "valuation_time": self.valuation_time,
+            "sum": converted.formatted(format),
+            "rate_missing": converted.is_none(),
+        })
+    }
+
+    // Convert the sum of the given currency_id, or return None if there is no rate available, or if the converted sum doesn't fit in a DFP.
+    pub fn convert<C: GenericConnection>(
+        &mut self,
+        conn: &mut C,
+        apikey: &String,
+        sum: &DFP,
+        currency_id: u32,
+    ) -> Result<Option<DFP>, mysql::Error> {
+        if currency_id == self.convert_to {
+            return Ok(Some(sum.clone()));
+        }
+
+        if !self.rates.contains_key(&currency_id) {
+            let rate = latest_price(
+                conn,
+                apikey,
+                &currency_id.to_string(),
+                &self.convert_to.to_string(),
+                self.valuation_time.as_ref(),
+            )?
+            .map(|price| dfp_from_string_exp(&price.rate, price.rate_exp));
+            self.rates.insert(currency_id, rate);
+        }
+
+        Ok(self.rates[&currency_id]
+            .as_ref()
+            .and_then(|rate| dfp_mul(sum.clone(), rate.clone())))
+    }
+}
+
+// Find the currency_id of each of the given accounts.
+pub fn account_currencies<C: GenericConnection>(
+    conn: &mut C,
+    apikey: &String,
+    account_ids: &Vec<u32>,
+) -> Result<HashMap<u32, u32>, mysql::Error> {
+    if account_ids.len() == 0 {
+        return Ok(HashMap::new());
+    }
+
+    // WARNING! Push these in the same order they are used in the prep_exec function!
+    let mut params = vec![apikey.clone()];
+    let rows: Vec<(u32, u32)> = conn
+        .prep_exec(
+            format!(
+                "SELECT id, currency_id FROM accounts WHERE apikey = :apikey AND id IN ({})",
+                id_placeholders("account_id", account_ids, &mut params)
+            ),
+            params,
+        )?
+        .map(|x| x.unwrap())
+        .map(|row| rocket_contrib::databases::mysql::from_row(row))
+        .collect();
+
+    Ok(rows.into_iter().collect())
+}
+
+// Does this apikey want us to enforce balanced transactions?  An apikey that doesn't exist isn't strict.
+pub fn is_strict<C: GenericConnection>(conn: &mut C, apikey: &String) -> Result<bool, mysql::Error> {
+    let vec: Vec<bool> = conn
+        .prep_exec(
+            "SELECT strict FROM apikeys WHERE apikey = :apikey",
+            vec![apikey.clone()],
+        )?
+        .map(|x| x.unwrap())
+        .map(|row| rocket_contrib::databases::mysql::from_row(row))
+        .collect();
+
+    Ok(vec.get(0) == Some(&true))
+}
+
+/* The distributions of a transaction, summed within each currency.  Also count the distributions because a transaction that has fewer than two of them is suspicious, even if it's balanced.
+ */
+pub struct Tally {
+    pub distribution_cnt: u32,
+    pub sums: BTreeMap<u32, DFP>, // currency_id -> sum
+}
+
+/* Tally the distributions of each transaction that has any.  Given an optional list of transaction_id, only examine those transactions.  Else examine all of them.
+
+We use BTreeMaps to keep the results in a predictable order.
+ */
+pub fn tally<C: GenericConnection>(
+    conn: &mut C,
+    apikey: &String,
+    transaction_ids: Option<&Vec<u32>>,
+) -> Result<BTreeMap<u32, Tally>, mysql::Error> {
+    // The apikey comes first, then the transaction_id, in the order that they appear in the query.
+    let mut params: Vec<String> = vec![apikey.clone()];
+    let transaction_clause = match transaction_ids {
+        None => String::from(""),
+        Some(v) if v.len() == 0 => return Ok(BTreeMap::new()),
+        Some(v) => format!(
+            "AND ds.transaction_id IN ({})",
+            id_placeholders("transaction_id", v, &mut params)
+        ),
+    };
+
+    let rows: Vec<(u32, u32, String, i8)> = conn
+        .prep_exec(
+            format!(
+                "
+                SELECT ds.transaction_id, ac.currency_id, ds.amountbt, ds.amount_exp
+                FROM distributions AS ds
+                JOIN accounts AS ac ON ac.id = ds.account_id AND ac.apikey = ds.apikey
+                WHERE ds.apikey = :apikey
+                {}
+                ",
+                transaction_clause
+            ),
+            params,
+        )?
+        .map(|x| x.unwrap())
+        .map(|row| rocket_contrib::databases::mysql::from_row(row))
+        .collect();
+
+    let mut tallies: BTreeMap<u32, Tally> = BTreeMap::new();
+    for (transaction_id, currency_id, amountbt, amount_exp) in rows {
+        let t = tallies.entry(transaction_id).or_insert(Tally {
+            distribution_cnt: 0,
+            sums: BTreeMap::new(),
+        });
+        t.distribution_cnt = t.distribution_cnt + 1;
+        let sum = t
+            .sums
+            .entry(currency_id)
+            .or_insert(DFP { amount: vec![], exp: 0, sign: Sign::Zero });
+        *sum = dfp_add(sum.clone(), dfp_from_string_exp(&amountbt, amount_exp));
+    }
+
+    Ok(tallies)
+}
+
+// Is this sum zero?
+pub fn is_zero(sum: &DFP) -> bool {
+    *sum == DFP { amount: vec![], exp: 0, sign: Sign::Zero }
+}
+
+/* Find the sum of the distributions of each transaction, within each currency, and return the sums that are not zero.
+
+Given an optional list of transaction_id, only examine those transactions.  Else examine all of them.
+ */
+pub fn unbalanced<C: GenericConnection>(
+    conn: &mut C,
+    apikey: &String,
+    transaction_ids: Option<&Vec<u32>>,
+) -> Result<Vec<UnbalancedTransaction>, mysql::Error> {
+    let mut ret_val = Vec::new();
+    for (transaction_id, t) in tally(conn, apikey, transaction_ids)? {
+        for (currency_id, sum) in t.sums {
+            if !is_zero(&sum) {
+                ret_val.push(UnbalancedTransaction {
+                    transaction_id,
+                    currency_id,
+                    sum,
+                });
+            }
+        }
+    }
+    Ok(ret_val)
+}
+
+/* If the apikey is strict, ensure that the given transactions are balanced.  Call this after a write, but before the commit, and rollback if this returns an error.
+ */
+pub fn enforce_balance<C: GenericConnection>(
+    conn: &mut C,
+    apikey: &String,
+    transaction_ids: &Vec<u32>,
+) -> Result<(), String> {
+    match is_strict(conn, apikey) {
+        Ok(false) => return Ok(()),
+        Ok(true) => {}
+        Err(err) => return Err(err.to_string()),
+    }
+
+    match unbalanced(conn, apikey, Some(transaction_ids)) {
+        Ok(v) => match v.get(0) {
+            None => Ok(()),
+            Some(u) => Err(format!(
+                "transaction {} is unbalanced in currency {} by {}.",
+                u.transaction_id, u.currency_id, u.sum
+            )),
+        },
+        Err(err) => Err(err.to_string()),
+    }
+}
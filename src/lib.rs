@@ -1,14 +1,21 @@
 #![feature(decl_macro)]
 
+pub mod calendar;
+pub mod category_filter;
+pub mod clauses;
 pub mod constants;
 pub mod db;
 pub mod dfp;
+pub mod format;
+pub mod idlist;
+pub mod ledger;
+pub mod linter;
 pub mod routz;
 pub mod sql;
 
 pub mod routes {
 
-    use crate::db::{MyRocketSQLConn, Ping, PostApikeysResponse, Semver};
+    use crate::db::{APIResponse, ApikeyStrict, MyRocketSQLConn, Ping, PostApikeysResponse, Semver};
     use rocket::http::ContentType;
     use rocket::request::Request;
     use rocket::response;
@@ -54,4 +61,22 @@ pub mod routes {
             Err(err) => Json(PostApikeysResponse::Error(err.to_string())),
         }
     }
+
+    /* Opt-in, or out, of strict mode.  If strict, we reject any POST or PUT that would leave a transaction unbalanced within a currency.  Please see crate::ledger.
+     */
+    #[rocket::put("/apikeys", data = "<apikey>")]
+    pub fn put_apikey(
+        apikey: rocket::request::Form<ApikeyStrict>,
+        mut conn: MyRocketSQLConn,
+    ) -> Json<APIResponse> {
+        match conn.prep_exec(
+            "UPDATE apikeys SET strict = :strict WHERE apikey = :apikey",
+            (&apikey.strict, &apikey.apikey),
+        ) {
+            Ok(result) => Json(APIResponse::Info(
+                String::from_utf8_lossy(&result.info()).to_string(),
+            )),
+            Err(err) => Json(APIResponse::Error(err.to_string())),
+        }
+    }
 }
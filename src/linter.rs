@@ -0,0 +1,463 @@
+use crate::db::{CurrencySum, Finding, LinterLong, LinterRule, LinterShort, LinterTransaction, Mutation, MutationAction, Severity};
+use crate::ledger::{Tally, tally, unbalanced};
+use rocket_contrib::databases::mysql;
+use rocket_contrib::databases::mysql::prelude::GenericConnection;
+use std::collections::BTreeMap;
+
+/*
+A linter rule looks for something suspicious in the books of a single apikey.  Each rule has a unique id, a severity, and a description.  All of the rules are listed in the registry, and GET /linter runs all of them, or a selected subset, and returns their findings.
+
+In order to add a new check, implement Linter and add it to the registry.  No new routes are needed.
+
+Some rules are fixable.  Given their findings, they can produce a list of mutations that will fix them.  Each mutation names an action, a table, and the id of a record.  POST /linter/fix will either merely return these mutations, as a dry-run, or will apply them.  Only apply builds any SQL, and only for the tables that the rules may change.
+
+A sealed transaction must not change, or its seal won't verify anymore.  So no rule may produce a mutation of a sealed transaction.  Please see crate::routz::seal.
+
+The original linter routes, such as GET /linter/accounts, predate this and return their own bare lists.  They use the same queries as the rules.
+ */
+pub trait Linter<C: GenericConnection> {
+    fn id(&self) -> &'static str;
+    fn severity(&self) -> Severity;
+    fn description(&self) -> &'static str;
+    fn run(&self, conn: &mut C, apikey: &String) -> Result<Vec<Finding>, mysql::Error>;
+
+    // Most rules are not fixable.
+    fn fixable(&self) -> bool {
+        false
+    }
+
+    // Given the findings of this rule, return the mutations that will fix them.
+    fn fix(&self, _conn: &mut C, _apikey: &String, _findings: &Vec<Finding>) -> Result<Vec<Mutation>, mysql::Error> {
+        Ok(Vec::new())
+    }
+
+    // Describe this rule, without running it.
+    fn rule(&self) -> LinterRule {
+        LinterRule {
+            id: String::from(self.id()),
+            severity: self.severity(),
+            description: String::from(self.description()),
+            fixable: self.fixable(),
+        }
+    }
+
+    // Build a Mutation for this rule that deletes a single record.
+    fn delete(&self, table: &str, id: u32) -> Mutation {
+        Mutation {
+            rule: String::from(self.id()),
+            action: MutationAction::Delete,
+            table: String::from(table),
+            id,
+        }
+    }
+
+    // Build a Finding for this rule.
+    fn finding(&self, table: &str, id: u32, message: String, sums: Vec<CurrencySum>) -> Finding {
+        Finding {
+            rule: String::from(self.id()),
+            severity: self.severity(),
+            table: String::from(table),
+            id,
+            message,
+            sums,
+        }
+    }
+}
+
+// All of the rules that we know about, in the order that we run them.
+pub fn registry<C: GenericConnection>() -> Vec<Box<dyn Linter<C>>> {
+    vec![
+        Box::new(UnusedAccounts),
+        Box::new(UnusedCategories),
+        Box::new(UnusedCurrencies),
+        Box::new(EmptyTransactions),
+        Box::new(SingleDistributionTransactions),
+        Box::new(UnbalancedTransactions),
+    ]
+}
+
+/* Given a comma delimited list of rule ids, return the selected rules from the registry, in registry order.  If there is no list, return all of them.  If any of the ids are unknown, return an error.
+ */
+pub fn select<C: GenericConnection>(rules: Option<String>) -> Result<Vec<Box<dyn Linter<C>>>, String> {
+    match rules {
+        None => Ok(registry()),
+        Some(rules) => {
+            let ids: Vec<&str> = rules.split(',').map(|id| id.trim()).collect();
+            let registry = registry::<C>();
+            for id in &ids {
+                if !registry.iter().any(|r| r.id() == *id) {
+                    return Err(format!("{} is not a linter rule.", id));
+                }
+            }
+            Ok(registry.into_iter().filter(|r| ids.contains(&r.id())).collect())
+        }
+    }
+}
+
+/* Apply a single mutation.  The table of a mutation comes from a rule, but we build SQL from it, so make sure that it's a table that the rules may change.
+ */
+pub fn apply<C: GenericConnection>(conn: &mut C, apikey: &String, m: &Mutation) -> Result<(), String> {
+    match m.table.as_str() {
+        "accounts" | "categories" | "currencies" | "transactions" | "transactions_categories" => {}
+        table => return Err(format!("{} is not a table that a linter rule may change.", table)),
+    }
+
+    match m.action {
+        MutationAction::Delete => conn
+            .prep_exec(
+                format!("DELETE FROM {} WHERE id = :id AND apikey = :apikey", m.table),
+                vec![m.id.to_string(), apikey.clone()],
+            )
+            .map(|_| ())
+            .map_err(|err| err.to_string()),
+    }
+}
+
+// 1. The queries.  These are shared by the rules and by the original linter routes.
+
+// Find accounts that are not used by any distributions or categories.
+pub fn unused_accounts<C: GenericConnection>(conn: &mut C, apikey: &String) -> Result<Vec<LinterShort>, mysql::Error> {
+    Ok(conn
+        .prep_exec(
+            r#"
+            SELECT ac.id, ac.title
+            FROM accounts AS ac
+            LEFT JOIN distributions AS d on ac.id = d.account_id
+            LEFT JOIN accounts_categories AS acat on ac.id = acat.account_id
+            WHERE ac.apikey = :apikey AND d.account_id IS NULL AND acat.account_id IS NULL
+            "#,
+            vec![apikey.clone()],
+        )?
+        .map(|x| x.unwrap())
+        .map(|row| {
+            let (id, title) = rocket_contrib::databases::mysql::from_row(row);
+            LinterShort { id, title }
+        })
+        .collect())
+}
+
+// Find categories that are not used by any accounts.
+pub fn unused_categories<C: GenericConnection>(conn: &mut C, apikey: &String) -> Result<Vec<LinterLong>, mysql::Error> {
+    Ok(conn
+        .prep_exec(
+            r#"
+            SELECT c.id, c.symbol, c.title
+            FROM categories AS c
+            LEFT JOIN accounts_categories AS ac
+            ON c.id = ac.category_id
+            WHERE c.apikey = :apikey AND ac.category_id IS NULL
+            "#,
+            vec![apikey.clone()],
+        )?
+        .map(|x| x.unwrap())
+        .map(|row| {
+            let (id, symbol, title) = rocket_contrib::databases::mysql::from_row(row);
+            LinterLong { id, symbol, title }
+        })
+        .collect())
+}
+
+// Find currencies that are not used by any accounts or prices.
+pub fn unused_currencies<C: GenericConnection>(conn: &mut C, apikey: &String) -> Result<Vec<LinterLong>, mysql::Error> {
+    Ok(conn
+        .prep_exec(
+            r#"
+            SELECT c.id, c.symbol, c.title
+            FROM currencies AS c
+            LEFT JOIN accounts AS ac
+            ON c.id = ac.currency_id
+            WHERE c.apikey = :apikey AND ac.currency_id IS NULL
+            AND NOT EXISTS (
+                SELECT 1 FROM prices AS pr
+                WHERE pr.apikey = c.apikey
+                AND (pr.currency_id = c.id OR pr.base_currency_id = c.id)
+            )
+            "#,
+            vec![apikey.clone()],
+        )?
+        .map(|x| x.unwrap())
+        .map(|row| {
+            let (id, symbol, title) = rocket_contrib::databases::mysql::from_row(row);
+            LinterLong { id, symbol, title }
+        })
+        .collect())
+}
+
+/* Find suspicious transactions.  Given a filter that examines the Tally of a transaction, if any, and decides whether it's suspicious, and which of its sums to return, return the suspicious transactions.
+ */
+pub fn suspicious_transactions<C: GenericConnection>(
+    conn: &mut C,
+    apikey: &String,
+    filter: fn(Option<&Tally>) -> Option<Vec<CurrencySum>>,
+) -> Result<Vec<LinterTransaction>, mysql::Error> {
+    let tallies: BTreeMap<u32, Tally> = tally(conn, apikey, None)?;
+
+    let mut ret_val: Vec<LinterTransaction> = Vec::new();
+    for (id, notes, time) in transactions(conn, apikey)? {
+        if let Some(sums) = filter(tallies.get(&id)) {
+            ret_val.push(LinterTransaction {
+                id,
+                notes,
+                time,
+                sums,
+            });
+        }
+    }
+    Ok(ret_val)
+}
+
+/* Find unbalanced transactions, as per crate::ledger::unbalanced.  Return each of them with only its offending sums.
+ */
+pub fn unbalanced_transactions<C: GenericConnection>(conn: &mut C, apikey: &String) -> Result<Vec<LinterTransaction>, mysql::Error> {
+    let mut sums: BTreeMap<u32, Vec<CurrencySum>> = BTreeMap::new();
+    for u in unbalanced(conn, apikey, None)? {
+        sums.entry(u.transaction_id).or_insert_with(Vec::new).push(CurrencySum {
+            currency_id: u.currency_id,
+            sum: u.sum,
+        });
+    }
+
+    Ok(transactions(conn, apikey)?
+        .into_iter()
+        .filter_map(|(id, notes, time)| {
+            sums.remove(&id).map(|sums| LinterTransaction {
+                id,
+                notes,
+                time,
+                sums,
+            })
+        })
+        .collect())
+}
+
+// Find the id, notes, and time of every transaction of an apikey.
+fn transactions<C: GenericConnection>(conn: &mut C, apikey: &String) -> Result<Vec<(u32, String, String)>, mysql::Error> {
+    Ok(conn
+        .prep_exec(
+            "SELECT id, notes, time FROM transactions WHERE apikey = :apikey ORDER BY id",
+            vec![apikey.clone()],
+        )?
+        .map(|x| x.unwrap())
+        .map(|row| rocket_contrib::databases::mysql::from_row(row))
+        .collect())
+}
+
+// Given a Tally, return its sums in the form of a Vec.
+fn to_vec(t: &Tally) -> Vec<CurrencySum> {
+    t.sums
+        .iter()
+        .map(|(currency_id, sum)| CurrencySum {
+            currency_id: *currency_id,
+            sum: sum.clone(),
+        })
+        .collect()
+}
+
+// A transaction that has no distributions.
+pub fn empty(t: Option<&Tally>) -> Option<Vec<CurrencySum>> {
+    match t {
+        None => Some(Vec::new()),
+        Some(_) => None,
+    }
+}
+
+// A transaction that has exactly one distribution.
+pub fn single(t: Option<&Tally>) -> Option<Vec<CurrencySum>> {
+    match t {
+        Some(t) if t.distribution_cnt == 1 => Some(to_vec(t)),
+        _ => None,
+    }
+}
+
+// 2. The rules.
+
+pub struct UnusedAccounts;
+
+impl<C: GenericConnection> Linter<C> for UnusedAccounts {
+    fn id(&self) -> &'static str {
+        "unused_accounts"
+    }
+    fn severity(&self) -> Severity {
+        Severity::Info
+    }
+    fn description(&self) -> &'static str {
+        "Accounts that are not used by any distributions or categories."
+    }
+    fn run(&self, conn: &mut C, apikey: &String) -> Result<Vec<Finding>, mysql::Error> {
+        Ok(unused_accounts(conn, apikey)?
+            .into_iter()
+            .map(|a| self.finding("accounts", a.id, format!("The account '{}' is not used.", a.title), vec![]))
+            .collect())
+    }
+    fn fixable(&self) -> bool {
+        true
+    }
+    // An unused account is not referenced by anything so we can simply delete it.
+    fn fix(&self, _conn: &mut C, _apikey: &String, findings: &Vec<Finding>) -> Result<Vec<Mutation>, mysql::Error> {
+        Ok(findings.iter().map(|f| self.delete("accounts", f.id)).collect())
+    }
+}
+
+pub struct UnusedCategories;
+
+impl<C: GenericConnection> Linter<C> for UnusedCategories {
+    fn id(&self) -> &'static str {
+        "unused_categories"
+    }
+    fn severity(&self) -> Severity {
+        Severity::Info
+    }
+    fn description(&self) -> &'static str {
+        "Categories that do not tag any accounts."
+    }
+    fn run(&self, conn: &mut C, apikey: &String) -> Result<Vec<Finding>, mysql::Error> {
+        Ok(unused_categories(conn, apikey)?
+            .into_iter()
+            .map(|c| self.finding("categories", c.id, format!("The category '{} {}' is not used.", c.symbol, c.title), vec![]))
+            .collect())
+    }
+    fn fixable(&self) -> bool {
+        true
+    }
+    // A category that doesn't tag any accounts might still tag transactions, or be the parent of another category.  Only delete it if it's truly an orphan.
+    fn fix(&self, conn: &mut C, apikey: &String, findings: &Vec<Finding>) -> Result<Vec<Mutation>, mysql::Error> {
+        let busy: Vec<u32> = conn
+            .prep_exec(
+                r#"
+                SELECT category_id FROM transactions_categories WHERE apikey = :apikey
+                UNION
+                SELECT parent_id FROM categories WHERE apikey = :apikey AND parent_id IS NOT NULL
+                "#,
+                vec![apikey.clone(), apikey.clone()],
+            )?
+            .map(|x| x.unwrap())
+            .map(|row| rocket_contrib::databases::mysql::from_row(row))
+            .collect();
+
+        Ok(findings
+            .iter()
+            .filter(|f| !busy.contains(&f.id))
+            .map(|f| self.delete("categories", f.id))
+            .collect())
+    }
+}
+
+pub struct UnusedCurrencies;
+
+impl<C: GenericConnection> Linter<C> for UnusedCurrencies {
+    fn id(&self) -> &'static str {
+        "unused_currencies"
+    }
+    fn severity(&self) -> Severity {
+        Severity::Info
+    }
+    fn description(&self) -> &'static str {
+        "Currencies that are not used by any accounts or prices."
+    }
+    fn run(&self, conn: &mut C, apikey: &String) -> Result<Vec<Finding>, mysql::Error> {
+        Ok(unused_currencies(conn, apikey)?
+            .into_iter()
+            .map(|c| self.finding("currencies", c.id, format!("The currency '{} {}' is not used.", c.symbol, c.title), vec![]))
+            .collect())
+    }
+    fn fixable(&self) -> bool {
+        true
+    }
+    // An unused currency is not referenced by anything so we can simply delete it.
+    fn fix(&self, _conn: &mut C, _apikey: &String, findings: &Vec<Finding>) -> Result<Vec<Mutation>, mysql::Error> {
+        Ok(findings.iter().map(|f| self.delete("currencies", f.id)).collect())
+    }
+}
+
+pub struct EmptyTransactions;
+
+impl<C: GenericConnection> Linter<C> for EmptyTransactions {
+    fn id(&self) -> &'static str {
+        "empty_transactions"
+    }
+    fn severity(&self) -> Severity {
+        Severity::Warning
+    }
+    fn description(&self) -> &'static str {
+        "Transactions that have no distributions."
+    }
+    fn run(&self, conn: &mut C, apikey: &String) -> Result<Vec<Finding>, mysql::Error> {
+        Ok(suspicious_transactions(conn, apikey, empty)?
+            .into_iter()
+            .map(|t| self.finding("transactions", t.id, format!("The transaction '{}' at {} has no distributions.", t.notes, t.time), t.sums))
+            .collect())
+    }
+    fn fixable(&self) -> bool {
+        true
+    }
+    // An empty transaction has no distributions, but it might still be tagged with categories.  Delete those tags first.  Leave sealed transactions alone.
+    fn fix(&self, conn: &mut C, apikey: &String, findings: &Vec<Finding>) -> Result<Vec<Mutation>, mysql::Error> {
+        let sealed: Vec<u32> = conn
+            .prep_exec(
+                "SELECT transaction_id FROM seals WHERE apikey = :apikey",
+                vec![apikey.clone()],
+            )?
+            .map(|x| x.unwrap())
+            .map(|row| rocket_contrib::databases::mysql::from_row(row))
+            .collect();
+
+        let trancats: Vec<(u32, u32)> = conn
+            .prep_exec(
+                "SELECT id, transaction_id FROM transactions_categories WHERE apikey = :apikey ORDER BY id",
+                vec![apikey.clone()],
+            )?
+            .map(|x| x.unwrap())
+            .map(|row| rocket_contrib::databases::mysql::from_row(row))
+            .collect();
+
+        let mut ret_val = Vec::new();
+        for f in findings.iter().filter(|f| !sealed.contains(&f.id)) {
+            for (id, _) in trancats.iter().filter(|(_, transaction_id)| *transaction_id == f.id) {
+                ret_val.push(self.delete("transactions_categories", *id));
+            }
+            ret_val.push(self.delete("transactions", f.id));
+        }
+        Ok(ret_val)
+    }
+}
+
+pub struct SingleDistributionTransactions;
+
+impl<C: GenericConnection> Linter<C> for SingleDistributionTransactions {
+    fn id(&self) -> &'static str {
+        "single_distribution_transactions"
+    }
+    fn severity(&self) -> Severity {
+        Severity::Warning
+    }
+    fn description(&self) -> &'static str {
+        "Transactions that have a single distribution."
+    }
+    fn run(&self, conn: &mut C, apikey: &String) -> Result<Vec<Finding>, mysql::Error> {
+        Ok(suspicious_transactions(conn, apikey, single)?
+            .into_iter()
+            .map(|t| self.finding("transactions", t.id, format!("The transaction '{}' at {} has a single distribution.", t.notes, t.time), t.sums))
+            .collect())
+    }
+}
+
+pub struct UnbalancedTransactions;
+
+impl<C: GenericConnection> Linter<C> for UnbalancedTransactions {
+    fn id(&self) -> &'static str {
+        "unbalanced_transactions"
+    }
+    fn severity(&self) -> Severity {
+        Severity::Error
+    }
+    fn description(&self) -> &'static str {
+        "Transactions whose distributions do not sum to zero within each currency."
+    }
+    // Only return the offending sums, as per crate::ledger::unbalanced.
+    fn run(&self, conn: &mut C, apikey: &String) -> Result<Vec<Finding>, mysql::Error> {
+        Ok(unbalanced_transactions(conn, apikey)?
+            .into_iter()
+            .map(|t| self.finding("transactions", t.id, format!("The transaction '{}' at {} is unbalanced.", t.notes, t.time), t.sums))
+            .collect())
+    }
+}
@@ -4,10 +4,20 @@ pub mod category;
 pub mod currency;
 pub mod distribution;
 pub mod get_account_dist_sum;
+pub mod get_account_register;
+pub mod get_allocation;
+pub mod get_balance_series;
 pub mod get_category_dist_sums;
+pub mod get_linter;
 pub mod get_linter_accounts;
 pub mod get_linter_categories;
 pub mod get_linter_currencies;
+pub mod get_linter_transactions;
+pub mod get_reports;
+pub mod get_trial_balance;
+pub mod post_linter_fix;
+pub mod price;
+pub mod seal;
 pub mod sql;
 pub mod trancat;
 pub mod transaction;
@@ -1,6 +1,50 @@
-use crate::db::{APIResponse, Category, CategoryShort, GetCategoryResponse, MyRocketSQLConn};
-use rocket::http::RawStr;
+use crate::category_filter::category_parents;
+use crate::db::{APIResponse, Category, CategoryNode, CategoryShort, CategoryUpdate, GetCategoryResponse, MyRocketSQLConn, ParentId};
+use rocket::http::{RawStr, Status};
+use rocket_contrib::databases::mysql::prelude::GenericConnection;
+use rocket_contrib::json;
 use rocket_contrib::json::Json;
+use std::collections::HashSet;
+
+/* Categories form a hierarchy.  The parent of a category must be another category of the same apikey, and a category must not be its own ancestor.  Given the id of the category being written, if it exists yet, and its proposed parent_id, walk up the chain of parents looking for trouble.
+
+Lock the categories of the apikey while we do this.  Call this inside the same db transaction as the write, so that nobody else can change the hierarchy between this check and the write.
+ */
+fn check_parent<C: GenericConnection>(
+    conn: &mut C,
+    apikey: &String,
+    id: Option<u32>,
+    parent_id: Option<u32>,
+) -> Result<(), String> {
+    let parent_id = match parent_id {
+        None => return Ok(()),
+        Some(parent_id) => parent_id,
+    };
+
+    conn.prep_exec(
+        "SELECT id FROM categories WHERE apikey = :apikey FOR UPDATE",
+        vec![apikey.clone()],
+    )
+    .map_err(|err| err.to_string())?;
+
+    let parents = category_parents(conn, apikey).map_err(|err| err.to_string())?;
+    if !parents.contains_key(&parent_id) {
+        return Err(format!("parent_id {} is not a category.", parent_id));
+    }
+
+    let mut visited = HashSet::new();
+    let mut ancestor = Some(parent_id);
+    while let Some(a) = ancestor {
+        if Some(a) == id {
+            return Err(String::from("A category cannot be its own ancestor."));
+        }
+        if !visited.insert(a) {
+            break; // This is a cycle that somebody made directly in the db.  It doesn't include this category, so it's not our problem now.
+        }
+        ancestor = parents.get(&a).cloned().flatten();
+    }
+    Ok(())
+}
 
 #[rocket::delete("/category/<id>?<apikey>")]
 pub fn delete_category(
@@ -39,20 +83,21 @@ pub fn get_category(
 
     let vec: Vec<Category> = conn
         .prep_exec(
-            "SELECT id, apikey, symbol, title from categories where id = :id and apikey = :apikey",
+            "SELECT id, apikey, symbol, title, parent_id from categories where id = :id and apikey = :apikey",
             params,
         )
         .map(|result| {
             result
                 .map(|x| x.unwrap())
                 .map(|row| {
-                    let (id, apikey, symbol, title) =
+                    let (id, apikey, symbol, title, parent_id) =
                         rocket_contrib::databases::mysql::from_row(row);
                     Category {
                         id,
                         apikey,
                         symbol,
                         title,
+                        parent_id,
                     }
                 })
                 .collect()
@@ -81,11 +126,11 @@ pub fn get_category_bysym(
     params.push(apikey.html_escape().to_mut().clone());
 
     let vec: Vec<Category> =
-        conn.prep_exec("SELECT id, apikey, symbol, title from categories where symbol = :symbol and apikey = :apikey", params)
+        conn.prep_exec("SELECT id, apikey, symbol, title, parent_id from categories where symbol = :symbol and apikey = :apikey", params)
             .map(|result| {
                 result.map(|x| x.unwrap()).map(|row| {
-                    let (id, apikey, symbol, title) = rocket_contrib::databases::mysql::from_row(row);
-                    Category {id, apikey, symbol, title}
+                    let (id, apikey, symbol, title, parent_id) = rocket_contrib::databases::mysql::from_row(row);
+                    Category {id, apikey, symbol, title, parent_id}
                 }).collect()
             }).unwrap();
 
@@ -106,20 +151,21 @@ pub fn get_categories(apikey: &RawStr, mut conn: MyRocketSQLConn) -> Json<GetCat
 
     let vec: Vec<Category> = conn
         .prep_exec(
-            "SELECT id, apikey, symbol, title from categories where apikey = :apikey",
+            "SELECT id, apikey, symbol, title, parent_id from categories where apikey = :apikey",
             params,
         )
         .map(|result| {
             result
                 .map(|x| x.unwrap())
                 .map(|row| {
-                    let (id, apikey, symbol, title) =
+                    let (id, apikey, symbol, title, parent_id) =
                         rocket_contrib::databases::mysql::from_row(row);
                     Category {
                         id,
                         apikey,
                         symbol,
                         title,
+                        parent_id,
                     }
                 })
                 .collect()
@@ -129,28 +175,122 @@ pub fn get_categories(apikey: &RawStr, mut conn: MyRocketSQLConn) -> Json<GetCat
     Json(GetCategoryResponse::Many(vec))
 }
 
+/* Produce the category hierarchy of an apikey as a forest.  The roots are the categories that have no parent.  Siblings are ordered by symbol.
+
+We reject cycles on write, but somebody might make one directly in the db.  The categories of a cycle have no root, so they are absent from the forest.
+ */
+#[rocket::get("/categories/tree?<apikey>")]
+pub fn get_categories_tree(apikey: &RawStr, mut conn: MyRocketSQLConn) -> crate::db::ApiResponseOld {
+    // Build the children of the given parent, and all of their descendants.
+    fn children(parent_id: Option<u32>, categories: &[Category]) -> Vec<CategoryNode> {
+        categories
+            .iter()
+            .filter(|c| c.parent_id == parent_id)
+            .map(|c| CategoryNode {
+                id: c.id,
+                symbol: c.symbol.clone(),
+                title: c.title.clone(),
+                children: children(Some(c.id), categories),
+            })
+            .collect()
+    }
+
+    // We receive apikey as &RawStr.  We must convert it into a form that the mysql parametrization can use.
+    let mut params = Vec::new();
+    params.push(apikey.html_escape().to_mut().clone());
+
+    match conn.prep_exec(
+        "SELECT id, apikey, symbol, title, parent_id from categories where apikey = :apikey order by symbol",
+        params,
+    ) {
+        Ok(result) => {
+            let categories: Vec<Category> = result
+                .map(|x| x.unwrap())
+                .map(|row| {
+                    let (id, apikey, symbol, title, parent_id) =
+                        rocket_contrib::databases::mysql::from_row(row);
+                    Category {
+                        id,
+                        apikey,
+                        symbol,
+                        title,
+                        parent_id,
+                    }
+                })
+                .collect();
+            crate::db::ApiResponseOld {
+                json: json!({ "tree": children(None, &categories) }),
+                status: Status::Ok,
+            }
+        }
+        Err(err) => crate::db::ApiResponseOld {
+            json: json!({"error": err.to_string()}),
+            status: Status::Ok,
+        },
+    }
+}
+
 #[rocket::post("/categories", data = "<category>")]
 pub fn post_category(
     category: rocket::request::Form<CategoryShort>,
     mut conn: MyRocketSQLConn,
 ) -> Json<APIResponse> {
+    if let Err(err) = check_parent(&mut *conn, &category.apikey, None, category.parent_id) {
+        return Json(APIResponse::Error(err));
+    }
+
     match conn.prep_exec(
-        "INSERT INTO categories (apikey, symbol, title) VALUES (:apikey, :symbol, :title)",
-        (&category.apikey, &category.symbol, &category.title),
+        "INSERT INTO categories (apikey, symbol, title, parent_id) VALUES (:apikey, :symbol, :title, :parent_id)",
+        (&category.apikey, &category.symbol, &category.title, &category.parent_id),
     ) {
         Ok(result) => Json(APIResponse::LastInsertId(result.last_insert_id())),
         Err(err) => Json(APIResponse::Error(String::from(err.to_string()))),
     }
 }
 
+/* Please see CategoryUpdate for the meaning of parent_id.  If it's given, check it and update it inside the same db transaction.  Please see check_parent.
+ */
 #[rocket::put("/categories", data = "<category>")]
 pub fn put_category(
-    category: rocket::request::Form<Category>,
+    category: rocket::request::Form<CategoryUpdate>,
     mut conn: MyRocketSQLConn,
 ) -> Json<APIResponse> {
-    match conn.prep_exec("UPDATE categories SET symbol = :symbol, title = :title where id = :id and apikey = :apikey",(
-        &category.symbol, &category.title, &category.id, &category.apikey)) {
-        Ok(result) => Json(APIResponse::Info(String::from_utf8_lossy(&result.info()).to_string())),
-        Err(err) => Json(APIResponse::Error(String::from(err.to_string()))),
+    let parent_id: Option<Option<u32>> = match &category.parent_id {
+        None => None,
+        Some(Ok(ParentId::Clear)) => Some(None),
+        Some(Ok(ParentId::Id(parent_id))) => Some(Some(*parent_id)),
+        Some(Err(err)) => return Json(APIResponse::Error(err.clone())),
+    };
+
+    let mut tx = match conn.start_transaction(false, None, None) {
+        Ok(tx) => tx,
+        Err(err) => return Json(APIResponse::Error(err.to_string())),
+    };
+
+    let result = match parent_id {
+        None => tx.prep_exec(
+            "UPDATE categories SET symbol = :symbol, title = :title where id = :id and apikey = :apikey",
+            (&category.symbol, &category.title, &category.id, &category.apikey),
+        ),
+        Some(parent_id) => {
+            if let Err(err) = check_parent(&mut tx, &category.apikey, Some(category.id), parent_id) {
+                let _ = tx.rollback();
+                return Json(APIResponse::Error(err));
+            }
+            tx.prep_exec(
+                "UPDATE categories SET symbol = :symbol, title = :title, parent_id = :parent_id where id = :id and apikey = :apikey",
+                (&category.symbol, &category.title, &parent_id, &category.id, &category.apikey),
+            )
+        }
+    };
+
+    let info = match result {
+        Ok(result) => String::from_utf8_lossy(&result.info()).to_string(),
+        Err(err) => return Json(APIResponse::Error(err.to_string())),
+    };
+
+    match tx.commit() {
+        Ok(_) => Json(APIResponse::Info(info)),
+        Err(err) => Json(APIResponse::Error(err.to_string())),
     }
 }
@@ -1,30 +1,92 @@
 use crate::db::{
-    APIResponse, Distribution, DistributionJoined, DistributionShort,
-    GetDistributionJoinedResponse, GetDistributionResponse, MyRocketSQLConn,
+    APIResponse, Apikey, Distribution, DistributionBackfill, DistributionJoined, DistributionShort,
+    GetDistributionJoinedResponse, GetDistributionResponse, MyRocketSQLConn, PostBackfillAmountsResponse,
 };
+use crate::ledger::enforce_balance;
+use lazy_static::lazy_static;
 use regex::Regex;
 use rocket::http::RawStr;
 use rocket_contrib::json::Json;
 
+/* A distribution stores its amount twice.  amountbt is the source of truth and can hold more digits than will fit into an i64.  amount is merely a convenient copy that is easy to use via /sql.  If amountbt fits into an i64 then amount must equal it, else amount must be 0.
+
+Given an amountbt, return the amount that must go with it, or an error if amountbt is not an integer.
+ */
+pub fn amount_from_amountbt(amountbt: &String) -> Result<i64, String> {
+    // Compile this only once, not once per distribution.
+    lazy_static! {
+        static ref INTEGER: Regex = Regex::new(r"^-?[0-9]+$").unwrap();
+    }
+    if !INTEGER.is_match(amountbt) {
+        return Err(String::from(
+            "amountbt contains one or more non-numeric characters.",
+        ));
+    }
+
+    // The regex guarantees that the only way this can fail is by overflow.
+    match amountbt.parse::<i64>() {
+        Ok(n) => Ok(n),
+        Err(_) => Ok(0),
+    }
+}
+
+// Given an amount and an amountbt, ensure that they agree.
+pub fn check_amounts(amount: i64, amountbt: &String) -> Result<(), String> {
+    let expected = amount_from_amountbt(amountbt)?;
+    if amount == expected {
+        Ok(())
+    } else {
+        Err(format!(
+            "amount {} does not agree with amountbt {}.  amount should be {}.",
+            amount, amountbt, expected
+        ))
+    }
+}
+
+/* Please see the comment for post_distribution.  Removing a distribution can unbalance its transaction, so do the delete inside a db transaction and check the balance before the commit.
+ */
 #[rocket::delete("/distribution/<id>?<apikey>")]
 pub fn delete_distribution(
     id: &RawStr,
     apikey: &RawStr,
     mut conn: MyRocketSQLConn,
 ) -> Json<APIResponse> {
-    let mut params = Vec::new();
-
     // We receive these arguments as &RawStr.  We must convert them into a form that the mysql parametrization can use.
-    params.push(id.html_escape().to_mut().clone());
-    params.push(apikey.html_escape().to_mut().clone());
+    let id = id.html_escape().to_mut().clone();
+    let apikey = apikey.html_escape().to_mut().clone();
+
+    let mut tx = match conn.start_transaction(false, None, None) {
+        Ok(tx) => tx,
+        Err(err) => return Json(APIResponse::Error(err.to_string())),
+    };
 
-    match conn.prep_exec(
+    // Which transaction does this distribution belong to?
+    let transaction_ids: Vec<u32> = match tx.prep_exec(
+        "SELECT transaction_id from distributions where id = :id and apikey = :apikey FOR UPDATE",
+        (&id, &apikey),
+    ) {
+        Ok(result) => result
+            .map(|x| x.unwrap())
+            .map(|row| rocket_contrib::databases::mysql::from_row(row))
+            .collect(),
+        Err(err) => return Json(APIResponse::Error(err.to_string())),
+    };
+
+    let info = match tx.prep_exec(
         "DELETE from distributions where id = :id and apikey = :apikey",
-        params,
+        (&id, &apikey),
     ) {
-        Ok(result) => Json(APIResponse::Info(
-            String::from_utf8_lossy(&result.info()).to_string(),
-        )),
+        Ok(result) => String::from_utf8_lossy(&result.info()).to_string(),
+        Err(err) => return Json(APIResponse::Error(err.to_string())),
+    };
+
+    if let Err(err) = enforce_balance(&mut tx, &apikey, &transaction_ids) {
+        let _ = tx.rollback();
+        return Json(APIResponse::Error(err));
+    }
+
+    match tx.commit() {
+        Ok(_) => Json(APIResponse::Info(info)),
         Err(err) => Json(APIResponse::Error(err.to_string())),
     }
 }
@@ -146,43 +208,163 @@ pub fn get_distributions_for_tx(
     get_distributions_private("SELECT d.id as did, t.id as tid, a.id as aid, amount, amountbt, amount_exp, d.apikey, title, time, notes from distributions as d join transactions as t on d.transaction_id = t.id join accounts as a on d.account_id = a.id where d.apikey = :apikey and transaction_id = :transaction_id order by time", params, conn)
 }
 
+// Find all distributions where amount does not agree with amountbt.  POST /distributions/backfill_amounts will fix them.
+#[rocket::get("/distributions/verify_amounts?<apikey>")]
+pub fn get_distributions_verify_amounts(
+    apikey: &RawStr,
+    mut conn: MyRocketSQLConn,
+) -> Json<GetDistributionResponse> {
+    // We receive apikey as &RawStr.  We must convert it into a form that the mysql parametrization can use.
+    let mut params = Vec::new();
+    params.push(apikey.html_escape().to_mut().clone());
+
+    let vec: Vec<Distribution> =
+        conn.prep_exec("SELECT id, account_id, amount, amountbt, amount_exp, apikey, transaction_id from distributions where apikey = :apikey", params)
+            .map(|result| {
+                result.map(|x| x.unwrap()).map(|row| {
+                    let (id, account_id, amount, amountbt, amount_exp, apikey, transaction_id) = rocket_contrib::databases::mysql::from_row(row);
+                    Distribution {id, account_id, amount, amountbt, amount_exp, apikey, transaction_id}
+                }).filter(|d: &Distribution| check_amounts(d.amount, &d.amountbt).is_err())
+                .collect()
+            }).unwrap();
+
+    Json(GetDistributionResponse::Many(vec))
+}
+
+/* Find and fix any distributions where amount does not agree with amountbt.  Such records predate the check in POST and PUT /distributions.  Do this in a single db transaction so that we either fix them all or none.
+
+A distribution whose amountbt is not an integer can't be fixed this way.  Don't let it stop us from fixing the others.  Instead, return its id so that the caller can deal with it.
+ */
+#[rocket::post("/distributions/backfill_amounts", data = "<apikey>")]
+pub fn post_distributions_backfill_amounts(
+    apikey: rocket::request::Form<Apikey>,
+    mut conn: MyRocketSQLConn,
+) -> Json<PostBackfillAmountsResponse> {
+    let mut tx = match conn.start_transaction(false, None, None) {
+        Ok(tx) => tx,
+        Err(err) => return Json(PostBackfillAmountsResponse::Error(err.to_string())),
+    };
+
+    let rows: Vec<(u32, i64, String)> = match tx.prep_exec(
+        "SELECT id, amount, amountbt from distributions where apikey = :apikey FOR UPDATE",
+        vec![apikey.apikey.clone()],
+    ) {
+        Ok(result) => result
+            .map(|x| x.unwrap())
+            .map(|row| rocket_contrib::databases::mysql::from_row(row))
+            .collect(),
+        Err(err) => return Json(PostBackfillAmountsResponse::Error(err.to_string())),
+    };
+
+    let mut backfill = DistributionBackfill {
+        updated: 0,
+        bad_amountbt: Vec::new(),
+    };
+    for (id, amount, amountbt) in rows {
+        let expected = match amount_from_amountbt(&amountbt) {
+            Ok(n) => n,
+            Err(_) => {
+                backfill.bad_amountbt.push(id);
+                continue;
+            }
+        };
+
+        if amount != expected {
+            if let Err(err) = tx.prep_exec(
+                "UPDATE distributions SET amount = :amount where id = :id and apikey = :apikey",
+                (&expected, &id, &apikey.apikey),
+            ) {
+                let _ = tx.rollback();
+                return Json(PostBackfillAmountsResponse::Error(err.to_string()));
+            }
+            backfill.updated = backfill.updated + 1;
+        }
+    }
+
+    match tx.commit() {
+        Ok(_) => Json(PostBackfillAmountsResponse::Backfill(backfill)),
+        Err(err) => Json(PostBackfillAmountsResponse::Error(err.to_string())),
+    }
+}
+
+/* If the apikey is strict, a distribution must not leave its transaction unbalanced.  So do the write inside a db transaction, check the balance, and only then commit.  Please see crate::ledger.
+
+Because of this, a balanced transaction can't be changed one distribution at a time in strict mode.  Please use PUT /transactions/compound, or DELETE /transaction, instead.
+ */
 #[rocket::post("/distributions", data = "<distribution>")]
 pub fn post_distribution(
     distribution: rocket::request::Form<DistributionShort>,
     mut conn: MyRocketSQLConn,
 ) -> Json<APIResponse> {
-    let re = Regex::new(r"^-?[0-9]+$").unwrap();
-    if re.is_match(&distribution.amountbt) {
-        match conn.prep_exec(
-            "INSERT INTO distributions (account_id, amount, amountbt, amount_exp, apikey, transaction_id) VALUES (:account_id, :amount, :amountbt, :amount_exp, :apikey, :transaction_id)",(&distribution.account_id, &distribution.amount, &distribution.amountbt, &distribution.amount_exp, &distribution.apikey, &distribution.transaction_id)) {
-            Ok(result) => Json(APIResponse::LastInsertId(result.last_insert_id())),
-            Err(err) => Json(APIResponse::Error(String::from(err.to_string())))
-        }
-    } else {
-        Json(APIResponse::Error(
-            String::from(
-                "amountbt contains one or more non-numeric characters.",
-            )
-        ))
+    if let Err(err) = check_amounts(distribution.amount, &distribution.amountbt) {
+        return Json(APIResponse::Error(err));
+    }
+
+    let mut tx = match conn.start_transaction(false, None, None) {
+        Ok(tx) => tx,
+        Err(err) => return Json(APIResponse::Error(err.to_string())),
+    };
+
+    let lid = match tx.prep_exec(
+        "INSERT INTO distributions (account_id, amount, amountbt, amount_exp, apikey, transaction_id) VALUES (:account_id, :amount, :amountbt, :amount_exp, :apikey, :transaction_id)",(&distribution.account_id, &distribution.amount, &distribution.amountbt, &distribution.amount_exp, &distribution.apikey, &distribution.transaction_id)) {
+        Ok(result) => result.last_insert_id(),
+        Err(err) => return Json(APIResponse::Error(String::from(err.to_string())))
+    };
+
+    if let Err(err) = enforce_balance(&mut tx, &distribution.apikey, &vec![distribution.transaction_id]) {
+        let _ = tx.rollback();
+        return Json(APIResponse::Error(err));
+    }
+
+    match tx.commit() {
+        Ok(_) => Json(APIResponse::LastInsertId(lid)),
+        Err(err) => Json(APIResponse::Error(err.to_string())),
     }
 }
 
+/* Please see the comment for post_distribution.  A PUT can move a distribution from one transaction to another, so we must check the balance of both of them.
+ */
 #[rocket::put("/distributions", data = "<distribution>")]
 pub fn put_distribution(
     distribution: rocket::request::Form<Distribution>,
     mut conn: MyRocketSQLConn,
 ) -> Json<APIResponse> {
-    let re = Regex::new(r"^-?[0-9]+$").unwrap();
-    if re.is_match(&distribution.amountbt) {
-        match conn.prep_exec("UPDATE distributions SET account_id = :account_id, amount = :amount, amountbt = :amountbt, amount_exp = :amount_exp, transaction_id = :transaction_id where id = :id and apikey = :apikey",(&distribution.account_id, &distribution.amount, &distribution.amountbt, &distribution.amount_exp, &distribution.transaction_id, &distribution.id, &distribution.apikey)) {
-            Ok(result) => Json(APIResponse::Info(String::from_utf8_lossy(&result.info()).to_string())),
-            Err(err) => Json(APIResponse::Error(String::from(err.to_string()))),
-        }
-    } else {
-        Json(APIResponse::Error(
-            String::from(
-                "amountbt contains one or more non-numeric characters.",
-            )
-        ))
+    if let Err(err) = check_amounts(distribution.amount, &distribution.amountbt) {
+        return Json(APIResponse::Error(err));
+    }
+
+    let mut tx = match conn.start_transaction(false, None, None) {
+        Ok(tx) => tx,
+        Err(err) => return Json(APIResponse::Error(err.to_string())),
+    };
+
+    // Which transaction does this distribution belong to now?
+    let mut transaction_ids: Vec<u32> = match tx.prep_exec(
+        "SELECT transaction_id from distributions where id = :id and apikey = :apikey FOR UPDATE",
+        (&distribution.id, &distribution.apikey),
+    ) {
+        Ok(result) => result
+            .map(|x| x.unwrap())
+            .map(|row| rocket_contrib::databases::mysql::from_row(row))
+            .collect(),
+        Err(err) => return Json(APIResponse::Error(err.to_string())),
+    };
+    if !transaction_ids.contains(&distribution.transaction_id) {
+        transaction_ids.push(distribution.transaction_id);
+    }
+
+    let info = match tx.prep_exec("UPDATE distributions SET account_id = :account_id, amount = :amount, amountbt = :amountbt, amount_exp = :amount_exp, transaction_id = :transaction_id where id = :id and apikey = :apikey",(&distribution.account_id, &distribution.amount, &distribution.amountbt, &distribution.amount_exp, &distribution.transaction_id, &distribution.id, &distribution.apikey)) {
+        Ok(result) => String::from_utf8_lossy(&result.info()).to_string(),
+        Err(err) => return Json(APIResponse::Error(String::from(err.to_string()))),
+    };
+
+    if let Err(err) = enforce_balance(&mut tx, &distribution.apikey, &transaction_ids) {
+        let _ = tx.rollback();
+        return Json(APIResponse::Error(err));
+    }
+
+    match tx.commit() {
+        Ok(_) => Json(APIResponse::Info(info)),
+        Err(err) => Json(APIResponse::Error(err.to_string())),
     }
 }
@@ -1,7 +1,13 @@
 //use crate::dfpx::DFPx;
-use crate::dfp::dfp::{DFP, Sign, dfp_add, dfp_from_string_exp};
+use crate::clauses::{time_clause, trancat_clause};
+use crate::db::AccountDistSumParams;
+use crate::dfp::dfp::{DFP, FormattedJson, Sign, dfp_add, dfp_from_string_exp};
+use crate::format::parse_format;
+use crate::idlist::bad_id_list;
+use crate::ledger::{Converter, account_currencies};
 use rocket::get;
 use rocket::http::{RawStr, Status};
+use rocket::request::LenientForm;
 use rocket_contrib::json;
 
 /*
@@ -22,46 +28,86 @@ Setting both time_* params gives us the change in balance during a time period a
  income statement items.
 
 Setting only time_start doesn't seem real useful, but I'm sure somebody can find a need for doing this.
+
+Given an optional format param of digits, decimal, or significand, express the sum using that DFPFormat. The default is digits.
+
+Given an optional convert_to param, which is a currency_id, also convert the sum into that currency, using the most recent price as of an optional valuation_time.  The valuation_time defaults to time_stop.  If there's no rate available, the converted sum is null and rate_missing is true.  Please see crate::ledger::Converter.
+
+Given an optional comma delimited list of trancat_id, which are category_id that tag transactions, as per /trancats, only examine the distributions of transactions tagged with any of those categories.  Given an optional comma delimited list of exclude_trancat_id, don't examine the distributions of transactions tagged with any of those categories.  Such as "expenses excluding internal transfers."
  */
-#[get("/account_dist_sum?<apikey>&<account_id>&<time_start>&<time_stop>")]
+#[get("/account_dist_sum?<apikey>&<account_id>&<options..>")]
 pub fn get_account_dist_sum(
     apikey: &RawStr,
     account_id: &RawStr,
-    time_start: Option<&RawStr>,
-    time_stop: Option<&RawStr>,
+    options: LenientForm<AccountDistSumParams>,
     mut conn: crate::db::MyRocketSQLConn,
 ) -> crate::db::ApiResponseOld {
+    let AccountDistSumParams {
+        time_start,
+        time_stop,
+        format,
+        convert_to,
+        valuation_time,
+        trancat_id,
+        exclude_trancat_id,
+    } = options.into_inner();
+
+    // Which account?
+    let account_id: u32 = match account_id.html_escape().to_mut().parse() {
+        Ok(id) => id,
+        Err(_) => {
+            return crate::db::ApiResponseOld {
+                json: json!({ "error": format!("account_id {} is not an id.", account_id) }),
+                status: Status::Ok,
+            }
+        }
+    };
+
+    // Which format shall we use for the sum?
+    let format = match parse_format(format) {
+        Ok(f) => f,
+        Err(e) => return e,
+    };
+
+    // Shall we convert the sum?
+    let mut converter = match Converter::from_params(convert_to, valuation_time, time_stop) {
+        Ok(c) => c,
+        Err(e) => {
+            return crate::db::ApiResponseOld {
+                json: json!({ "error": e }),
+                status: Status::Ok,
+            }
+        }
+    };
+
+    // Shall we filter by the categories that tag the transactions?
+    let trancat_id = match trancat_id.transpose() {
+        Ok(ids) => ids,
+        Err(e) => return bad_id_list("trancat_id", e),
+    };
+    let exclude_trancat_id = match exclude_trancat_id.transpose() {
+        Ok(ids) => ids,
+        Err(e) => return bad_id_list("exclude_trancat_id", e),
+    };
+
     // This is a vector of parameters that we recover from the request and feed into our sql statement
     let mut params = Vec::new();
     // We receive these arguments as &RawStr.  We must convert them into a form that the mysql parametrization can use.
     // WARNING! Push these in the same order they are used in the prep_exec function!
-    params.push(account_id.html_escape().to_mut().clone());
+    params.push(account_id.to_string());
     params.push(apikey.html_escape().to_mut().clone());
 
     // time_start and time_stop are both optional. This will affect what we push onto the param stack
     // as well as the actual sql statement
-    let mut time_clause = String::from("");
-
-    match time_start {
-        None => match time_stop {
-            None => {}
-            Some(n) => {
-                params.push(n.html_escape().to_mut().clone());
-                time_clause = String::from("AND tx.time <= :time_stop");
-            }
-        },
-        Some(time_start) => match time_stop {
-            None => {
-                params.push(time_start.html_escape().to_mut().clone());
-                time_clause = String::from("AND :time_start <= tx.time");
-            }
-            Some(time_stop) => {
-                params.push(time_start.html_escape().to_mut().clone());
-                params.push(time_stop.html_escape().to_mut().clone());
-                time_clause = String::from("AND :time_start <= tx.time AND tx.time <= :time_stop");
-            }
-        },
-    }
+    let time_clause = time_clause(time_start, time_stop, &mut params);
+
+    // The trancat filters are also optional.
+    let trancat_clause = trancat_clause(
+        &apikey.html_escape().to_mut().clone(),
+        &trancat_id,
+        &exclude_trancat_id,
+        &mut params,
+    );
 
     let q = format!(
         "
@@ -72,8 +118,9 @@ pub fn get_account_dist_sum(
         WHERE ac.id = :account_id
             AND ac.apikey = :apikey
             {}
+            {}
             ",
-        time_clause
+        time_clause, trancat_clause
     );
 
     let vec: Vec<crate::db::BalanceResultBt> = conn
@@ -107,8 +154,31 @@ pub fn get_account_dist_sum(
 
 
     // Now build and return the http response.
-    crate::db::ApiResponseOld {
-        json: json!({ "sum": sum }),
-        status: Status::Ok,
+    match converter.as_mut() {
+        None => crate::db::ApiResponseOld {
+            json: json!({ "sum": sum.formatted(&format) }),
+            status: Status::Ok,
+        },
+        Some(converter) => {
+            let apikey = apikey.html_escape().to_mut().clone();
+            let converted = account_currencies(&mut *conn, &apikey, &vec![account_id])
+                .and_then(|currencies| match currencies.get(&account_id) {
+                    None => Ok(None),
+                    Some(currency_id) => converter.convert(&mut *conn, &apikey, &sum, *currency_id),
+                });
+            match converted {
+                Ok(converted) => crate::db::ApiResponseOld {
+                    json: json!({
+                        "sum": sum.formatted(&format),
+                        "converted": converter.to_json(&converted, &format)
+                    }),
+                    status: Status::Ok,
+                },
+                Err(err) => crate::db::ApiResponseOld {
+                    json: json!({"error": err.to_string()}),
+                    status: Status::Ok,
+                },
+            }
+        }
     }
 }
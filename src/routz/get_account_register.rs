@@ -0,0 +1,214 @@
+use crate::clauses::time_clause;
+use crate::constants::{DEFAULT_PAGE_LIMIT, MAX_PAGE_LIMIT};
+use crate::db::{AccountRegister, AccountRegisterEntry, AccountRegisterParams};
+use crate::dfp::dfp::{DFP, FormattedJson, Sign, dfp_add, dfp_from_string_exp};
+use crate::format::parse_format;
+use rocket::get;
+use rocket::http::{RawStr, Status};
+use rocket::request::LenientForm;
+use rocket_contrib::databases::mysql::prelude::GenericConnection;
+use rocket_contrib::databases::mysql::IsolationLevel;
+use rocket_contrib::json;
+
+/*
+Given an account_id, produce an account register.  That is, the opening balance of the account, each of its distributions along with the running balance after it, and the closing balance.
+
+The optional time_start and time_stop params have the same meaning as they do for account_dist_sum.  The opening balance is the sum of the distributions before time_start, or zero if there is no time_start.  The distributions are ordered by the time of their transactions.
+
+The distributions are paginated using optional offset and limit params.  The default offset is 0 and the default limit is DEFAULT_PAGE_LIMIT.  The running balances are correct on every page and the opening and closing balances are the same on every page.
+
+The register takes several queries.  We run all of them in a single read-only db transaction with a consistent snapshot, so that the balances, the count, and the page agree with each other even if somebody writes distributions meanwhile.
+
+Given an optional format param of digits, decimal, or significand, express the amounts and balances using that DFPFormat. The default is digits.
+ */
+
+// Parse an optional paging param, or use the default.
+fn parse_u32(name: &str, value: Option<&RawStr>, default: u32) -> Result<u32, String> {
+    match value {
+        None => Ok(default),
+        Some(v) => v
+            .html_escape()
+            .to_mut()
+            .parse()
+            .map_err(|_| format!("{} must be a non-negative integer.", name)),
+    }
+}
+
+/* Sum the distributions selected by the given query, without fetching them.  The query must select amount_exp, the SUM of the amountbt that have that amount_exp, and their COUNT.  The db adds the significands exactly as DECIMAL(65,0), so there's no rounding, and we only have to combine the few sums, one per amount_exp, that come back.
+
+Return the sum and the count of the distributions.
+ */
+fn sum_distributions<C: GenericConnection>(q: String, params: Vec<String>, conn: &mut C) -> Result<(DFP, u32), String> {
+    let rows: Vec<(i8, String, u32)> = conn
+        .prep_exec(q, params)
+        .map_err(|err| err.to_string())?
+        .map(|x| x.unwrap())
+        .map(|row| rocket_contrib::databases::mysql::from_row(row))
+        .collect();
+
+    Ok(rows.into_iter().fold(
+        (DFP { amount: vec![], exp: 0, sign: Sign::Zero }, 0),
+        |(sum, cnt), (amount_exp, amountbt, n)| (dfp_add(sum, dfp_from_string_exp(&amountbt, amount_exp)), cnt + n),
+    ))
+}
+
+// This is the core functionality of the account register.
+fn get_account_register_private<C: GenericConnection>(
+    apikey: &RawStr,
+    account_id: &RawStr,
+    options: &AccountRegisterParams,
+    conn: &mut C,
+) -> Result<AccountRegister, String> {
+    // 1. Examine the params.
+    let account_id: u32 = parse_u32("account_id", Some(account_id), 0)?;
+    let offset = parse_u32("offset", options.offset, 0)?;
+    let limit = parse_u32("limit", options.limit, DEFAULT_PAGE_LIMIT)?;
+    if limit > MAX_PAGE_LIMIT {
+        return Err(format!("limit must be no more than {}.", MAX_PAGE_LIMIT));
+    }
+
+    // We receive this argument as &RawStr.  We must convert it into a form that the mysql parametrization can use.
+    let apikey = apikey.html_escape().to_mut().clone();
+
+    // Every query below selects from the distributions of the period, in the order of the register.  Build a fresh param stack for each of them.
+    // WARNING! Push these in the same order they are used in the prep_exec function!
+    let period = |params: &mut Vec<String>| -> String {
+        params.push(account_id.to_string());
+        params.push(apikey.clone());
+        format!(
+            "
+            FROM distributions AS ds
+            JOIN transactions AS tx ON tx.id = ds.transaction_id
+            WHERE ds.account_id = :account_id
+            AND ds.apikey = :apikey
+            {}
+            ",
+            time_clause(options.time_start, options.time_stop, params)
+        )
+    };
+
+    // 2. The opening balance is the sum of the distributions before time_start.
+    let opening_balance = match options.time_start {
+        None => DFP { amount: vec![], exp: 0, sign: Sign::Zero },
+        Some(time_start) => sum_distributions(
+            String::from(
+                "
+                SELECT ds.amount_exp, SUM(CAST(ds.amountbt AS DECIMAL(65,0))), COUNT(*)
+                FROM distributions AS ds
+                JOIN transactions AS tx ON tx.id = ds.transaction_id
+                WHERE ds.account_id = :account_id
+                AND ds.apikey = :apikey
+                AND tx.time < :time_start
+                GROUP BY ds.amount_exp
+                ",
+            ),
+            vec![account_id.to_string(), apikey.clone(), time_start.html_escape().to_mut().clone()],
+            conn,
+        )?
+        .0,
+    };
+
+    // 3. How many distributions are in the period, and what do they add up to?
+    let mut params = Vec::new();
+    let q = format!(
+        "SELECT ds.amount_exp, SUM(CAST(ds.amountbt AS DECIMAL(65,0))), COUNT(*) {} GROUP BY ds.amount_exp",
+        period(&mut params)
+    );
+    let (period_sum, entry_cnt) = sum_distributions(q, params, conn)?;
+    let closing_balance = dfp_add(opening_balance.clone(), period_sum);
+
+    // 4. The balance brought forward to this page is the sum of the distributions of the period on the pages before it.
+    let mut balance = opening_balance.clone();
+    if offset > 0 {
+        let mut params = Vec::new();
+        let q = format!(
+            "
+            SELECT page.amount_exp, SUM(CAST(page.amountbt AS DECIMAL(65,0))), COUNT(*)
+            FROM (SELECT ds.amountbt, ds.amount_exp {} ORDER BY tx.time, tx.id, ds.id LIMIT {}) AS page
+            GROUP BY page.amount_exp
+            ",
+            period(&mut params),
+            offset
+        );
+        balance = dfp_add(balance, sum_distributions(q, params, conn)?.0);
+    }
+
+    // 5. Now get the distributions on this page and run the balance through them.
+    let mut params = Vec::new();
+    let q = format!(
+        "
+        SELECT ds.id, ds.transaction_id, ds.amountbt, ds.amount_exp, tx.time, tx.notes
+        {}
+        ORDER BY tx.time, tx.id, ds.id
+        LIMIT {} OFFSET {}
+        ",
+        period(&mut params),
+        limit,
+        offset
+    );
+
+    let rows: Vec<(u32, u32, String, i8, String, String)> = conn
+        .prep_exec(q, params)
+        .map_err(|err| err.to_string())?
+        .map(|x| x.unwrap())
+        .map(|row| rocket_contrib::databases::mysql::from_row(row))
+        .collect();
+
+    let mut entries: Vec<AccountRegisterEntry> = Vec::new();
+    for (distribution_id, transaction_id, amountbt, amount_exp, time, notes) in rows {
+        let amount = dfp_from_string_exp(&amountbt, amount_exp);
+        balance = dfp_add(balance, amount.clone());
+        entries.push(AccountRegisterEntry {
+            distribution_id,
+            transaction_id,
+            time,
+            notes,
+            amount,
+            balance: balance.clone(),
+        });
+    }
+
+    Ok(AccountRegister {
+        account_id,
+        opening_balance,
+        entries,
+        closing_balance,
+        offset,
+        limit,
+        entry_cnt,
+    })
+}
+
+#[get("/account_register?<apikey>&<account_id>&<options..>")]
+pub fn get_account_register(
+    apikey: &RawStr,
+    account_id: &RawStr,
+    options: LenientForm<AccountRegisterParams>,
+    mut conn: crate::db::MyRocketSQLConn,
+) -> crate::db::ApiResponseOld {
+    // Which format shall we use for the amounts?
+    let format = match parse_format(options.format) {
+        Ok(f) => f,
+        Err(e) => return e,
+    };
+
+    let result = conn
+        .start_transaction(true, Some(IsolationLevel::RepeatableRead), Some(true))
+        .map_err(|err| err.to_string())
+        .and_then(|mut tx| {
+            let register = get_account_register_private(apikey, account_id, &options, &mut tx)?;
+            tx.commit().map_err(|err| err.to_string())?;
+            Ok(register)
+        });
+
+    match result {
+        Ok(register) => crate::db::ApiResponseOld {
+            json: json!(register.formatted(&format)),
+            status: Status::Ok,
+        },
+        Err(e) => crate::db::ApiResponseOld {
+            json: json!({ "error": e }),
+            status: Status::Ok,
+        },
+    }
+}
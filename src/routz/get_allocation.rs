@@ -0,0 +1,91 @@
+use crate::db::{DistributionShort, GetAllocationResponse};
+use crate::dfp::dfp::{dfp_allocate, dfp_to_string_exp, DFP};
+use crate::idlist::IdList;
+use crate::routz::distribution::amount_from_amountbt;
+use rocket::get;
+use rocket::http::RawStr;
+use rocket_contrib::json::Json;
+
+/*
+Given an amount, a comma delimited list of account_id, and a comma delimited list of weights, one for each account, allocate the amount across the accounts in proportion to the weights.  Return a list of distributions, for the given transaction_id, that are ready to POST to /distributions.
+
+The amount and the weights are human-readable decimal text such as "1234.56".  The shares are expressed using the given exp and always add up to exactly the amount, after rounding said amount to exp.  Please see dfp_allocate for the details of how we deal with the leftover pennies.
+
+For example, allocating 100 across three accounts with the weights 1,1,1 and exp 0 yields 34, 33, 33.
+
+This only does the arithmetic.  Nothing is written to the db.
+ */
+#[get("/allocation?<apikey>&<transaction_id>&<amount>&<exp>&<account_id>&<weight>")]
+pub fn get_allocation(
+    apikey: &RawStr,
+    transaction_id: u32,
+    amount: &RawStr,
+    exp: i8,
+    account_id: Result<IdList, String>,
+    weight: &RawStr,
+) -> Json<GetAllocationResponse> {
+    // 1. Tediously extract the values of the query parameters.
+
+    // 1.1 amount and weights may contain characters that must be % encoded, such as a '+' sign.
+    fn parse_dfp(s: &str) -> Result<DFP, String> {
+        s.parse::<DFP>().map_err(|e| format!("{} is not a valid number. {}", s, e))
+    }
+
+    let amount = match amount.percent_decode() {
+        Ok(s) => match parse_dfp(&s) {
+            Ok(d) => d,
+            Err(e) => return Json(GetAllocationResponse::Error(e)),
+        },
+        Err(e) => return Json(GetAllocationResponse::Error(e.to_string())),
+    };
+
+    let weights: Vec<DFP> = match weight.percent_decode() {
+        Ok(s) => match s.split(',').map(parse_dfp).collect() {
+            Ok(v) => v,
+            Err(e) => return Json(GetAllocationResponse::Error(e)),
+        },
+        Err(e) => return Json(GetAllocationResponse::Error(e.to_string())),
+    };
+
+    // 1.2 account_id is a comma delimited list of integers.
+    let account_ids: Vec<u32> = match account_id {
+        Ok(ids) => ids.0,
+        Err(e) => return Json(GetAllocationResponse::Error(format!("account_id {}", e))),
+    };
+
+    if account_ids.len() != weights.len() {
+        return Json(GetAllocationResponse::Error(String::from(
+            "account_id and weight must contain the same quantity of items.",
+        )));
+    }
+
+    // 2. Do the arithmetic.
+    let shares = match dfp_allocate(amount, &weights, exp) {
+        Some(v) => v,
+        None => {
+            return Json(GetAllocationResponse::Error(String::from(
+                "The weights must not be negative and must not add up to zero.",
+            )))
+        }
+    };
+
+    // 3. Now build the distributions.  A share too large for amount still gets the same amount that POST /distributions expects for it.
+    let mut ret_val = Vec::new();
+    for (account_id, share) in account_ids.iter().zip(shares.iter()) {
+        let (amountbt, amount_exp) = dfp_to_string_exp(share);
+        let amount = match amount_from_amountbt(&amountbt) {
+            Ok(n) => n,
+            Err(e) => return Json(GetAllocationResponse::Error(e)),
+        };
+        ret_val.push(DistributionShort {
+            account_id: *account_id,
+            amount,
+            amount_exp,
+            amountbt,
+            apikey: apikey.html_escape().to_mut().clone(),
+            transaction_id,
+        });
+    }
+
+    Json(GetAllocationResponse::Many(ret_val))
+}
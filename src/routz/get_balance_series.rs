@@ -0,0 +1,201 @@
+use crate::calendar::{Date, Interval, bucket_start, next_bucket_start};
+use crate::category_filter::{CategoryMatch, category_filter_subquery};
+use crate::constants::MAX_SERIES_BUCKETS;
+use crate::db::{BalanceSeries, BalanceSeriesAccount, BalanceSeriesParams, BalanceSeriesPoint};
+use crate::dfp::dfp::{DFP, FormattedJson, Sign, dfp_add, dfp_from_string_exp};
+use crate::format::parse_format;
+use crate::idlist::{IdList, bad_id_list};
+use rocket::get;
+use rocket::http::{RawStr, Status};
+use rocket::request::LenientForm;
+use rocket_contrib::databases::mysql;
+use rocket_contrib::json;
+use std::collections::BTreeMap;
+
+/*
+Produce a balance series, suitable for charting.  That is, the sums of the distributions of one or more accounts, grouped into buckets of time.
+
+Given either an account_id, or a comma delimited list of category_id, examine that account, or all of the accounts tagged with _all_ of the given categories, as does category_dist_sums.  A category_id that is not a comma delimited list of integers gets a 400 Bad Request.
+
+The time_start and time_stop params are dates, in the form YYYY, YYYY-MM, or YYYY-MM-DD.  The interval param is one of day, week, month, quarter, or year.  The buckets are the intervals that contain time_start, time_stop, and everything in between.  Weeks start on Monday.
+
+Given an optional boolean cumulative param, the sum of each bucket is the balance at the end of the bucket.  This is the default.  Else the sum of each bucket is the change in balance during the bucket.
+
+We examine all of the distributions with a single query, and assign each of them to a bucket using the date at the start of its transaction's time.  A transaction time that doesn't start with a date is assigned to a bucket by comparing it to the starts of the buckets as strings.
+
+Given an optional format param of digits, decimal, or significand, express the sums using that DFPFormat. The default is digits.
+ */
+
+// Parse a required date param.
+fn parse_date(name: &str, value: &RawStr) -> Result<Date, String> {
+    let value = value.html_escape().to_mut().clone();
+    Date::parse(&value).ok_or(format!("{} {} is not a date.", name, value))
+}
+
+fn get_balance_series_private(
+    apikey: &RawStr,
+    time_start: &RawStr,
+    time_stop: &RawStr,
+    interval: &RawStr,
+    options: &BalanceSeriesParams,
+    category_id: Option<IdList>,
+    conn: &mut mysql::Conn,
+) -> Result<BalanceSeries, String> {
+    // 1. Examine the params.
+    let time_start = parse_date("time_start", time_start)?;
+    let time_stop = parse_date("time_stop", time_stop)?;
+    let interval: Interval = interval.html_escape().to_mut().parse()?;
+    let cumulative: bool = match options.cumulative {
+        None => true,
+        Some(b) => b.html_escape().to_mut().parse().map_err(|e: std::str::ParseBoolError| e.to_string())?,
+    };
+
+    // 2. Build the buckets.  These strings sort in the same order as the dates.
+    let mut starts: Vec<String> = Vec::new();
+    let mut stops: Vec<String> = Vec::new();
+    let mut start = bucket_start(&time_start, &interval);
+    while start <= time_stop {
+        if starts.len() == MAX_SERIES_BUCKETS {
+            return Err(format!("A balance series can have no more than {} buckets.", MAX_SERIES_BUCKETS));
+        }
+        let stop = next_bucket_start(&start, &interval);
+        starts.push(start.to_string());
+        stops.push(stop.to_string());
+        start = stop;
+    }
+    let end = match stops.last() {
+        Some(end) => end.clone(),
+        None => return Err(String::from("time_start must not be after time_stop.")),
+    };
+
+    // 3. Which accounts shall we examine?
+    // We receive these arguments as &RawStr.  We must convert them into a form that the mysql parametrization can use.
+    // WARNING! Push these in the same order they are used in the prep_exec function!
+    let apikey = apikey.html_escape().to_mut().clone();
+    let mut params = Vec::new();
+    params.push(apikey.clone());
+    let (account_clause, account_id) = match (options.account_id, category_id) {
+        (Some(account_id), None) => {
+            let account_id: u32 = account_id
+                .html_escape()
+                .to_mut()
+                .parse()
+                .map_err(|_| String::from("account_id must be a non-negative integer."))?;
+            params.push(account_id.to_string());
+            (String::from("ds.account_id = :account_id"), Some(account_id))
+        }
+        (None, Some(category_id)) => {
+            let groups: Vec<Vec<u32>> = category_id.0.iter().map(|id| vec![*id]).collect();
+            let subquery = category_filter_subquery(&apikey, &groups, &CategoryMatch::All, &[], &mut params);
+            (format!("ds.account_id IN {}", subquery), None)
+        }
+        _ => return Err(String::from("Please specify either account_id or category_id, but not both.")),
+    };
+    params.push(end.clone());
+
+    // 4. Now get all of the relevant distributions, in a single pass.  If we don't want cumulative sums we could filter by time_start here, but the buckets will do that for us.
+    let rows: Vec<(u32, String, String, i8)> = conn
+        .prep_exec(
+            format!(
+                "
+                SELECT ds.account_id, tx.time, ds.amountbt, ds.amount_exp
+                FROM distributions AS ds
+                JOIN transactions AS tx ON tx.id = ds.transaction_id
+                WHERE ds.apikey = :apikey
+                AND {}
+                AND tx.time < :end
+                ",
+                account_clause
+            ),
+            params,
+        )
+        .map_err(|err| err.to_string())?
+        .map(|x| x.unwrap())
+        .map(|row| rocket_contrib::databases::mysql::from_row(row))
+        .collect();
+
+    // 5. Sum the distributions of each account into its buckets.  The opening balance is the sum of everything before the first bucket.
+    let zero = DFP { amount: vec![], exp: 0, sign: Sign::Zero };
+    let mut accounts: BTreeMap<u32, (DFP, Vec<DFP>)> = BTreeMap::new();
+    if let Some(account_id) = account_id {
+        // The caller asked for this account, so give it a series even if it has no distributions.
+        accounts.insert(account_id, (zero.clone(), vec![zero.clone(); starts.len()]));
+    }
+
+    for (account_id, time, amountbt, amount_exp) in rows {
+        let key = match Date::parse(&time) {
+            Some(date) => date.to_string(),
+            None => time,
+        };
+        if key >= end {
+            continue; // Such as "2021" when the series ends at 2021-01-01.
+        }
+
+        let (opening, sums) = accounts
+            .entry(account_id)
+            .or_insert((zero.clone(), vec![zero.clone(); starts.len()]));
+        let amount = dfp_from_string_exp(&amountbt, amount_exp);
+        match starts.binary_search(&key) {
+            Ok(i) => sums[i] = dfp_add(sums[i].clone(), amount),
+            Err(0) => *opening = dfp_add(opening.clone(), amount),
+            Err(i) => sums[i - 1] = dfp_add(sums[i - 1].clone(), amount),
+        }
+    }
+
+    // 6. Build the series.
+    let mut series: Vec<BalanceSeriesAccount> = Vec::new();
+    for (account_id, (opening, sums)) in accounts {
+        let mut balance = opening;
+        let mut points: Vec<BalanceSeriesPoint> = Vec::new();
+        for (i, sum) in sums.into_iter().enumerate() {
+            let sum = if cumulative {
+                balance = dfp_add(balance, sum);
+                balance.clone()
+            } else {
+                sum
+            };
+            points.push(BalanceSeriesPoint {
+                start: starts[i].clone(),
+                stop: stops[i].clone(),
+                sum,
+            });
+        }
+        series.push(BalanceSeriesAccount { account_id, points });
+    }
+
+    Ok(BalanceSeries { series })
+}
+
+#[get("/balance_series?<apikey>&<time_start>&<time_stop>&<interval>&<options..>")]
+pub fn get_balance_series(
+    apikey: &RawStr,
+    time_start: &RawStr,
+    time_stop: &RawStr,
+    interval: &RawStr,
+    options: LenientForm<BalanceSeriesParams>,
+    mut conn: crate::db::MyRocketSQLConn,
+) -> crate::db::ApiResponseOld {
+    // Which format shall we use for the sums?
+    let format = match parse_format(options.format) {
+        Ok(f) => f,
+        Err(e) => return e,
+    };
+
+    // Is category_id a proper list?
+    let category_id = match options.category_id.clone() {
+        None => None,
+        Some(Ok(ids)) => Some(ids),
+        Some(Err(e)) => return bad_id_list("category_id", e),
+    };
+
+    match get_balance_series_private(apikey, time_start, time_stop, interval, &options, category_id, &mut *conn) {
+        Ok(series) => crate::db::ApiResponseOld {
+            json: json!(series.formatted(&format)),
+            status: Status::Ok,
+        },
+        Err(e) => crate::db::ApiResponseOld {
+            json: json!({ "error": e }),
+            status: Status::Ok,
+        },
+    }
+}
@@ -1,7 +1,15 @@
 //use crate::dfpx::DFPx;
-use crate::dfp::dfp::{DFP, Sign, dfp_add, dfp_from_string_exp};
+use crate::category_filter::{CategoryMatch, category_descendants, category_filter_subquery, category_parents};
+use crate::clauses::{time_clause, trancat_clause};
+use crate::dfp::dfp::{DFP, DFPFormat, FormattedJson, Sign, dfp_add, dfp_from_string_exp};
+use crate::format::parse_format;
+use crate::db::CategoryDistSumsParams;
+use crate::idlist::{IdList, bad_id_list, id_placeholders};
+use crate::ledger::{Converter, account_currencies};
 use rocket::get;
 use rocket::http::{RawStr, Status};
+use rocket::request::LenientForm;
+use rocket_contrib::databases::mysql;
 use rocket_contrib::json;
 use std::collections::HashMap;
 
@@ -27,96 +35,166 @@ Setting both time_* params gives us the change in balance during a time period a
  income statement items.
 
 Setting only time_start doesn't seem real useful, but I'm sure somebody can find a need for doing this.
+
+Given an optional format param of digits, decimal, or significand, express the sums using that DFPFormat. The default is digits.
+
+Given an optional convert_to param, which is a currency_id, also convert each sum into that currency, using the most recent price as of an optional valuation_time.  The valuation_time defaults to time_stop.  If there's no rate available for an account, its converted sum is null and rate_missing is true.  Please see crate::ledger::Converter.
+
+Given an optional boolean descendants param, also examine the accounts that are tagged with any descendant of a given category, so that the sums roll up through the category hierarchy.  That is, an account is examined if, for each of the given categories, it is tagged with that category or one of its descendants.  The default is false.
+
+Given an optional match param of any or all, examine the accounts tagged with _any_ of the given categories, or with _all_ of them.  The default is all.
+
+Given an optional comma delimited list of exclude_category_id, don't examine any account that is tagged with any of those categories.  Such as "assets but not retirement."  If we roll up the descendants, the descendants of the excluded categories are excluded too.
+
+Given an optional comma delimited list of trancat_id, which are category_id that tag transactions, as per /trancats, only examine the distributions of transactions tagged with any of those categories.  Given an optional comma delimited list of exclude_trancat_id, don't examine the distributions of transactions tagged with any of those categories.
+
+A category_id, exclude_category_id, trancat_id, or exclude_trancat_id that is not a comma delimited list of integers gets a 400 Bad Request.  Please see crate::idlist.
+ */
+
+/* Turn a list of category_id into groups of category_id.  Each group is a single category, or a category and its descendants.
  */
-#[get("/category_dist_sums?<apikey>&<category_id>&<time_start>&<time_stop>&<decorate>")]
+fn category_groups(
+    conn: &mut mysql::Conn,
+    apikey: &String,
+    category_id: &IdList,
+    descendants: bool,
+) -> Result<Vec<Vec<u32>>, mysql::Error> {
+    if descendants {
+        let parents = category_parents(conn, apikey)?;
+        Ok(category_id.0.iter().map(|id| category_descendants(&parents, *id)).collect())
+    } else {
+        Ok(category_id.0.iter().map(|id| vec![*id]).collect())
+    }
+}
+
+#[get("/category_dist_sums?<apikey>&<category_id>&<options..>")]
 pub fn get_category_dist_sums(
     apikey: &RawStr,
-    category_id: &RawStr,
-    time_start: Option<&RawStr>,
-    time_stop: Option<&RawStr>,
-    decorate: Option<&RawStr>,
+    category_id: Result<IdList, String>,
+    options: LenientForm<CategoryDistSumsParams>,
     mut conn: crate::db::MyRocketSQLConn,
 ) -> crate::db::ApiResponseOld {
-    // 1. Build a vector of sanitized incoming request parameters. We will feed this to the SQL query. While we're here, let's also use this opportunity to build a time filtering clause for the SQL query.
-    let mut params = Vec::new();
-
-    // 1.1 We receive these arguments as &RawStr.  We must convert them into a form that the mysql parametrization can use.
-    // WARNING! Push these in the same order they are used in the prep_exec function!
-    //params.push(category_id.html_escape().to_mut().clone());
-    let pcat = category_id.html_escape().to_mut().clone();
-    params.push(apikey.html_escape().to_mut().clone());
+    let CategoryDistSumsParams {
+        time_start,
+        time_stop,
+        decorate,
+        format,
+        convert_to,
+        valuation_time,
+        descendants,
+        exclude_category_id,
+        trancat_id,
+        exclude_trancat_id,
+        category_match,
+    } = options.into_inner();
+
+    // 0. Which format shall we use for the sums?
+    let format = match parse_format(format) {
+        Ok(f) => f,
+        Err(e) => return e,
+    };
 
-    // 1.2 time_start and time_stop are both optional.
-    let mut time_clause = String::from("");
+    // 0.1 Shall we convert the sums?
+    let mut converter = match Converter::from_params(convert_to, valuation_time, time_stop) {
+        Ok(c) => c,
+        Err(e) => {
+            return crate::db::ApiResponseOld {
+                json: json!({ "error": e }),
+                status: Status::Ok,
+            }
+        }
+    };
 
-    match time_start {
-        None => match time_stop {
-            None => {}
-            Some(time_stop) => {
-                params.push(time_stop.html_escape().to_mut().clone());
-                time_clause = String::from("AND tx.time <= :time_stop");
+    // 0.2 Shall we roll up the descendants of the categories?
+    let descendants: bool = match descendants {
+        None => false,
+        Some(b) => match b.html_escape().to_mut().parse() {
+            Ok(b) => b,
+            Err(e) => {
+                return crate::db::ApiResponseOld {
+                    json: json!({"error": e.to_string()}),
+                    status: Status::Ok,
+                }
             }
         },
-        Some(time_start) => match time_stop {
-            None => {
-                params.push(time_start.html_escape().to_mut().clone());
-                time_clause = String::from("AND :time_start <= tx.time");
-            }
-            Some(time_stop) => {
-                params.push(time_start.html_escape().to_mut().clone());
-                params.push(time_stop.html_escape().to_mut().clone());
-                time_clause = String::from("AND :time_start <= tx.time AND tx.time <= :time_stop");
+    };
+
+    // 0.3 Shall we match any or all of the categories?
+    let category_match: CategoryMatch = match category_match {
+        None => CategoryMatch::All,
+        Some(m) => match m.html_escape().to_mut().parse() {
+            Ok(m) => m,
+            Err(e) => {
+                return crate::db::ApiResponseOld {
+                    json: json!({ "error": e }),
+                    status: Status::Ok,
+                }
             }
         },
-    }
+    };
 
-    // 2. Obtain all of the relevant distributions.
-    // The subquery produces a list of relevant accounts recall that we want accounts that are tagged with _all_ of the given categories.  The outer query filters by time and produces the final values.
+    // 0.4 Which categories shall we examine, or exclude?
+    let category_id = match category_id {
+        Ok(ids) => ids,
+        Err(e) => return bad_id_list("category_id", e),
+    };
+    let exclude_category_id = match exclude_category_id {
+        None => IdList(vec![]),
+        Some(Ok(ids)) => ids,
+        Some(Err(e)) => return bad_id_list("exclude_category_id", e),
+    };
 
-    // 2.1 We'll need to know how many categories the caller requested.
-    // If a category param is not sent, execution will never get here.  If a category param is sent, then category_cnt must therefore be at least 1.
-    let mut category_cnt = 0;
-    for _ in pcat.split(',') {
-        category_cnt = category_cnt + 1;
-    }
+    // 0.5 Shall we filter by the categories that tag the transactions?
+    let trancat_id = match trancat_id.transpose() {
+        Ok(ids) => ids,
+        Err(e) => return bad_id_list("trancat_id", e),
+    };
+    let exclude_trancat_id = match exclude_trancat_id.transpose() {
+        Ok(ids) => ids,
+        Err(e) => return bad_id_list("exclude_trancat_id", e),
+    };
 
-    // 2.2 Build a subquery to retrieve the relevant account_id, based on the quantity of categories requested.  Can these queries be unified into a single query regardless of the category_cnt?
+    // 1. Build a vector of sanitized incoming request parameters. We will feed this to the SQL query. While we're here, let's also use this opportunity to build the clauses for the SQL query.
+    let mut params = Vec::new();
 
-    let acct_sub_query = if category_cnt == 1 {
-        format!(
-            "
-            ( SELECT account_id
-              FROM accounts_categories
-              WHERE category_id = {}
-              AND apikey = :apikey
-            )
-        ",
-            pcat
-        )
-    } else {
-        // must be > 1
-        format!(
-            "
-            ( SELECT account_id
-              FROM accounts_categories
-              WHERE category_id IN ({})  AND apikey = :apikey
-              GROUP BY account_id
-              HAVING count(*) = {}
-            )
-        ",
-            pcat, category_cnt
-        )
+    // 1.1 We receive these arguments as &RawStr.  We must convert them into a form that the mysql parametrization can use.  If we roll up the descendants, each of the given categories becomes a group of itself and its descendants.
+    let apikey_s = apikey.html_escape().to_mut().clone();
+    let groups = category_groups(&mut *conn, &apikey_s, &category_id, descendants).and_then(|groups| {
+        Ok((groups, category_groups(&mut *conn, &apikey_s, &exclude_category_id, descendants)?))
+    });
+    let (groups, exclude) = match groups {
+        Ok(g) => g,
+        Err(err) => {
+            return crate::db::ApiResponseOld {
+                json: json!({"error": err.to_string()}),
+                status: Status::Ok,
+            }
+        }
     };
 
+    // 2. Obtain all of the relevant distributions.
+    // The subquery produces a list of relevant accounts.  Recall that we want accounts that are tagged with _any_ or _all_ of the given categories, but none of the excluded categories.  The outer query filters by time and produces the final values.
+
+    // 2.1 Build a subquery to retrieve the relevant account_id.
+    // WARNING! Push these in the same order they are used in the prep_exec function!
+    let acct_sub_query = category_filter_subquery(&apikey_s, &groups, &category_match, &exclude, &mut params);
+
+    // 2.2 time_start and time_stop are both optional.
+    let time_clause = time_clause(time_start, time_stop, &mut params);
+
+    // 2.3 The trancat filters are also optional.
+    let trancat_clause = trancat_clause(&apikey_s, &trancat_id, &exclude_trancat_id, &mut params);
+
     let q = format!(
         "
             SELECT account_id, amountbt, amount_exp
                 FROM distributions AS ds
                 JOIN transactions as tx on tx.id = ds.transaction_id
             WHERE account_id in ( {} )
+            {}
             {}  ORDER BY account_id
             ",
-        acct_sub_query, time_clause
+        acct_sub_query, time_clause, trancat_clause
     );
 
     let vec: Vec<crate::db::BalanceResultBt> = conn
@@ -200,12 +278,43 @@ pub fn get_category_dist_sums(
         },
     );
 
+    // 3.4 If we have requested conversions, convert each sum.
+    let mut converted: HashMap<u32, serde_json::Value> = HashMap::new();
+    if let Some(converter) = converter.as_mut() {
+        let apikey = apikey.html_escape().to_mut().clone();
+        let account_ids: Vec<u32> = hm.keys().cloned().collect();
+        let result = account_currencies(&mut *conn, &apikey, &account_ids).and_then(|currencies| {
+            for (account_id, v) in &hm {
+                let c = match currencies.get(account_id) {
+                    None => None,
+                    Some(currency_id) => converter.convert(&mut *conn, &apikey, &v.sum, *currency_id)?,
+                };
+                converted.insert(*account_id, converter.to_json(&c, &format));
+            }
+            Ok(())
+        });
+        if let Err(err) = result {
+            return crate::db::ApiResponseOld {
+                json: json!({"error": err.to_string()}),
+                status: Status::Ok,
+            };
+        }
+    }
+
+    // 3.5 Given a sum, build its json, along with its conversion, if any.
+    fn with_converted(mut v: serde_json::Value, account_id: u32, converted: &HashMap<u32, serde_json::Value>) -> serde_json::Value {
+        if let Some(c) = converted.get(&account_id) {
+            v["converted"] = c.clone();
+        }
+        v
+    }
+
     // 4. We will soon need this.  Is there an easier way to do this?
-    fn to_vec(hm: HashMap<u32, crate::db::AcctSum>) -> Vec<crate::db::AcctSum> {
-        let mut ret_val: Vec<crate::db::AcctSum> = Vec::new();
+    fn to_vec(hm: &HashMap<u32, crate::db::AcctSum>, converted: &HashMap<u32, serde_json::Value>, format: &DFPFormat) -> Vec<serde_json::Value> {
+        let mut ret_val: Vec<serde_json::Value> = Vec::new();
 
         for (_k, v) in hm {
-            ret_val.push(v);
+            ret_val.push(with_converted(v.formatted(format), v.account_id, converted));
         }
         return ret_val;
     }
@@ -215,7 +324,7 @@ pub fn get_category_dist_sums(
         None => {
             // 5.1 If we have not requested the decorations we can return the HashMap as a Vector as the response now.
             return crate::db::ApiResponseOld {
-                json: json!({ "sums": to_vec(hm) }),
+                json: json!({ "sums": to_vec(&hm, &converted, &format) }),
                 status: Status::Ok,
             };
         }
@@ -228,7 +337,7 @@ pub fn get_category_dist_sums(
                     } else {
                         // decorate parsed to an explicit false.  No decorations, just the HashMap.
                         return crate::db::ApiResponseOld {
-                            json: json!({ "sums": to_vec(hm) }),
+                            json: json!({ "sums": to_vec(&hm, &converted, &format) }),
                             status: Status::Ok,
                         };
                     }
@@ -247,23 +356,14 @@ pub fn get_category_dist_sums(
     // 6. At this point we know that we have info to return and we know that the caller has requested decorations.  So now build and execute a 2nd SQL query to retrieve the related decorative items.
 
     // 6.1 First build an "in clause" containing a list of relevant account_id.
-    let mut in_clause = String::new();
-    in_clause.push_str("(");
-    let mut first_time = true;
-    for (k, _) in &hm {
-        if first_time {
-            first_time = false;
-        } else {
-            in_clause.push_str(", ");
-        }
-        in_clause.push_str(k.to_string().as_str())
-    }
-    in_clause.push_str(")");
+    // WARNING! Push these in the same order they are used in the prep_exec function!
+    params = Vec::new();
+    params.push(apikey.html_escape().to_mut().clone());
+    let account_ids: Vec<u32> = hm.keys().cloned().collect();
+    let in_clause = format!("({})", id_placeholders("account_id", &account_ids, &mut params));
 
     // 6.2 Now build and execute the SQL to get the decorations.
     // WARNING! The two queries are not atomic.  Contemplate what errors might arise because of this.
-    params = Vec::new();
-    params.push(apikey.html_escape().to_mut().clone());
     let vec: Vec<crate::db::AccountCurrencyDecorations> = conn
         .prep_exec(
             format!(
@@ -314,7 +414,7 @@ pub fn get_category_dist_sums(
                     },
                     sum: (*asum).sum.clone(),
                 };
-                ret_val.push(n);
+                ret_val.push(with_converted(n.formatted(&format), d.account_id, &converted));
             //}
             //_ => {
                 // This should never happen. Contemplate why.
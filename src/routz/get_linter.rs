@@ -0,0 +1,45 @@
+use crate::db::{Finding, GetLinterResponse, LinterRule, MyRocketSQLConn};
+use crate::linter::{registry, select};
+use rocket::get;
+use rocket::http::RawStr;
+use rocket_contrib::databases::mysql::Conn;
+use rocket_contrib::json::Json;
+
+/*
+Run all of the linter rules, or only those selected by an optional comma delimited list of rule ids, and return all of their findings.  Please see crate::linter.
+ */
+#[get("/linter?<apikey>&<rules>")]
+pub fn get_linter(
+    apikey: &RawStr,
+    rules: Option<&RawStr>,
+    mut conn: MyRocketSQLConn,
+) -> Json<GetLinterResponse> {
+    // We receive these arguments as &RawStr.  We must convert them into a form that we can use.
+    let apikey = apikey.html_escape().to_mut().clone();
+    let rules = match rules.map(|r| r.percent_decode()) {
+        None => None,
+        Some(Ok(r)) => Some(r.to_string()),
+        Some(Err(err)) => return Json(GetLinterResponse::Error(err.to_string())),
+    };
+
+    let linters = match select(rules) {
+        Ok(linters) => linters,
+        Err(err) => return Json(GetLinterResponse::Error(err)),
+    };
+
+    let mut findings: Vec<Finding> = Vec::new();
+    for linter in linters {
+        match linter.run(&mut *conn, &apikey) {
+            Ok(mut v) => findings.append(&mut v),
+            Err(err) => return Json(GetLinterResponse::Error(err.to_string())),
+        }
+    }
+
+    Json(GetLinterResponse::Many(findings))
+}
+
+// List all of the linter rules.
+#[get("/linter/rules")]
+pub fn get_linter_rules() -> Json<Vec<LinterRule>> {
+    Json(registry::<Conn>().iter().map(|linter| linter.rule()).collect())
+}
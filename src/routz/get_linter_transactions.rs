@@ -0,0 +1,70 @@
+use crate::db::LinterTransaction;
+use crate::linter::{empty, single, suspicious_transactions, unbalanced_transactions};
+use rocket::get;
+use rocket::http::{RawStr, Status};
+use rocket_contrib::databases::mysql;
+use rocket_contrib::json;
+
+/*
+Find suspicious transactions.  There are three linters here:
+
+1. Empty transactions, that have no distributions at all.
+2. Transactions that have a single distribution.  These are almost certainly unbalanced, but even if the distribution is zero, it's still strange.
+3. Unbalanced transactions, whose distributions do not sum to zero within each currency.  These are the same transactions that GET /transactions/unbalanced finds.
+
+Each of them returns the suspicious transactions along with the sums of their distributions.  The unbalanced linter only returns the offending sums.
+
+The queries are shared with the corresponding linter rules.
+ */
+
+// This is the core functionality shared by the linters.
+fn get_linter_transactions_private(
+    apikey: &RawStr,
+    mut conn: crate::db::MyRocketSQLConn,
+    query: fn(&mut mysql::Conn, &String) -> Result<Vec<LinterTransaction>, mysql::Error>,
+) -> crate::db::ApiResponseOld {
+    // We receive this argument as &RawStr.  We must convert it into a form that the mysql parametrization can use.
+    let apikey = apikey.html_escape().to_mut().clone();
+
+    match query(&mut *conn, &apikey) {
+        Ok(vec) => crate::db::ApiResponseOld {
+            json: json!(vec),
+            status: Status::Ok,
+        },
+        Err(err) => crate::db::ApiResponseOld {
+            json: json!({"error": err.to_string()}),
+            status: Status::Ok,
+        },
+    }
+}
+
+// Find transactions that have no distributions.
+#[get("/linter/transactions/empty?<apikey>")]
+pub fn get_linter_transactions_empty(
+    apikey: &RawStr,
+    conn: crate::db::MyRocketSQLConn,
+) -> crate::db::ApiResponseOld {
+    get_linter_transactions_private(apikey, conn, |conn, apikey| {
+        suspicious_transactions(conn, apikey, empty)
+    })
+}
+
+// Find transactions that have a single distribution.
+#[get("/linter/transactions/single?<apikey>")]
+pub fn get_linter_transactions_single(
+    apikey: &RawStr,
+    conn: crate::db::MyRocketSQLConn,
+) -> crate::db::ApiResponseOld {
+    get_linter_transactions_private(apikey, conn, |conn, apikey| {
+        suspicious_transactions(conn, apikey, single)
+    })
+}
+
+// Find transactions whose distributions do not sum to zero within each currency.
+#[get("/linter/transactions/unbalanced?<apikey>")]
+pub fn get_linter_transactions_unbalanced(
+    apikey: &RawStr,
+    conn: crate::db::MyRocketSQLConn,
+) -> crate::db::ApiResponseOld {
+    get_linter_transactions_private(apikey, conn, unbalanced_transactions)
+}
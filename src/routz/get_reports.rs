@@ -0,0 +1,256 @@
+use crate::db::{CurrencySymbol, Report, ReportCurrency, ReportSection, TrialBalanceAccount};
+use crate::dfp::dfp::{DFP, DFPFormat, FormattedJson, Sign, dfp_add};
+use crate::format::parse_format;
+use crate::idlist::id_placeholders;
+use crate::ledger::account_sums;
+use rocket::get;
+use rocket::http::{RawStr, Status};
+use rocket_contrib::databases::mysql;
+use rocket_contrib::json;
+use std::collections::BTreeMap;
+
+/*
+Produce a balance sheet or an income statement.  The accounts in each section of the report are the accounts tagged with a given category.  Each category param is either a category id or a category symbol.  A param that parses as an integer is an id, else it's a symbol.
+
+A balance sheet has the sections assets, liabilities, and equity.  It examines the balances as of an optional time_stop.
+
+An income statement has the sections revenue and expense.  It examines the change in balances between optional time_start and time_stop.
+
+Each report is grouped by currency.  Within each currency every section has its accounts, each with the sum of its distributions, and a subtotal of those sums.  Each currency also has a total of its subtotals.  Recall that debits are positive and credits are negative.  So the total of an income statement is the net income, expressed as a negative number.  Because all distributions sum to zero, the total of a balance sheet is the net income that has not yet been closed into equity, expressed as a positive number.
+
+An account tagged with more than one of the given categories would appear in each of the relevant sections, and the total would count it more than once.  So we refuse to produce such a report, and instead explain which accounts are in more than one section.  Likewise, we refuse to use the same category for more than one section.
+
+Given an optional format param of digits, decimal, or significand, express the sums using that DFPFormat. The default is digits.
+ */
+
+// Given a category id or symbol, find the category_id.
+fn category_id(
+    conn: &mut mysql::Conn,
+    apikey: &String,
+    category: &RawStr,
+) -> Result<u32, String> {
+    let category = category.html_escape().to_mut().clone();
+    let (q, param) = match category.parse::<u32>() {
+        Ok(id) => ("SELECT id FROM categories WHERE id = :id AND apikey = :apikey", id.to_string()),
+        Err(_) => ("SELECT id FROM categories WHERE symbol = :symbol AND apikey = :apikey", category.clone()),
+    };
+
+    let vec: Vec<u32> = conn
+        .prep_exec(q, vec![param, apikey.clone()])
+        .map_err(|err| err.to_string())?
+        .map(|x| x.unwrap())
+        .map(|row| rocket_contrib::databases::mysql::from_row(row))
+        .collect();
+
+    match vec.get(0) {
+        Some(id) => Ok(*id),
+        None => Err(format!("{} is not a category.", category)),
+    }
+}
+
+// An error that isn't the caller's fault.
+fn error(e: String) -> crate::db::ApiResponseOld {
+    crate::db::ApiResponseOld {
+        json: json!({ "error": e }),
+        status: Status::Ok,
+    }
+}
+
+// An error that is the caller's fault.
+fn bad_request(e: String) -> crate::db::ApiResponseOld {
+    crate::db::ApiResponseOld {
+        json: json!({ "error": e }),
+        status: Status::BadRequest,
+    }
+}
+
+// Find the accounts that are tagged with more than one of the given categories.
+fn overlapping_accounts(
+    conn: &mut mysql::Conn,
+    apikey: &String,
+    category_ids: &Vec<u32>,
+) -> Result<Vec<(u32, String)>, mysql::Error> {
+    let mut params = vec![apikey.clone()];
+    let in_clause = id_placeholders("category_id", category_ids, &mut params);
+    Ok(conn
+        .prep_exec(
+            format!(
+                "
+                SELECT ac.id, ac.title
+                FROM accounts AS ac
+                JOIN accounts_categories AS acc ON acc.account_id = ac.id
+                WHERE ac.apikey = :apikey
+                AND acc.category_id IN ({})
+                GROUP BY ac.id, ac.title
+                HAVING COUNT(DISTINCT acc.category_id) > 1
+                ORDER BY ac.id
+                ",
+                in_clause
+            ),
+            params,
+        )?
+        .map(|x| x.unwrap())
+        .map(|row| rocket_contrib::databases::mysql::from_row(row))
+        .collect())
+}
+
+// This is the core functionality shared by the reports.  Each section is a title and a category id or symbol.
+fn get_report_private(
+    apikey: &RawStr,
+    sections: Vec<(&str, &RawStr)>,
+    time_start: Option<&RawStr>,
+    time_stop: Option<&RawStr>,
+    format: DFPFormat,
+    conn: &mut mysql::Conn,
+) -> Result<serde_json::Value, crate::db::ApiResponseOld> {
+    // We receive this argument as &RawStr.  We must convert it into a form that the mysql parametrization can use.
+    let apikey = apikey.html_escape().to_mut().clone();
+
+    // 1. Which categories define the sections?
+    let mut category_ids: Vec<u32> = Vec::new();
+    for (_, category) in &sections {
+        let id = category_id(conn, &apikey, category).map_err(error)?;
+        if category_ids.contains(&id) {
+            return Err(bad_request(format!(
+                "The category {} is used for more than one section.",
+                category.html_escape()
+            )));
+        }
+        category_ids.push(id);
+    }
+
+    // 1.1 An account may only be in one section.
+    let overlaps = overlapping_accounts(conn, &apikey, &category_ids).map_err(|err| error(err.to_string()))?;
+    if !overlaps.is_empty() {
+        return Err(bad_request(format!(
+            "These accounts are in more than one section: {}.",
+            overlaps
+                .iter()
+                .map(|(id, title)| format!("{} {}", id, title))
+                .collect::<Vec<String>>()
+                .join(", ")
+        )));
+    }
+
+    // 2. Sum the distributions of each account.
+    let mut sums = account_sums(conn, &apikey, time_start, time_stop).map_err(|err| error(err.to_string()))?;
+
+    // 3. Now find the accounts of each section and place them in the report.  Use a BTreeMap so that the currencies are in a predictable order.
+    let zero = DFP { amount: vec![], exp: 0, sign: Sign::Zero };
+    let mut currencies: BTreeMap<u32, ReportCurrency> = BTreeMap::new();
+    for (i, category_id) in category_ids.iter().enumerate() {
+        let accounts: Vec<(u32, String, u32, String)> = conn
+            .prep_exec(
+                "
+                SELECT ac.id, ac.title, cu.id, cu.symbol
+                FROM accounts AS ac
+                JOIN currencies AS cu ON cu.id = ac.currency_id
+                JOIN accounts_categories AS acc ON acc.account_id = ac.id
+                WHERE ac.apikey = :apikey
+                AND acc.category_id = :category_id
+                ORDER BY cu.id, ac.id
+                ",
+                vec![apikey.clone(), category_id.to_string()],
+            )
+            .map_err(|err| error(err.to_string()))?
+            .map(|x| x.unwrap())
+            .map(|row| rocket_contrib::databases::mysql::from_row(row))
+            .collect();
+
+        for (account_id, title, currency_id, symbol) in accounts {
+            // 3.1 The first time we see a currency, give it every section, so that all currencies have the same shape.
+            let c = currencies.entry(currency_id).or_insert(ReportCurrency {
+                currency: CurrencySymbol { currency_id, symbol },
+                sections: sections
+                    .iter()
+                    .zip(category_ids.iter())
+                    .map(|((section, _), category_id)| ReportSection {
+                        section: section.to_string(),
+                        category_id: *category_id,
+                        accounts: Vec::new(),
+                        subtotal: zero.clone(),
+                    })
+                    .collect(),
+                total: zero.clone(),
+            });
+
+            // 3.2 Each account is in only one section, so we can take its sum.
+            let sum = sums.remove(&account_id).unwrap_or(zero.clone());
+            c.total = dfp_add(c.total.clone(), sum.clone());
+            let s = &mut c.sections[i];
+            s.subtotal = dfp_add(s.subtotal.clone(), sum.clone());
+            s.accounts.push(TrialBalanceAccount {
+                account_id,
+                title,
+                sum,
+            });
+        }
+    }
+
+    let report = Report {
+        currencies: currencies.into_iter().map(|(_, c)| c).collect(),
+    };
+    Ok(report.formatted(&format))
+}
+
+fn to_response(result: Result<serde_json::Value, crate::db::ApiResponseOld>) -> crate::db::ApiResponseOld {
+    match result {
+        Ok(v) => crate::db::ApiResponseOld {
+            json: json!(v),
+            status: Status::Ok,
+        },
+        Err(e) => e,
+    }
+}
+
+#[get("/balance_sheet?<apikey>&<assets>&<liabilities>&<equity>&<time_stop>&<format>")]
+pub fn get_balance_sheet(
+    apikey: &RawStr,
+    assets: &RawStr,
+    liabilities: &RawStr,
+    equity: &RawStr,
+    time_stop: Option<&RawStr>,
+    format: Option<&RawStr>,
+    mut conn: crate::db::MyRocketSQLConn,
+) -> crate::db::ApiResponseOld {
+    // Which format shall we use for the sums?
+    let format = match parse_format(format) {
+        Ok(f) => f,
+        Err(e) => return e,
+    };
+
+    to_response(get_report_private(
+        apikey,
+        vec![("assets", assets), ("liabilities", liabilities), ("equity", equity)],
+        None,
+        time_stop,
+        format,
+        &mut *conn,
+    ))
+}
+
+#[get("/income_statement?<apikey>&<revenue>&<expense>&<time_start>&<time_stop>&<format>")]
+pub fn get_income_statement(
+    apikey: &RawStr,
+    revenue: &RawStr,
+    expense: &RawStr,
+    time_start: Option<&RawStr>,
+    time_stop: Option<&RawStr>,
+    format: Option<&RawStr>,
+    mut conn: crate::db::MyRocketSQLConn,
+) -> crate::db::ApiResponseOld {
+    // Which format shall we use for the sums?
+    let format = match parse_format(format) {
+        Ok(f) => f,
+        Err(e) => return e,
+    };
+
+    to_response(get_report_private(
+        apikey,
+        vec![("revenue", revenue), ("expense", expense)],
+        time_start,
+        time_stop,
+        format,
+        &mut *conn,
+    ))
+}
@@ -0,0 +1,91 @@
+use crate::db::{CurrencySymbol, TrialBalance, TrialBalanceAccount, TrialBalanceCurrency};
+use crate::dfp::dfp::{DFP, FormattedJson, Sign, dfp_add};
+use crate::format::parse_format;
+use crate::ledger::account_sums;
+use rocket::get;
+use rocket::http::{RawStr, Status};
+use rocket_contrib::json;
+
+/*
+Produce a trial balance.  That is, every account along with the sum of its distributions, grouped by currency.  Each currency also has a total of the sums of its accounts, which should be zero.  Accounts that have no distributions are listed with a sum of zero.
+
+The optional time_start and time_stop params have the same meaning as they do for account_dist_sum.
+
+Given an optional format param of digits, decimal, or significand, express the sums using that DFPFormat. The default is digits.
+ */
+#[get("/trial_balance?<apikey>&<time_start>&<time_stop>&<format>")]
+pub fn get_trial_balance(
+    apikey: &RawStr,
+    time_start: Option<&RawStr>,
+    time_stop: Option<&RawStr>,
+    format: Option<&RawStr>,
+    mut conn: crate::db::MyRocketSQLConn,
+) -> crate::db::ApiResponseOld {
+    // 1. Which format shall we use for the sums?
+    let format = match parse_format(format) {
+        Ok(f) => f,
+        Err(e) => return e,
+    };
+
+    // 2. Get all of the accounts, in the order that we want to list them.
+    // We receive these arguments as &RawStr.  We must convert them into a form that the mysql parametrization can use.
+    let apikey = apikey.html_escape().to_mut().clone();
+    let accounts: Vec<(u32, String, u32, String)> = conn
+        .prep_exec(
+            "
+            SELECT ac.id, ac.title, cu.id, cu.symbol
+            FROM accounts AS ac
+            JOIN currencies AS cu ON cu.id = ac.currency_id
+            WHERE ac.apikey = :apikey
+            ORDER BY cu.id, ac.id
+            ",
+            vec![apikey.clone()],
+        )
+        .map(|result| {
+            result
+                .map(|x| x.unwrap())
+                .map(|row| rocket_contrib::databases::mysql::from_row(row))
+                .collect()
+        })
+        .unwrap();
+
+    // 3. Now sum the distributions of each account.
+    let mut sums = match account_sums(&mut *conn, &apikey, time_start, time_stop) {
+        Ok(sums) => sums,
+        Err(err) => {
+            return crate::db::ApiResponseOld {
+                json: json!({"error": err.to_string()}),
+                status: Status::Ok,
+            }
+        }
+    };
+
+    // 4. Group the accounts by currency.  Because of the ORDER BY, all the accounts of a currency are adjacent.
+    let mut currencies: Vec<TrialBalanceCurrency> = Vec::new();
+    for (account_id, title, currency_id, symbol) in accounts {
+        let sum = sums
+            .remove(&account_id)
+            .unwrap_or(DFP { amount: vec![], exp: 0, sign: Sign::Zero });
+
+        if currencies.last().map(|c| c.currency.currency_id) != Some(currency_id) {
+            currencies.push(TrialBalanceCurrency {
+                currency: CurrencySymbol { currency_id, symbol },
+                accounts: Vec::new(),
+                total: DFP { amount: vec![], exp: 0, sign: Sign::Zero },
+            });
+        }
+
+        let c = currencies.last_mut().unwrap();
+        c.total = dfp_add(c.total.clone(), sum.clone());
+        c.accounts.push(TrialBalanceAccount {
+            account_id,
+            title,
+            sum,
+        });
+    }
+
+    crate::db::ApiResponseOld {
+        json: json!(TrialBalance { currencies }.formatted(&format)),
+        status: Status::Ok,
+    }
+}
@@ -0,0 +1,82 @@
+use crate::db::{LinterFix, Mutation, MyRocketSQLConn, PostLinterFixResponse};
+use crate::linter::{Linter, apply, select};
+use rocket::post;
+use rocket_contrib::databases::mysql;
+use rocket_contrib::databases::mysql::prelude::GenericConnection;
+use rocket_contrib::json::Json;
+
+/*
+Run the fixable linter rules, or only those selected by an optional comma delimited list of rule ids, and return the mutations that will fix their findings.  Please see crate::linter.
+
+By default this is a dry-run and we merely return the mutations.  Given apply=true, apply the mutations, all in a single db transaction, and return the mutations that we made.  No rule will touch a sealed transaction, so this never breaks the seals.
+ */
+#[post("/linter/fix", data = "<fix>")]
+pub fn post_linter_fix(
+    fix: rocket::request::Form<LinterFix>,
+    mut conn: MyRocketSQLConn,
+) -> Json<PostLinterFixResponse> {
+    if !fix.apply.unwrap_or(false) {
+        return match fixable(&fix.rules).and_then(|linters| {
+            mutations(&mut *conn, &fix.apikey, &linters).map_err(|err| err.to_string())
+        }) {
+            Ok(v) => Json(PostLinterFixResponse::Many(v)),
+            Err(err) => Json(PostLinterFixResponse::Error(err)),
+        };
+    }
+
+    // Run the linters and the mutations inside the same db transaction, so that we fix exactly what we found.
+    let mut tx = match conn.start_transaction(false, None, None) {
+        Ok(tx) => tx,
+        Err(err) => return Json(PostLinterFixResponse::Error(err.to_string())),
+    };
+
+    let linters = match fixable(&fix.rules) {
+        Ok(linters) => linters,
+        Err(err) => return Json(PostLinterFixResponse::Error(err)),
+    };
+
+    let result = mutations(&mut tx, &fix.apikey, &linters)
+        .map_err(|err| err.to_string())
+        .and_then(|v| {
+            for m in &v {
+                apply(&mut tx, &fix.apikey, m)?;
+            }
+            Ok(v)
+        });
+
+    match result {
+        Ok(v) => match tx.commit() {
+            Ok(_) => Json(PostLinterFixResponse::Many(v)),
+            Err(err) => Json(PostLinterFixResponse::Error(err.to_string())),
+        },
+        Err(err) => {
+            let _ = tx.rollback();
+            Json(PostLinterFixResponse::Error(err))
+        }
+    }
+}
+
+// Select the rules to fix.  If specific rules were requested, they must all be fixable.  Else just quietly use the fixable ones.
+fn fixable<C: GenericConnection>(rules: &Option<String>) -> Result<Vec<Box<dyn Linter<C>>>, String> {
+    let linters = select(rules.clone())?;
+    if rules.is_some() {
+        if let Some(linter) = linters.iter().find(|linter| !linter.fixable()) {
+            return Err(format!("{} is not fixable.", linter.id()));
+        }
+    }
+    Ok(linters.into_iter().filter(|linter| linter.fixable()).collect())
+}
+
+// Run the given linters and collect the mutations that will fix their findings.
+fn mutations<C: GenericConnection>(
+    conn: &mut C,
+    apikey: &String,
+    linters: &Vec<Box<dyn Linter<C>>>,
+) -> Result<Vec<Mutation>, mysql::Error> {
+    let mut ret_val = Vec::new();
+    for linter in linters {
+        let findings = linter.run(conn, apikey)?;
+        ret_val.append(&mut linter.fix(conn, apikey, &findings)?);
+    }
+    Ok(ret_val)
+}
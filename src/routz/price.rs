@@ -0,0 +1,132 @@
+use crate::db::{APIResponse, GetPriceResponse, MyRocketSQLConn, Price, PriceShort};
+use crate::ledger::latest_price;
+use lazy_static::lazy_static;
+use regex::Regex;
+use rocket::http::RawStr;
+use rocket_contrib::json::Json;
+
+/* A price records the exchange rate between a pair of currencies at a point in time.  One unit of currency_id is worth rate units of base_currency_id.  The rate is a decimal floating point number.  Its significand is a string of digits, like a distribution's amountbt, and its exponent is rate_exp.
+
+A rate can't be negative, so its significand must contain nothing but digits.  Nor can it be zero, because a zero rate would silently wipe out every amount that we convert with it.
+ */
+pub fn check_rate(rate: &String) -> Result<(), String> {
+    // Compile this only once, not once per price.
+    lazy_static! {
+        static ref RATE: Regex = Regex::new(r"^[0-9]*[1-9][0-9]*$").unwrap();
+    }
+    if RATE.is_match(rate) {
+        Ok(())
+    } else {
+        Err(String::from("rate must contain nothing but digits, and at least one of them must not be zero."))
+    }
+}
+
+#[rocket::delete("/price/<id>?<apikey>")]
+pub fn delete_price(id: &RawStr, apikey: &RawStr, mut conn: MyRocketSQLConn) -> Json<APIResponse> {
+    let mut params = Vec::new();
+
+    // We receive these arguments as &RawStr.  We must convert them into a form that the mysql parametrization can use.
+    params.push(id.html_escape().to_mut().clone());
+    params.push(apikey.html_escape().to_mut().clone());
+
+    match conn.prep_exec(
+        "DELETE from prices where id = :id and apikey = :apikey",
+        params,
+    ) {
+        Ok(result) => Json(APIResponse::Info(
+            String::from_utf8_lossy(&result.info()).to_string(),
+        )),
+        Err(err) => Json(APIResponse::Error(err.to_string())),
+    }
+}
+
+#[rocket::get("/price/<id>?<apikey>")]
+pub fn get_price(id: &RawStr, apikey: &RawStr, mut conn: MyRocketSQLConn) -> Json<GetPriceResponse> {
+    let mut params = Vec::new();
+
+    // We receive these arguments as &RawStr.  We must convert them into a form that the mysql parametrization can use.
+    params.push(id.html_escape().to_mut().clone());
+    params.push(apikey.html_escape().to_mut().clone());
+
+    let vec: Vec<Price> =
+        conn.prep_exec("SELECT id, apikey, currency_id, base_currency_id, time, rate, rate_exp from prices where id = :id and apikey = :apikey", params)
+            .map(|result| {
+                result.map(|x| x.unwrap()).map(|row| {
+                    let (id, apikey, currency_id, base_currency_id, time, rate, rate_exp) = rocket_contrib::databases::mysql::from_row(row);
+                    Price {id, apikey, currency_id, base_currency_id, time, rate, rate_exp}
+                }).collect()
+            }).unwrap();
+
+    match vec.len() {
+        0 => Json(GetPriceResponse::Error(String::from("record not found"))),
+        1 => Json(GetPriceResponse::One(vec.get(0).unwrap().clone())),
+        _ => Json(GetPriceResponse::Error(String::from(
+            "ID01T Max fubar error. More than one record found. This does not compute.",
+        ))),
+    }
+}
+
+#[rocket::get("/prices?<apikey>")]
+pub fn get_prices(apikey: &RawStr, mut conn: MyRocketSQLConn) -> Json<GetPriceResponse> {
+    // We receive apikey as &RawStr.  We must convert it into a form that the mysql parametrization can use.
+    let mut params = Vec::new();
+    params.push(apikey.html_escape().to_mut().clone());
+
+    let vec: Vec<Price> =
+        conn.prep_exec("SELECT id, apikey, currency_id, base_currency_id, time, rate, rate_exp from prices where apikey = :apikey order by time", params)
+            .map(|result| {
+                result.map(|x| x.unwrap()).map(|row| {
+                    let (id, apikey, currency_id, base_currency_id, time, rate, rate_exp) = rocket_contrib::databases::mysql::from_row(row);
+                    Price {id, apikey, currency_id, base_currency_id, time, rate, rate_exp}
+                }).collect()
+            }).unwrap();
+
+    Json(GetPriceResponse::Many(vec))
+}
+
+// Find the most recent price of currency_id, in terms of base_currency_id, on or before the given time.
+#[rocket::get("/prices/lookup?<apikey>&<currency_id>&<base_currency_id>&<time>")]
+pub fn get_prices_lookup(
+    apikey: &RawStr,
+    currency_id: &RawStr,
+    base_currency_id: &RawStr,
+    time: &RawStr,
+    mut conn: MyRocketSQLConn,
+) -> Json<GetPriceResponse> {
+    // We receive these arguments as &RawStr.  We must convert them into a form that the mysql parametrization can use.
+    match latest_price(
+        &mut *conn,
+        &apikey.html_escape().to_mut().clone(),
+        &currency_id.html_escape().to_mut().clone(),
+        &base_currency_id.html_escape().to_mut().clone(),
+        Some(&time.html_escape().to_mut().clone()),
+    ) {
+        Ok(Some(price)) => Json(GetPriceResponse::One(price)),
+        Ok(None) => Json(GetPriceResponse::Error(String::from("record not found"))),
+        Err(err) => Json(GetPriceResponse::Error(err.to_string())),
+    }
+}
+
+#[rocket::post("/prices", data = "<price>")]
+pub fn post_price(price: rocket::request::Form<PriceShort>, mut conn: MyRocketSQLConn) -> Json<APIResponse> {
+    if let Err(err) = check_rate(&price.rate) {
+        return Json(APIResponse::Error(err));
+    }
+
+    match conn.prep_exec("INSERT INTO prices (apikey, currency_id, base_currency_id, time, rate, rate_exp) VALUES (:apikey, :currency_id, :base_currency_id, :time, :rate, :rate_exp)",(&price.apikey, &price.currency_id, &price.base_currency_id, &price.time, &price.rate, &price.rate_exp)) {
+        Ok(result) => Json(APIResponse::LastInsertId(result.last_insert_id())),
+        Err(err) => Json(APIResponse::Error(String::from(err.to_string())))
+    }
+}
+
+#[rocket::put("/prices", data = "<price>")]
+pub fn put_price(price: rocket::request::Form<Price>, mut conn: MyRocketSQLConn) -> Json<APIResponse> {
+    if let Err(err) = check_rate(&price.rate) {
+        return Json(APIResponse::Error(err));
+    }
+
+    match conn.prep_exec("UPDATE prices SET currency_id = :currency_id, base_currency_id = :base_currency_id, time = :time, rate = :rate, rate_exp = :rate_exp where id = :id and apikey = :apikey",(&price.currency_id, &price.base_currency_id, &price.time, &price.rate, &price.rate_exp, &price.id, &price.apikey)) {
+        Ok(result) => Json(APIResponse::Info(String::from_utf8_lossy(&result.info()).to_string())),
+        Err(err) => Json(APIResponse::Error(String::from(err.to_string()))),
+    }
+}
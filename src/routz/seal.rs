@@ -0,0 +1,272 @@
+use crate::db::{APIResponse, Apikey, BrokenSeal, MyRocketSQLConn, SealVerification};
+use rocket::http::{RawStr, Status};
+use rocket::State;
+use rocket_contrib::databases::mysql;
+use rocket_contrib::databases::mysql::prelude::GenericConnection;
+use rocket_contrib::json;
+use rocket_contrib::json::Json;
+use serde_json::Value;
+
+/*
+A tamper-evident hash chain over the transactions of an apikey.
+
+Sealing a transaction computes the HMAC-SHA256 of the transaction, its distributions, and the hash of the prior seal, and records it.  The first seal of an apikey uses GENESIS_HASH as its prior hash.  Because each hash covers the prior hash, changing any sealed transaction or distribution, via the API or directly in the db, breaks every link from that seal onward.  So does adding a distribution to, or deleting, a sealed transaction.
+
+The HMAC is keyed with a secret that the server receives at startup, via --seal_key or BCR_SEAL_KEY, and that is never stored in the db.  Otherwise anybody who can change a row could simply recompute every seal after it.  So only the server, or whoever else holds the key, can verify the chain.  The db still computes the underlying SHA-256 hashes.
+
+Transactions are only sealed upon request, because a transaction and its distributions are usually created with several requests.
+ */
+pub const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+// The HMAC block size of SHA-256, in bytes.
+const BLOCK_SIZE: usize = 64;
+
+/* The secret that keys the seals, already padded and xor'ed into the inner and outer keys of the HMAC construction, as hex.  Rocket manages this.
+ */
+pub struct SealKey {
+    inner_key: String,
+    outer_key: String,
+}
+
+impl SealKey {
+    // A longer key would have to be hashed first, and an empty one is no secret at all, so refuse both.
+    pub fn new(key: &str) -> Result<SealKey, String> {
+        let bytes = key.as_bytes();
+        if bytes.is_empty() || bytes.len() > BLOCK_SIZE {
+            return Err(format!(
+                "The seal key must be between 1 and {} bytes long.",
+                BLOCK_SIZE
+            ));
+        }
+
+        let pad = |x: u8| -> String {
+            (0..BLOCK_SIZE)
+                .map(|i| format!("{:02x}", bytes.get(i).unwrap_or(&0) ^ x))
+                .collect()
+        };
+        Ok(SealKey {
+            inner_key: pad(0x36),
+            outer_key: pad(0x5c),
+        })
+    }
+}
+
+/* Build the string to hash for the given transaction and prior hash, or None if the transaction doesn't exist.  This is a json array so that no field can bleed into another.
+ */
+fn seal_payload<C: GenericConnection>(
+    conn: &mut C,
+    apikey: &String,
+    transaction_id: u32,
+    prior_hash: &String,
+) -> Result<Option<String>, mysql::Error> {
+    let transactions: Vec<(u32, String, String)> = conn
+        .prep_exec(
+            "SELECT id, time, notes FROM transactions WHERE id = :id AND apikey = :apikey",
+            vec![transaction_id.to_string(), apikey.clone()],
+        )?
+        .map(|x| x.unwrap())
+        .map(|row| rocket_contrib::databases::mysql::from_row(row))
+        .collect();
+
+    let (id, time, notes) = match transactions.into_iter().next() {
+        None => return Ok(None),
+        Some(t) => t,
+    };
+
+    let distributions: Vec<Value> = conn
+        .prep_exec(
+            "
+            SELECT id, account_id, amountbt, amount_exp
+            FROM distributions
+            WHERE transaction_id = :transaction_id AND apikey = :apikey
+            ORDER BY id
+            ",
+            vec![transaction_id.to_string(), apikey.clone()],
+        )?
+        .map(|x| x.unwrap())
+        .map(|row| {
+            let (id, account_id, amountbt, amount_exp): (u32, u32, String, i8) =
+                rocket_contrib::databases::mysql::from_row(row);
+            serde_json::json!([id, account_id, amountbt, amount_exp])
+        })
+        .collect();
+
+    Ok(Some(
+        serde_json::json!([prior_hash, id, time, notes, distributions]).to_string(),
+    ))
+}
+
+// Ask the db for the HMAC-SHA256 of the given string, as hex.  This is SHA2(outer_key || SHA2(inner_key || s)).
+fn hmac_sha256<C: GenericConnection>(
+    conn: &mut C,
+    seal_key: &SealKey,
+    s: &String,
+) -> Result<String, mysql::Error> {
+    let vec: Vec<String> = conn
+        .prep_exec(
+            "
+            SELECT LOWER(SHA2(CONCAT(UNHEX(:outer_key), UNHEX(SHA2(CONCAT(UNHEX(:inner_key), CAST(:s AS BINARY)), 256))), 256))
+            ",
+            vec![
+                seal_key.outer_key.clone(),
+                seal_key.inner_key.clone(),
+                s.clone(),
+            ],
+        )?
+        .map(|x| x.unwrap())
+        .map(|row| rocket_contrib::databases::mysql::from_row(row))
+        .collect();
+    Ok(vec.into_iter().next().unwrap_or_default())
+}
+
+// Seal all of the transactions of an apikey that aren't sealed yet, in the order they were created.
+#[rocket::post("/seals", data = "<apikey>")]
+pub fn post_seals(
+    apikey: rocket::request::Form<Apikey>,
+    seal_key: State<SealKey>,
+    mut conn: MyRocketSQLConn,
+) -> Json<APIResponse> {
+    let mut tx = match conn.start_transaction(false, None, None) {
+        Ok(tx) => tx,
+        Err(err) => return Json(APIResponse::Error(err.to_string())),
+    };
+
+    // 1. Where does the chain end now?  Lock it so nobody else can extend it at the same time.
+    let prior: Vec<String> = match tx.prep_exec(
+        "SELECT hash FROM seals WHERE apikey = :apikey ORDER BY id DESC LIMIT 1 FOR UPDATE",
+        vec![apikey.apikey.clone()],
+    ) {
+        Ok(result) => result
+            .map(|x| x.unwrap())
+            .map(|row| rocket_contrib::databases::mysql::from_row(row))
+            .collect(),
+        Err(err) => return Json(APIResponse::Error(err.to_string())),
+    };
+    let mut prior_hash = prior
+        .into_iter()
+        .next()
+        .unwrap_or_else(|| String::from(GENESIS_HASH));
+
+    // 2. Which transactions aren't sealed yet?
+    let transaction_ids: Vec<u32> = match tx.prep_exec(
+        "
+        SELECT tx.id
+        FROM transactions AS tx
+        LEFT JOIN seals AS se ON se.transaction_id = tx.id AND se.apikey = tx.apikey
+        WHERE tx.apikey = :apikey AND se.id IS NULL
+        ORDER BY tx.id
+        ",
+        vec![apikey.apikey.clone()],
+    ) {
+        Ok(result) => result
+            .map(|x| x.unwrap())
+            .map(|row| rocket_contrib::databases::mysql::from_row(row))
+            .collect(),
+        Err(err) => return Json(APIResponse::Error(err.to_string())),
+    };
+
+    // 3. Now extend the chain.
+    let mut cnt = 0;
+    for transaction_id in transaction_ids {
+        // A transaction that was deleted after we found it has nothing left to seal.  Don't seal it as if it were empty.
+        let payload = match seal_payload(&mut tx, &apikey.apikey, transaction_id, &prior_hash) {
+            Ok(Some(payload)) => payload,
+            Ok(None) => continue,
+            Err(err) => {
+                let _ = tx.rollback();
+                return Json(APIResponse::Error(err.to_string()));
+            }
+        };
+
+        let result = hmac_sha256(&mut tx, &seal_key, &payload)
+            .and_then(|hash| {
+                tx.prep_exec(
+                    "INSERT INTO seals (apikey, transaction_id, hash) VALUES (:apikey, :transaction_id, :hash)",
+                    (&apikey.apikey, &transaction_id, &hash),
+                )?;
+                Ok(hash)
+            });
+
+        match result {
+            Ok(hash) => prior_hash = hash,
+            Err(err) => {
+                let _ = tx.rollback();
+                return Json(APIResponse::Error(err.to_string()));
+            }
+        }
+        cnt = cnt + 1;
+    }
+
+    match tx.commit() {
+        Ok(_) => Json(APIResponse::Info(format!("{} transactions sealed.", cnt))),
+        Err(err) => Json(APIResponse::Error(err.to_string())),
+    }
+}
+
+// Recompute the hash chain of an apikey and report the first seal that doesn't match, if any.
+#[rocket::get("/seals/verify?<apikey>")]
+pub fn get_seals_verify(
+    apikey: &RawStr,
+    seal_key: State<SealKey>,
+    mut conn: MyRocketSQLConn,
+) -> crate::db::ApiResponseOld {
+    // We receive apikey as &RawStr.  We must convert it into a form that the mysql parametrization can use.
+    let apikey = apikey.html_escape().to_mut().clone();
+
+    let result = (|| -> Result<SealVerification, mysql::Error> {
+        let seals: Vec<(u32, u32, String)> = conn
+            .prep_exec(
+                "SELECT id, transaction_id, hash FROM seals WHERE apikey = :apikey ORDER BY id",
+                vec![apikey.clone()],
+            )?
+            .map(|x| x.unwrap())
+            .map(|row| rocket_contrib::databases::mysql::from_row(row))
+            .collect();
+
+        let seal_cnt = seals.len() as u32;
+        let mut prior_hash = String::from(GENESIS_HASH);
+        for (seal_id, transaction_id, hash) in seals {
+            let reason = match seal_payload(&mut *conn, &apikey, transaction_id, &prior_hash)? {
+                None => Some(format!("transaction {} is missing.", transaction_id)),
+                Some(payload) => {
+                    if hmac_sha256(&mut *conn, &seal_key, &payload)? == hash {
+                        None
+                    } else {
+                        Some(format!(
+                            "transaction {}, its distributions, or the prior seal, have changed.",
+                            transaction_id
+                        ))
+                    }
+                }
+            };
+
+            if let Some(reason) = reason {
+                return Ok(SealVerification {
+                    seal_cnt,
+                    broken: Some(BrokenSeal {
+                        seal_id,
+                        transaction_id,
+                        reason,
+                    }),
+                });
+            }
+            prior_hash = hash;
+        }
+
+        Ok(SealVerification {
+            seal_cnt,
+            broken: None,
+        })
+    })();
+
+    match result {
+        Ok(v) => crate::db::ApiResponseOld {
+            json: json!(v),
+            status: Status::Ok,
+        },
+        Err(err) => crate::db::ApiResponseOld {
+            json: json!({"error": err.to_string()}),
+            status: Status::Ok,
+        },
+    }
+}
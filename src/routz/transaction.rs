@@ -1,28 +1,53 @@
+use crate::clauses::{time_clause, trancat_clause};
+use crate::constants::{DEFAULT_PAGE_LIMIT, MAX_PAGE_LIMIT};
 use crate::db::{
-    APIResponse, GetTransactionResponse, MyRocketSQLConn, Transaction, TransactionShort,
+    APIResponse, CompoundDistribution, CompoundTransaction, CompoundTransactionIds, CompoundTransactionUpdate, GetTransactionResponse,
+    GetUnbalancedResponse, MyRocketSQLConn, PostCompoundTransactionResponse, Transaction,
+    TransactionPage, TransactionSearch, TransactionShort,
 };
+use crate::dfp::dfp::DFP;
+use crate::idlist::id_placeholders;
+use crate::ledger::{enforce_balance, unbalanced};
+use crate::routz::distribution::check_amounts;
 use rocket::http::RawStr;
+use rocket::request::LenientForm;
+use rocket_contrib::databases::mysql;
 use rocket_contrib::json::Json;
 
+/* Delete a transaction, along with its distributions and trancats, in a single db transaction.  Either all of them are deleted, or none of them are.  Nothing remains to be unbalanced, so this is how to get rid of a balanced transaction in strict mode.
+ */
 #[rocket::delete("/transaction/<id>?<apikey>")]
 pub fn delete_transaction(
     id: &RawStr,
     apikey: &RawStr,
     mut conn: MyRocketSQLConn,
 ) -> Json<APIResponse> {
-    let mut params = Vec::new();
-
     // We receive these arguments as &RawStr.  We must convert them into a form that the mysql parametrization can use.
-    params.push(id.html_escape().to_mut().clone());
-    params.push(apikey.html_escape().to_mut().clone());
+    let id = id.html_escape().to_mut().clone();
+    let apikey = apikey.html_escape().to_mut().clone();
 
-    match conn.prep_exec(
-        "DELETE from transactions where id = :id and apikey = :apikey",
-        params,
-    ) {
-        Ok(result) => Json(APIResponse::Info(
-            String::from_utf8_lossy(&result.info()).to_string(),
-        )),
+    let mut tx = match conn.start_transaction(false, None, None) {
+        Ok(tx) => tx,
+        Err(err) => return Json(APIResponse::Error(err.to_string())),
+    };
+
+    let result = delete_children(&mut tx, &apikey, &id).and_then(|_| {
+        tx.prep_exec(
+            "DELETE from transactions where id = :id and apikey = :apikey",
+            (&id, &apikey),
+        )
+        .map(|result| String::from_utf8_lossy(&result.info()).to_string())
+    });
+    let info = match result {
+        Ok(info) => info,
+        Err(err) => {
+            let _ = tx.rollback();
+            return Json(APIResponse::Error(err.to_string()));
+        }
+    };
+
+    match tx.commit() {
+        Ok(_) => Json(APIResponse::Info(info)),
         Err(err) => Json(APIResponse::Error(err.to_string())),
     }
 }
@@ -71,37 +96,273 @@ pub fn get_transaction(
     }
 }
 
-#[rocket::get("/transactions?<apikey>")]
+/* A cursor marks the last transaction of a page, so that the next page can resume after it.  The caller should treat it as opaque.  It's really the hex encoded json array [sort, id, time], so it's safe to use in a url as-is.
+ */
+fn encode_cursor(sort: &str, transaction: &Transaction) -> String {
+    serde_json::json!([sort, transaction.id, transaction.time])
+        .to_string()
+        .bytes()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+// Recover the id and time of the last transaction of the prior page.  The cursor must have been made using the same sort.
+fn decode_cursor(sort: &str, cursor: &str) -> Result<(u32, String), String> {
+    let err = || format!("{} is not a cursor for sort {}.", cursor, sort);
+    let bytes = (0..cursor.len())
+        .step_by(2)
+        .map(|i| cursor.get(i..i + 2).and_then(|h| u8::from_str_radix(h, 16).ok()))
+        .collect::<Option<Vec<u8>>>()
+        .ok_or_else(err)?;
+    let (s, id, time): (String, u32, String) = serde_json::from_slice(&bytes).map_err(|_| err())?;
+    if s != sort {
+        return Err(err());
+    }
+    Ok((id, time))
+}
+
+// Escape the wildcards of a LIKE pattern, so that we can search for a literal substring.
+fn escape_like(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")
+}
+
+/*
+Find the transactions of an apikey.  Given none of the optional params, these are all of the transactions, ordered by id.
+
+Given optional time_start and time_stop params, filter the transactions by time, as does account_dist_sum.
+
+Given an optional notes param, only find the transactions whose notes contain it.  Given an optional boolean fulltext param that is true, use a natural language full-text search of the notes instead.
+
+Given an optional comma delimited list of account_id, only find the transactions that have a distribution for any of those accounts.  Given optional amount_min and/or amount_max params, which are human-readable decimal text such as "-1234.56", only find the transactions that have a distribution whose amount is within that range.  If both are given, the same distribution must satisfy them both.  The db compares the amounts as DECIMAL(65,30), so amount_min and amount_max must have no more than 35 integer digits and 30 decimal places.
+
+Given an optional comma delimited list of category_id, only find the transactions tagged with any of those categories, as per /trancats.
+
+Given an optional sort param of id, -id, time, or -time, order the transactions that way.  A leading - means descending.  Transactions with the same time are ordered by id.  The default is id.
+
+Given an optional limit param, or an optional cursor param, return a page of no more than limit transactions, along with a next_cursor.  Send the next_cursor back, along with the same params, to get the next page.  When there are no more pages, next_cursor is null.  The default limit is DEFAULT_PAGE_LIMIT.  Else return all of the transactions, as a plain list.
+ */
+#[rocket::get("/transactions?<apikey>&<search..>")]
 pub fn get_transactions(
     apikey: &RawStr,
+    search: LenientForm<TransactionSearch>,
     mut conn: MyRocketSQLConn,
 ) -> Json<GetTransactionResponse> {
+    match get_transactions_private(apikey, &search, &mut *conn) {
+        Ok(response) => Json(response),
+        Err(e) => Json(GetTransactionResponse::Error(e)),
+    }
+}
+
+fn get_transactions_private(
+    apikey: &RawStr,
+    search: &TransactionSearch,
+    conn: &mut mysql::Conn,
+) -> Result<GetTransactionResponse, String> {
+    // 1. Examine the params.
+    let sort = search.sort.clone().unwrap_or_else(|| String::from("id"));
+    let order_by = match sort.as_str() {
+        "id" => "tx.id",
+        "-id" => "tx.id DESC",
+        "time" => "tx.time, tx.id",
+        "-time" => "tx.time DESC, tx.id DESC",
+        _ => return Err(format!("{} is not a sort.  Please use id, -id, time, or -time.", sort)),
+    };
+
+    let fulltext: bool = match &search.fulltext {
+        None => false,
+        Some(b) => b.parse().map_err(|e: std::str::ParseBoolError| e.to_string())?,
+    };
+
+    let account_id = search.account_id.clone().transpose().map_err(|e| format!("account_id {}", e))?;
+    let category_id = search.category_id.clone().transpose().map_err(|e| format!("category_id {}", e))?;
+
+    // The amounts are compared by the db as DECIMAL(65,30), so give it canonical decimal text that fits.  That's no more than 35 integer digits and 30 decimal places.
+    let parse_amount = |name: &str, amount: &Option<String>| -> Result<Option<String>, String> {
+        match amount {
+            None => Ok(None),
+            Some(a) => {
+                let d = a
+                    .parse::<DFP>()
+                    .map_err(|e| format!("{} {} is not a valid number. {}", name, a, e))?;
+                let integer_digits = d.amount.len() as i32 + d.exp as i32;
+                let decimal_places = -(d.exp as i32);
+                if integer_digits > 35 || decimal_places > 30 {
+                    return Err(format!(
+                        "{} {} must have no more than 35 integer digits and 30 decimal places.",
+                        name, a
+                    ));
+                }
+                Ok(Some(d.to_string()))
+            }
+        }
+    };
+    let amount_min = parse_amount("amount_min", &search.amount_min)?;
+    let amount_max = parse_amount("amount_max", &search.amount_max)?;
+
+    let paged = search.limit.is_some() || search.cursor.is_some();
+    let limit: u32 = match &search.limit {
+        None => DEFAULT_PAGE_LIMIT,
+        Some(l) => l.parse().map_err(|_| String::from("limit must be a positive integer."))?,
+    };
+    if limit < 1 {
+        return Err(String::from("limit must be a positive integer."));
+    }
+    if limit > MAX_PAGE_LIMIT {
+        return Err(format!("limit must be no more than {}.", MAX_PAGE_LIMIT));
+    }
+    let cursor = match &search.cursor {
+        None => None,
+        Some(c) => Some(decode_cursor(&sort, c)?),
+    };
+
+    // 2. Build the query.
     // We receive apikey as &RawStr.  We must convert it into a form that the mysql parametrization can use.
+    // WARNING! Push these in the same order they are used in the prep_exec function!
+    let apikey = apikey.html_escape().to_mut().clone();
     let mut params = Vec::new();
-    params.push(apikey.html_escape().to_mut().clone());
+    params.push(apikey.clone());
 
-    let vec: Vec<Transaction> = conn
-        .prep_exec(
-            "SELECT id, apikey, notes, time from transactions where apikey = :apikey",
-            params,
-        )
-        .map(|result| {
-            result
-                .map(|x| x.unwrap())
-                .map(|row| {
-                    let (id, apikey, notes, time) = rocket_contrib::databases::mysql::from_row(row);
-                    Transaction {
-                        id,
-                        apikey,
-                        notes,
-                        time,
-                    }
-                })
-                .collect()
+    // 2.1 time_start and time_stop are both optional.
+    let time_clause = time_clause(
+        search.time_start.as_ref().map(|t| RawStr::from_str(t)),
+        search.time_stop.as_ref().map(|t| RawStr::from_str(t)),
+        &mut params,
+    );
+
+    // 2.2 Search the notes.
+    let notes_clause = match &search.notes {
+        None => "",
+        Some(notes) if fulltext => {
+            params.push(notes.clone());
+            "AND MATCH (tx.notes) AGAINST (:notes IN NATURAL LANGUAGE MODE)"
+        }
+        Some(notes) => {
+            params.push(format!("%{}%", escape_like(notes)));
+            "AND tx.notes LIKE :notes"
+        }
+    };
+
+    // 2.3 Examine the distributions.
+    let mut distribution_clause = String::new();
+    if account_id.is_some() || amount_min.is_some() || amount_max.is_some() {
+        params.push(apikey.clone());
+        let mut filters = String::new();
+        if let Some(ids) = &account_id {
+            filters.push_str(&format!(
+                " AND ds.account_id IN ({})",
+                id_placeholders("account_id", &ids.0, &mut params)
+            ));
+        }
+        for (amount, name, op) in [(&amount_min, "amount_min", ">="), (&amount_max, "amount_max", "<=")].iter() {
+            if let Some(amount) = amount {
+                params.push(amount.clone());
+                filters.push_str(&format!(
+                    " AND CAST(CONCAT(ds.amountbt, 'e', ds.amount_exp) AS DECIMAL(65, 30)) {} CAST(:{} AS DECIMAL(65, 30))",
+                    op, name
+                ));
+            }
+        }
+        distribution_clause = format!(
+            "
+            AND tx.id IN (
+                SELECT ds.transaction_id
+                FROM distributions AS ds
+                WHERE ds.apikey = :apikey{}
+            )",
+            filters
+        );
+    }
+
+    // 2.4 Examine the categories that tag the transactions.
+    let category_clause = trancat_clause(&apikey, &category_id, &None, &mut params);
+
+    // 2.5 Resume after the last transaction of the prior page.
+    let cursor_clause = match cursor {
+        None => String::new(),
+        Some((id, time)) => {
+            let cmp = if sort.starts_with('-') { "<" } else { ">" };
+            if sort.ends_with("time") {
+                params.push(time.clone());
+                params.push(time);
+                params.push(id.to_string());
+                format!(
+                    "AND (tx.time {} :cursor_time OR (tx.time = :cursor_time AND tx.id {} :cursor_id))",
+                    cmp, cmp
+                )
+            } else {
+                params.push(id.to_string());
+                format!("AND tx.id {} :cursor_id", cmp)
+            }
+        }
+    };
+
+    // 2.6 Get one more transaction than the page can hold, so we know whether there's another page.  limit is an integer, so it's safe to place it in the query as-is.
+    let limit_clause = if paged {
+        format!("LIMIT {}", limit as u64 + 1)
+    } else {
+        String::new()
+    };
+
+    let q = format!(
+        "
+        SELECT tx.id, tx.apikey, tx.notes, tx.time
+        FROM transactions AS tx
+        WHERE tx.apikey = :apikey
+        {}
+        {}
+        {}
+        {}
+        {}
+        ORDER BY {}
+        {}
+        ",
+        time_clause, notes_clause, distribution_clause, category_clause, cursor_clause, order_by, limit_clause
+    );
+
+    // 3. Execute the query.
+    let mut vec: Vec<Transaction> = conn
+        .prep_exec(q, params)
+        .map_err(|err| err.to_string())?
+        .map(|x| x.unwrap())
+        .map(|row| {
+            let (id, apikey, notes, time) = rocket_contrib::databases::mysql::from_row(row);
+            Transaction {
+                id,
+                apikey,
+                notes,
+                time,
+            }
         })
-        .unwrap();
+        .collect();
+
+    if !paged {
+        return Ok(GetTransactionResponse::Many(vec));
+    }
+
+    // 4. Build the page.
+    let next_cursor = if vec.len() > limit as usize {
+        vec.truncate(limit as usize);
+        vec.last().map(|t| encode_cursor(&sort, t))
+    } else {
+        None
+    };
 
-    Json(GetTransactionResponse::Many(vec))
+    Ok(GetTransactionResponse::Page(TransactionPage {
+        transactions: vec,
+        next_cursor,
+    }))
+}
+
+// Find all transactions that are not balanced within each currency.  This works whether or not the apikey is strict.
+#[rocket::get("/transactions/unbalanced?<apikey>")]
+pub fn get_transactions_unbalanced(
+    apikey: &RawStr,
+    mut conn: MyRocketSQLConn,
+) -> Json<GetUnbalancedResponse> {
+    match unbalanced(&mut *conn, &apikey.html_escape().to_mut().clone(), None) {
+        Ok(v) => Json(GetUnbalancedResponse::Many(v)),
+        Err(err) => Json(GetUnbalancedResponse::Error(err.to_string())),
+    }
 }
 
 #[rocket::post("/transactions", data = "<transaction>")]
@@ -118,6 +379,174 @@ pub fn post_transaction(
     }
 }
 
+/* Create a transaction, its distributions, and its trancats, all in a single db transaction.  Either all of the records are created, or none of them are.
+
+Unlike the other POSTs, this one receives a json body.
+ */
+#[rocket::post("/transactions/compound", data = "<compound>")]
+pub fn post_transaction_compound(
+    compound: Json<CompoundTransaction>,
+    mut conn: MyRocketSQLConn,
+) -> Json<PostCompoundTransactionResponse> {
+    // 1. Don't bother the db if we can already see that the distributions are bad.
+    for d in &compound.distributions {
+        if let Err(err) = check_amounts(d.amount, &d.amountbt) {
+            return Json(PostCompoundTransactionResponse::Error(err));
+        }
+    }
+
+    // 2. Now insert everything.
+    let mut tx = match conn.start_transaction(false, None, None) {
+        Ok(tx) => tx,
+        Err(err) => return Json(PostCompoundTransactionResponse::Error(err.to_string())),
+    };
+
+    let ids = match tx
+        .prep_exec(
+            "INSERT INTO transactions (apikey, notes, time) VALUES (:apikey, :notes, :time)",
+            (&compound.apikey, &compound.notes, &compound.time),
+        )
+        .map(|result| result.last_insert_id())
+        .and_then(|transaction_id| {
+            insert_children(&mut tx, &compound.apikey, transaction_id, &compound.distributions, &compound.category_ids)
+        }) {
+        Ok(ids) => ids,
+        Err(err) => {
+            let _ = tx.rollback();
+            return Json(PostCompoundTransactionResponse::Error(err.to_string()));
+        }
+    };
+
+    // 3. If the apikey is strict, the new transaction must be balanced.  Please see crate::ledger.
+    if let Err(err) = enforce_balance(&mut tx, &compound.apikey, &vec![ids.transaction_id as u32]) {
+        let _ = tx.rollback();
+        return Json(PostCompoundTransactionResponse::Error(err));
+    }
+
+    match tx.commit() {
+        Ok(_) => Json(PostCompoundTransactionResponse::Ids(ids)),
+        Err(err) => Json(PostCompoundTransactionResponse::Error(err.to_string())),
+    }
+}
+
+/* Replace the notes, time, distributions, and trancats of an existing transaction, all in a single db transaction.  We check the balance only once, after all of the distributions have been replaced.  So in strict mode, this is how to change the amounts of a balanced transaction.
+
+Unlike the other PUTs, this one receives a json body.
+ */
+#[rocket::put("/transactions/compound", data = "<compound>")]
+pub fn put_transaction_compound(
+    compound: Json<CompoundTransactionUpdate>,
+    mut conn: MyRocketSQLConn,
+) -> Json<PostCompoundTransactionResponse> {
+    // 1. Don't bother the db if we can already see that the distributions are bad.
+    for d in &compound.distributions {
+        if let Err(err) = check_amounts(d.amount, &d.amountbt) {
+            return Json(PostCompoundTransactionResponse::Error(err));
+        }
+    }
+
+    // 2. Now replace everything.
+    let mut tx = match conn.start_transaction(false, None, None) {
+        Ok(tx) => tx,
+        Err(err) => return Json(PostCompoundTransactionResponse::Error(err.to_string())),
+    };
+
+    let result = put_transaction_compound_private(&mut tx, &compound);
+    let ids = match result {
+        Ok(ids) => ids,
+        Err(err) => {
+            let _ = tx.rollback();
+            return Json(PostCompoundTransactionResponse::Error(err));
+        }
+    };
+
+    // 3. If the apikey is strict, the replaced transaction must be balanced.  Please see crate::ledger.
+    if let Err(err) = enforce_balance(&mut tx, &compound.apikey, &vec![compound.id]) {
+        let _ = tx.rollback();
+        return Json(PostCompoundTransactionResponse::Error(err));
+    }
+
+    match tx.commit() {
+        Ok(_) => Json(PostCompoundTransactionResponse::Ids(ids)),
+        Err(err) => Json(PostCompoundTransactionResponse::Error(err.to_string())),
+    }
+}
+
+// Replace all the records of a CompoundTransactionUpdate.  The caller is responsible for the commit or rollback.
+fn put_transaction_compound_private(
+    tx: &mut mysql::Transaction,
+    compound: &CompoundTransactionUpdate,
+) -> Result<CompoundTransactionIds, String> {
+    // Lock the transaction so that nobody else can change it at the same time.
+    let found: Vec<u32> = tx
+        .prep_exec(
+            "SELECT id FROM transactions WHERE id = :id AND apikey = :apikey FOR UPDATE",
+            (&compound.id, &compound.apikey),
+        )
+        .map_err(|err| err.to_string())?
+        .map(|x| x.unwrap())
+        .map(|row| rocket_contrib::databases::mysql::from_row(row))
+        .collect();
+    if found.is_empty() {
+        return Err(format!("transaction {} does not exist.", compound.id));
+    }
+
+    tx.prep_exec(
+        "UPDATE transactions SET notes = :notes, time = :time WHERE id = :id AND apikey = :apikey",
+        (&compound.notes, &compound.time, &compound.id, &compound.apikey),
+    )
+    .map_err(|err| err.to_string())?;
+    delete_children(tx, &compound.apikey, &compound.id.to_string()).map_err(|err| err.to_string())?;
+    insert_children(tx, &compound.apikey, compound.id as u64, &compound.distributions, &compound.category_ids)
+        .map_err(|err| err.to_string())
+}
+
+// Delete the distributions and trancats of a transaction.  The caller is responsible for the commit or rollback.
+fn delete_children(tx: &mut mysql::Transaction, apikey: &String, transaction_id: &String) -> Result<(), mysql::Error> {
+    tx.prep_exec(
+        "DELETE FROM distributions WHERE transaction_id = :transaction_id AND apikey = :apikey",
+        (transaction_id, apikey),
+    )?;
+    tx.prep_exec(
+        "DELETE FROM transactions_categories WHERE transaction_id = :transaction_id AND apikey = :apikey",
+        (transaction_id, apikey),
+    )?;
+    Ok(())
+}
+
+// Insert the distributions and trancats of a transaction.  The caller is responsible for the commit or rollback.
+fn insert_children(
+    tx: &mut mysql::Transaction,
+    apikey: &String,
+    transaction_id: u64,
+    distributions: &Vec<CompoundDistribution>,
+    category_ids: &Vec<u32>,
+) -> Result<CompoundTransactionIds, mysql::Error> {
+    let mut distribution_ids = Vec::new();
+    for d in distributions {
+        let result = tx.prep_exec(
+            "INSERT INTO distributions (account_id, amount, amountbt, amount_exp, apikey, transaction_id) VALUES (:account_id, :amount, :amountbt, :amount_exp, :apikey, :transaction_id)",
+            (&d.account_id, &d.amount, &d.amountbt, &d.amount_exp, apikey, &transaction_id),
+        )?;
+        distribution_ids.push(result.last_insert_id());
+    }
+
+    let mut trancat_ids = Vec::new();
+    for category_id in category_ids {
+        let result = tx.prep_exec(
+            "INSERT INTO transactions_categories (apikey, transaction_id, category_id) VALUES (:apikey, :transaction_id, :category_id)",
+            (apikey, &transaction_id, category_id),
+        )?;
+        trancat_ids.push(result.last_insert_id());
+    }
+
+    Ok(CompoundTransactionIds {
+        transaction_id,
+        distribution_ids,
+        trancat_ids,
+    })
+}
+
 #[rocket::put("/transactions", data = "<transaction>")]
 pub fn put_transaction(
     transaction: rocket::request::Form<Transaction>,
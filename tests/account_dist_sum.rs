@@ -3,7 +3,7 @@ use rocket::http::Status;
 use rocket::local::Client;
 use bookwerx_core_rust::dfp::dfp::{DFP, Sign};
 
-pub fn account_dist_sum(client: &Client, apikey: &String, accounts: &Vec<D::AccountJoined>) -> () {
+pub fn account_dist_sum(client: &Client, apikey: &String, accounts: &Vec<D::AccountJoined>, categories: &Vec<D::Category>) -> () {
     // 1. GET /account_dist_sum, bad account_id, no time_*. sb 200. sb 0.
     let mut response = client
         .get(format!(
@@ -15,6 +15,14 @@ pub fn account_dist_sum(client: &Client, apikey: &String, accounts: &Vec<D::Acco
     assert_eq!(response.status(), Status::Ok);
     assert_eq!(r.sum, DFP { amount: vec![], exp: 0, sign: Sign::Zero });
 
+    // 1.1 An account_id that isn't an id at all. sb 200. sb error.
+    response = client
+        .get(format!("/account_dist_sum?apikey={}&account_id=A", &apikey))
+        .dispatch();
+    let v: serde_json::Value = serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap();
+    assert_eq!(response.status(), Status::Ok);
+    assert!(v["error"].is_string());
+
     // 2. Good account_id with the four permutations of time_*
 
     // 2.1 no time_*
@@ -64,4 +72,112 @@ pub fn account_dist_sum(client: &Client, apikey: &String, accounts: &Vec<D::Acco
     r = serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap();
     assert_eq!(response.status(), Status::Ok);
     assert_eq!(r.sum, DFP { amount: vec![4], exp: 0, sign: Sign::Positive });
+
+    // 3. The optional format param.
+
+    // 3.1 decimal
+    response = client
+        .get(format!(
+            "/account_dist_sum?apikey={}&account_id={}&format=decimal",
+            &apikey,
+            (accounts.get(0).unwrap()).id
+        ))
+        .dispatch();
+    let v: serde_json::Value = serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(v["sum"], serde_json::json!("12"));
+
+    // 3.2 significand.  DFPResult can parse this format too.
+    response = client
+        .get(format!(
+            "/account_dist_sum?apikey={}&account_id={}&format=significand",
+            &apikey,
+            (accounts.get(0).unwrap()).id
+        ))
+        .dispatch();
+    r = serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(r.sum, DFP { amount: vec![2, 1], exp: 0, sign: Sign::Positive });
+
+    // 3.3 unknown format
+    response = client
+        .get(format!(
+            "/account_dist_sum?apikey={}&account_id={}&format=hex",
+            &apikey,
+            (accounts.get(0).unwrap()).id
+        ))
+        .dispatch();
+    let v: serde_json::Value = serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap();
+    assert_eq!(response.status(), Status::Ok);
+    assert!(v["error"].is_string());
+
+    // 4. Filter by the categories that tag the transactions.  Only tx0 is tagged, with Assets and Specific customer.
+    let assets = categories.get(0).unwrap().id;
+    let liabilities = categories.get(1).unwrap().id;
+    let customer = categories.get(3).unwrap().id;
+
+    // 4.1 trancat_id
+    response = client
+        .get(format!(
+            "/account_dist_sum?apikey={}&account_id={}&trancat_id={}",
+            &apikey,
+            (accounts.get(0).unwrap()).id,
+            assets
+        ))
+        .dispatch();
+    r = serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(r.sum, DFP { amount: vec![3], exp: 0, sign: Sign::Positive });
+
+    // 4.2 trancat_id, with more than one category.  A transaction tagged with any of them will do.
+    response = client
+        .get(format!(
+            "/account_dist_sum?apikey={}&account_id={}&trancat_id={},{}",
+            &apikey,
+            (accounts.get(0).unwrap()).id,
+            liabilities,
+            customer
+        ))
+        .dispatch();
+    r = serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(r.sum, DFP { amount: vec![3], exp: 0, sign: Sign::Positive });
+
+    // 4.3 exclude_trancat_id
+    response = client
+        .get(format!(
+            "/account_dist_sum?apikey={}&account_id={}&exclude_trancat_id={}",
+            &apikey,
+            (accounts.get(0).unwrap()).id,
+            assets
+        ))
+        .dispatch();
+    r = serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(r.sum, DFP { amount: vec![9], exp: 0, sign: Sign::Positive });
+
+    // 4.4 exclude_trancat_id, along with time_stop.
+    response = client
+        .get(format!(
+            "/account_dist_sum?apikey={}&account_id={}&exclude_trancat_id={}&time_stop=2020-12",
+            &apikey,
+            (accounts.get(0).unwrap()).id,
+            customer
+        ))
+        .dispatch();
+    r = serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(r.sum, DFP { amount: vec![4], exp: 0, sign: Sign::Positive });
+
+    // 4.5 trancat_id must be a list of integers.
+    response = client
+        .get(format!(
+            "/account_dist_sum?apikey={}&account_id={}&trancat_id=A",
+            &apikey,
+            (accounts.get(0).unwrap()).id
+        ))
+        .dispatch();
+    let v: serde_json::Value = serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap();
+    assert_eq!(response.status(), Status::BadRequest);
+    assert!(v["error"].is_string());
 }
@@ -0,0 +1,66 @@
+use bookwerx_core_rust::db as D;
+use bookwerx_core_rust::dfp::dfp::{DFP, Sign};
+use rocket::http::Status;
+use rocket::local::Client;
+
+/* Cash in mattress has three distributions, 3, 4, and 5, in transactions dated 2020, 2020-12, and 2020-12-31.
+ */
+pub fn account_register(client: &Client, apikey: &String, accounts: &Vec<D::AccountJoined>) -> () {
+    let account_id: u32 = (accounts.get(0).unwrap()).id;
+    let positive = |amount: Vec<u8>| DFP { amount, exp: 0, sign: Sign::Positive };
+
+    // 1. GET /account_register, no time_*, no paging.
+    let mut response = client
+        .get(format!("/account_register?apikey={}&account_id={}", &apikey, account_id))
+        .dispatch();
+    let r: D::AccountRegister = serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(r.opening_balance, DFP { amount: vec![], exp: 0, sign: Sign::Zero });
+    assert_eq!(r.entry_cnt, 3);
+    assert_eq!(r.entries.len(), 3);
+    assert_eq!(r.entries[0].amount, positive(vec![3]));
+    assert_eq!(r.entries[0].balance, positive(vec![3]));
+    assert_eq!(r.entries[1].balance, positive(vec![7]));
+    assert_eq!(r.entries[2].balance, positive(vec![2, 1]));
+    assert_eq!(r.closing_balance, positive(vec![2, 1]));
+
+    // 2. GET /account_register, time_start, and the 2nd page of 1 entry each.
+    response = client
+        .get(format!(
+            "/account_register?apikey={}&account_id={}&time_start=2020-12&offset=1&limit=1",
+            &apikey, account_id
+        ))
+        .dispatch();
+    let r: D::AccountRegister = serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(r.opening_balance, positive(vec![3]));
+    assert_eq!(r.entry_cnt, 2);
+    assert_eq!(r.entries.len(), 1);
+    assert_eq!(r.entries[0].time, "2020-12-31");
+    assert_eq!(r.entries[0].amount, positive(vec![5]));
+    assert_eq!(r.entries[0].balance, positive(vec![2, 1]));
+    assert_eq!(r.closing_balance, positive(vec![2, 1]));
+
+    // 3. GET /account_register, time_stop, decimal.
+    response = client
+        .get(format!(
+            "/account_register?apikey={}&account_id={}&time_stop=2020-12&format=decimal",
+            &apikey, account_id
+        ))
+        .dispatch();
+    let v: serde_json::Value = serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(v["entries"].as_array().unwrap().len(), 2);
+    assert_eq!(v["closing_balance"], serde_json::json!("7"));
+
+    // 4. GET /account_register, with a bad limit.
+    response = client
+        .get(format!(
+            "/account_register?apikey={}&account_id={}&limit=many",
+            &apikey, account_id
+        ))
+        .dispatch();
+    let v: serde_json::Value = serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap();
+    assert_eq!(response.status(), Status::Ok);
+    assert!(v["error"].as_str().unwrap().contains("limit"));
+}
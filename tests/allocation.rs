@@ -0,0 +1,83 @@
+use bookwerx_core_rust::db as D;
+use rocket::http::Status;
+use rocket::local::Client;
+
+pub fn allocation(
+    client: &Client,
+    apikey: &String,
+    accounts: &Vec<D::AccountJoined>,
+    transactions: &Vec<D::Transaction>,
+) {
+    let account_id0: u32 = (*accounts.get(0).unwrap()).id;
+    let account_id1: u32 = (*accounts.get(1).unwrap()).id;
+    let transaction_id0: u32 = (*transactions.get(0).unwrap()).id;
+
+    // 1. GET /allocation with a different quantity of account_id and weight. sb an error.
+    let mut response = client
+        .get(format!(
+            "/allocation?apikey={}&transaction_id={}&amount=100&exp=0&account_id={}&weight=1,1",
+            &apikey, transaction_id0, account_id0
+        ))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    match serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap() {
+        D::GetAllocationResponse::Error(_) => assert!(true),
+        _ => assert!(false),
+    }
+
+    // 2. GET /allocation with an unparsable amount. sb an error.
+    response = client
+        .get(format!(
+            "/allocation?apikey={}&transaction_id={}&amount=1.2.3&exp=0&account_id={},{}&weight=1,1",
+            &apikey, transaction_id0, account_id0, account_id1
+        ))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    match serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap() {
+        D::GetAllocationResponse::Error(_) => assert!(true),
+        _ => assert!(false),
+    }
+
+    // 3. Split 1.01 across two accounts with weights 1 and 1.  The leftover penny goes to the first account.
+    response = client
+        .get(format!(
+            "/allocation?apikey={}&transaction_id={}&amount=1.01&exp=-2&account_id={},{}&weight=1,1",
+            &apikey, transaction_id0, account_id0, account_id1
+        ))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    match serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap() {
+        D::GetAllocationResponse::Many(v) => {
+            assert_eq!(v.len(), 2);
+            assert_eq!(v[0].account_id, account_id0);
+            assert_eq!(v[0].amountbt, "51");
+            assert_eq!(v[0].amount, 51);
+            assert_eq!(v[0].amount_exp, -2);
+            assert_eq!(v[0].transaction_id, transaction_id0);
+            assert_eq!(v[1].account_id, account_id1);
+            assert_eq!(v[1].amountbt, "5");
+            assert_eq!(v[1].amount_exp, -1);
+        }
+        _ => assert!(false),
+    }
+
+    // 4. A share that is too large for amount.  amountbt gets all of the digits and amount is 0, just as POST /distributions expects.
+    response = client
+        .get(format!(
+            "/allocation?apikey={}&transaction_id={}&amount=12345678901234567890123&exp=0&account_id={},{}&weight=1,0",
+            &apikey, transaction_id0, account_id0, account_id1
+        ))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    match serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap() {
+        D::GetAllocationResponse::Many(v) => {
+            assert_eq!(v.len(), 2);
+            assert_eq!(v[0].amountbt, "12345678901234567890123");
+            assert_eq!(v[0].amount, 0);
+            assert_eq!(v[0].amount_exp, 0);
+            assert_eq!(v[1].amountbt, "0");
+            assert_eq!(v[1].amount, 0);
+        }
+        _ => assert!(false),
+    }
+}
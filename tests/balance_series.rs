@@ -0,0 +1,99 @@
+use bookwerx_core_rust::db as D;
+use bookwerx_core_rust::dfp::dfp::{DFP, Sign};
+use rocket::http::Status;
+use rocket::local::Client;
+
+/* Cash in mattress has three distributions, 3, 4, and 5, in transactions dated 2020, 2020-12, and 2020-12-31.  Cash in cookie jar has the negatives of these.  Both of them are assets.
+ */
+pub fn balance_series(
+    client: &Client,
+    apikey: &String,
+    accounts: &Vec<D::AccountJoined>,
+    categories: &Vec<D::Category>,
+) -> () {
+    let account_id: u32 = (accounts.get(0).unwrap()).id;
+    let zero = DFP { amount: vec![], exp: 0, sign: Sign::Zero };
+    let positive = |amount: Vec<u8>| DFP { amount, exp: 0, sign: Sign::Positive };
+
+    // 1. GET /balance_series, monthly, cumulative.
+    let mut response = client
+        .get(format!(
+            "/balance_series?apikey={}&account_id={}&time_start=2020&time_stop=2020-12&interval=month",
+            &apikey, account_id
+        ))
+        .dispatch();
+    let r: D::BalanceSeries = serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(r.series.len(), 1);
+    let points = &r.series[0].points;
+    assert_eq!(points.len(), 12);
+    assert_eq!(points[0].start, "2020-01-01");
+    assert_eq!(points[0].stop, "2020-02-01");
+    assert_eq!(points[0].sum, positive(vec![3]));
+    assert_eq!(points[10].sum, positive(vec![3]));
+    assert_eq!(points[11].start, "2020-12-01");
+    assert_eq!(points[11].sum, positive(vec![2, 1]));
+
+    // 2. GET /balance_series, monthly, per period.
+    response = client
+        .get(format!(
+            "/balance_series?apikey={}&account_id={}&time_start=2020&time_stop=2020-12&interval=month&cumulative=false",
+            &apikey, account_id
+        ))
+        .dispatch();
+    let r: D::BalanceSeries = serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap();
+    assert_eq!(response.status(), Status::Ok);
+    let points = &r.series[0].points;
+    assert_eq!(points[0].sum, positive(vec![3]));
+    assert_eq!(points[5].sum, zero);
+    assert_eq!(points[11].sum, positive(vec![9]));
+
+    // 3. GET /balance_series, quarterly, cumulative, so the first distribution is before the first bucket.
+    response = client
+        .get(format!(
+            "/balance_series?apikey={}&account_id={}&time_start=2020-12&time_stop=2020-12&interval=quarter&format=decimal",
+            &apikey, account_id
+        ))
+        .dispatch();
+    let v: serde_json::Value = serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(v["series"][0]["points"][0]["start"], serde_json::json!("2020-10-01"));
+    assert_eq!(v["series"][0]["points"][0]["sum"], serde_json::json!("12"));
+
+    // 4. GET /balance_series, yearly, for all the assets.
+    response = client
+        .get(format!(
+            "/balance_series?apikey={}&category_id={}&time_start=2020&time_stop=2020&interval=year",
+            &apikey,
+            (categories.get(0).unwrap()).id
+        ))
+        .dispatch();
+    let r: D::BalanceSeries = serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(r.series.len(), 2);
+    assert_eq!(r.series[0].points[0].sum, positive(vec![2, 1]));
+    assert_eq!(r.series[1].points[0].sum, DFP { amount: vec![2, 1], exp: 0, sign: Sign::Negative });
+
+    // 5. GET /balance_series, with neither account_id nor category_id.
+    response = client
+        .get(format!(
+            "/balance_series?apikey={}&time_start=2020&time_stop=2020&interval=year",
+            &apikey
+        ))
+        .dispatch();
+    let v: serde_json::Value = serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap();
+    assert_eq!(response.status(), Status::Ok);
+    assert!(v["error"].is_string());
+
+    // 6. GET /balance_series, with a category_id that is not a list of integers.
+    response = client
+        .get(format!(
+            "/balance_series?apikey={}&category_id={},x&time_start=2020&time_stop=2020&interval=year",
+            &apikey,
+            (categories.get(0).unwrap()).id
+        ))
+        .dispatch();
+    let v: serde_json::Value = serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap();
+    assert_eq!(response.status(), Status::BadRequest);
+    assert!(v["error"].is_string());
+}
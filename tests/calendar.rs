@@ -0,0 +1,46 @@
+use bookwerx_core_rust::calendar::{Date, Interval, bucket_start, next_bucket_start};
+
+#[test]
+fn date_parse_test() {
+    assert_eq!(Date::parse("2020"), Some(Date { year: 2020, month: 1, day: 1 }));
+    assert_eq!(Date::parse("2020-12"), Some(Date { year: 2020, month: 12, day: 1 }));
+    assert_eq!(Date::parse("2020-12-31"), Some(Date { year: 2020, month: 12, day: 31 }));
+    assert_eq!(Date::parse("2019-07-20T15:32:17.00000001Z"), Some(Date { year: 2019, month: 7, day: 20 }));
+    assert_eq!(Date::parse("2020-02-29"), Some(Date { year: 2020, month: 2, day: 29 }));
+
+    assert_eq!(Date::parse(""), None);
+    assert_eq!(Date::parse("20"), None);
+    assert_eq!(Date::parse("2020/12"), None);
+    assert_eq!(Date::parse("2020-13"), None);
+    assert_eq!(Date::parse("2019-02-29"), None);
+    assert_eq!(Date::parse("last tuesday"), None);
+}
+
+#[test]
+fn date_days_test() {
+    assert_eq!(Date { year: 1970, month: 1, day: 1 }.to_days(), 0);
+    assert_eq!(Date { year: 1969, month: 12, day: 31 }.to_days(), -1);
+    assert_eq!(Date { year: 2000, month: 3, day: 1 }.to_days(), 11017);
+    for days in -100000..100000 {
+        assert_eq!(Date::from_days(days).to_days(), days);
+    }
+}
+
+#[test]
+fn bucket_test() {
+    let date = Date { year: 2020, month: 12, day: 31 };
+    let bucket = |interval: Interval| {
+        (
+            bucket_start(&date, &interval).to_string(),
+            next_bucket_start(&date, &interval).to_string(),
+        )
+    };
+
+    assert_eq!(bucket(Interval::Day), (String::from("2020-12-31"), String::from("2021-01-01")));
+    assert_eq!(bucket(Interval::Week), (String::from("2020-12-28"), String::from("2021-01-04")));
+    assert_eq!(bucket(Interval::Month), (String::from("2020-12-01"), String::from("2021-01-01")));
+    assert_eq!(bucket(Interval::Quarter), (String::from("2020-10-01"), String::from("2021-01-01")));
+    assert_eq!(bucket(Interval::Year), (String::from("2020-01-01"), String::from("2021-01-01")));
+
+    assert_eq!("fortnight".parse::<Interval>().is_err(), true);
+}
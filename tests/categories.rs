@@ -167,5 +167,141 @@ pub fn categories(client: &Client, apikey: &String) -> Vec<D::Category> {
         _ => assert!(false),
     }
 
+    // 11. Examine the category hierarchy.  Temporarily make Equity the parent of Liabilities.
+    let liabilities = ret_val.get(1).unwrap().clone();
+    let equity = ret_val.get(2).unwrap().clone();
+
+    // 11.1 Successful put of a parent.
+    response = client
+        .put("/categories")
+        .body(format!(
+            "apikey={}&id={}&symbol=L&title=Liabilities&parent_id={}",
+            apikey, liabilities.id, equity.id
+        ))
+        .header(ContentType::Form)
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    match serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap() {
+        D::APIResponse::Info(info) => assert_eq!(info, "(Rows matched: 1  Changed: 1  Warnings: 0"),
+        _ => assert!(false),
+    }
+
+    // 11.2 The parent_id is returned.
+    response = client
+        .get(format!("/category/{}/?apikey={}", liabilities.id, apikey))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    match serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap() {
+        D::GetCategoryResponse::One(c) => assert_eq!(c.parent_id, Some(equity.id)),
+        _ => assert!(false),
+    }
+
+    // 11.3 The tree has three roots, ordered by symbol, and Liabilities is a child of Equity.
+    response = client
+        .get(format!("/categories/tree?apikey={}", &apikey))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let tree: D::CategoryTree = serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap();
+    assert_eq!(tree.tree.len(), 3);
+    assert_eq!(tree.tree[2].id, equity.id);
+    assert_eq!(tree.tree[2].children.len(), 1);
+    assert_eq!(tree.tree[2].children[0].id, liabilities.id);
+
+    // 11.4 Try to make a cycle.  Equity can't be a child of its own child.
+    response = client
+        .put("/categories")
+        .body(format!(
+            "apikey={}&id={}&symbol=Eq&title=Equity&parent_id={}",
+            apikey, equity.id, liabilities.id
+        ))
+        .header(ContentType::Form)
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    match serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap() {
+        D::APIResponse::Error(_) => assert!(true),
+        _ => assert!(false),
+    }
+
+    // 11.5 Nor can a category be its own parent.
+    response = client
+        .put("/categories")
+        .body(format!(
+            "apikey={}&id={}&symbol=Eq&title=Equity&parent_id={}",
+            apikey, equity.id, equity.id
+        ))
+        .header(ContentType::Form)
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    match serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap() {
+        D::APIResponse::Error(_) => assert!(true),
+        _ => assert!(false),
+    }
+
+    // 11.6 Try to post a category with a non-existent parent.
+    response = client
+        .post("/categories")
+        .body(format!("apikey={}&symbol=X&title=Orphan&parent_id=666", apikey))
+        .header(ContentType::Form)
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    match serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap() {
+        D::APIResponse::Error(_) => assert!(true),
+        _ => assert!(false),
+    }
+
+    // 11.7 A put without a parent_id leaves the parent alone.
+    response = client
+        .put("/categories")
+        .body(format!(
+            "apikey={}&id={}&symbol=L&title=Liabilities",
+            apikey, liabilities.id
+        ))
+        .header(ContentType::Form)
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    match serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap() {
+        D::APIResponse::Info(info) => assert_eq!(info, "(Rows matched: 1  Changed: 0  Warnings: 0"),
+        _ => assert!(false),
+    }
+
+    response = client
+        .get(format!("/category/{}/?apikey={}", liabilities.id, apikey))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    match serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap() {
+        D::GetCategoryResponse::One(c) => assert_eq!(c.parent_id, Some(equity.id)),
+        _ => assert!(false),
+    }
+
+    // 11.8 A parent_id must be a category id or none.
+    response = client
+        .put("/categories")
+        .body(format!(
+            "apikey={}&id={}&symbol=L&title=Liabilities&parent_id=nobody",
+            apikey, liabilities.id
+        ))
+        .header(ContentType::Form)
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    match serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap() {
+        D::APIResponse::Error(err) => assert!(err.contains("parent_id")),
+        _ => assert!(false),
+    }
+
+    // 11.9 Now restore Liabilities to the top of the hierarchy.
+    response = client
+        .put("/categories")
+        .body(format!(
+            "apikey={}&id={}&symbol=L&title=Liabilities&parent_id=none",
+            apikey, liabilities.id
+        ))
+        .header(ContentType::Form)
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    match serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap() {
+        D::APIResponse::Info(info) => assert_eq!(info, "(Rows matched: 1  Changed: 1  Warnings: 0"),
+        _ => assert!(false),
+    }
+
     ret_val
 }
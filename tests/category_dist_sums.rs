@@ -1,4 +1,5 @@
 use bookwerx_core_rust::db as D;
+use rocket::http::ContentType;
 use rocket::http::Status;
 use rocket::local::Client;
 use bookwerx_core_rust::dfp::dfp::{DFP, Sign, dfp_abs, dfp_add};
@@ -177,4 +178,231 @@ pub fn category_dist_sums(client: &Client, apikey: &String, categories: &Vec<D::
     let r: D::SumsDecorated = serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap();
     assert_eq!(response.status(), Status::Ok);
     assert_eq!(r.sums.len(), 2);
+
+    // 4. The optional format param.
+
+    // 4.1 decimal, decorated.  SumsDecorated can parse this format too.
+    response = client
+        .get(format!(
+            "/category_dist_sums?apikey={}&category_id={}&decorate=true&format=decimal",
+            &apikey,
+            (categories.get(0).unwrap()).id
+        ))
+        .dispatch();
+    let body = response.body_string().unwrap();
+    let v: serde_json::Value = serde_json::from_str(&body[..]).unwrap();
+    assert_eq!(response.status(), Status::Ok);
+    assert!(v["sums"][0]["sum"].is_string());
+    let r: D::SumsDecorated = serde_json::from_str(&body[..]).unwrap();
+    assert_eq!(dfp_abs(&(r.sums[0].sum) ), DFP { amount: vec![2, 1], exp: 0, sign: Sign::Positive });
+
+    // 4.2 significand, undecorated.
+    response = client
+        .get(format!(
+            "/category_dist_sums?apikey={}&category_id={}&format=significand",
+            &apikey,
+            (categories.get(0).unwrap()).id
+        ))
+        .dispatch();
+    let r: D::Sums = serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(r.sums.len(), 2);
+    assert_eq!(
+        dfp_add( r.sums[0].sum.clone(), r.sums[1].sum.clone()),
+        DFP { amount: vec![], exp: 0, sign: Sign::Zero }
+    );
+
+    // 4.3 unknown format
+    response = client
+        .get(format!(
+            "/category_dist_sums?apikey={}&category_id={}&format=hex",
+            &apikey,
+            (categories.get(0).unwrap()).id
+        ))
+        .dispatch();
+    let v: serde_json::Value = serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap();
+    assert_eq!(response.status(), Status::Ok);
+    assert!(v["error"].is_string());
+
+    // 5. Roll up the sums through the category hierarchy.  Temporarily make Equity, which tags no accounts, the parent of Assets.
+    let assets = categories.get(0).unwrap();
+    let equity = categories.get(2).unwrap();
+    let customer = categories.get(3).unwrap();
+    let put_assets = |parent_id: String| {
+        let mut response = client
+            .put("/categories")
+            .body(format!(
+                "apikey={}&id={}&symbol={}&title={}{}",
+                apikey, assets.id, assets.symbol, assets.title, parent_id
+            ))
+            .header(ContentType::Form)
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        match serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap() {
+            D::APIResponse::Info(_) => assert!(true),
+            _ => assert!(false),
+        }
+    };
+    put_assets(format!("&parent_id={}", equity.id));
+
+    // 5.1 Without descendants, Equity tags no accounts.
+    response = client
+        .get(format!(
+            "/category_dist_sums?apikey={}&category_id={}",
+            &apikey, equity.id
+        ))
+        .dispatch();
+    r = serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(r.sums.len(), 0);
+
+    // 5.2 With descendants, Equity includes the two accounts tagged with Assets.
+    response = client
+        .get(format!(
+            "/category_dist_sums?apikey={}&category_id={}&descendants=true",
+            &apikey, equity.id
+        ))
+        .dispatch();
+    r = serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(r.sums.len(), 2);
+    assert_eq!(
+        dfp_add(r.sums[0].sum.clone(), r.sums[1].sum.clone()),
+        DFP { amount: vec![], exp: 0, sign: Sign::Zero }
+    );
+
+    // 5.3 An account must still be tagged by every given category, or a descendant thereof.  Only Cash in mattress is also tagged with Specific customer.
+    response = client
+        .get(format!(
+            "/category_dist_sums?apikey={}&category_id={},{}&descendants=true",
+            &apikey, equity.id, customer.id
+        ))
+        .dispatch();
+    r = serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(r.sums.len(), 1);
+    assert_eq!(r.sums[0].sum, DFP { amount: vec![2, 1], exp: 0, sign: Sign::Positive });
+
+    // 5.4 category_id must be a list of integers.  Nothing else gets anywhere near the db.
+    response = client
+        .get(format!(
+            "/category_dist_sums?apikey={}&category_id=1)%20OR%20(1%3D1&descendants=true",
+            &apikey
+        ))
+        .dispatch();
+    let v: serde_json::Value = serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap();
+    assert_eq!(response.status(), Status::BadRequest);
+    assert!(v["error"].is_string());
+
+    // 5.5 Now restore Assets to the top of the hierarchy.
+    put_assets(String::from("&parent_id=none"));
+
+    // 6. Match any or all of the categories, and exclude some.
+
+    // 6.1 Two accounts (Cash in mattress, Cash in cookie jar) are tagged with any of two categories (Assets, Specific customer).
+    response = client
+        .get(format!(
+            "/category_dist_sums?apikey={}&category_id={},{}&match=any",
+            &apikey, assets.id, customer.id
+        ))
+        .dispatch();
+    r = serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(r.sums.len(), 2);
+    assert_eq!(
+        dfp_add(r.sums[0].sum.clone(), r.sums[1].sum.clone()),
+        DFP { amount: vec![], exp: 0, sign: Sign::Zero }
+    );
+
+    // 6.2 Only one account (Cash in mattress) is tagged with all of them.  This is the default.
+    response = client
+        .get(format!(
+            "/category_dist_sums?apikey={}&category_id={},{}&match=all",
+            &apikey, assets.id, customer.id
+        ))
+        .dispatch();
+    r = serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(r.sums.len(), 1);
+    assert_eq!(r.sums[0].sum, DFP { amount: vec![2, 1], exp: 0, sign: Sign::Positive });
+
+    // 6.3 Assets, but not Specific customer, leaves only Cash in cookie jar.
+    response = client
+        .get(format!(
+            "/category_dist_sums?apikey={}&category_id={}&exclude_category_id={}",
+            &apikey, assets.id, customer.id
+        ))
+        .dispatch();
+    r = serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(r.sums.len(), 1);
+    assert_eq!(r.sums[0].sum, DFP { amount: vec![2, 1], exp: 0, sign: Sign::Negative });
+
+    // 6.4 Excluding every account leaves nothing.
+    response = client
+        .get(format!(
+            "/category_dist_sums?apikey={}&category_id={},{}&match=any&exclude_category_id={}",
+            &apikey, assets.id, customer.id, assets.id
+        ))
+        .dispatch();
+    r = serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(r.sums.len(), 0);
+
+    // 6.5 An unknown match.
+    response = client
+        .get(format!(
+            "/category_dist_sums?apikey={}&category_id={}&match=some",
+            &apikey, assets.id
+        ))
+        .dispatch();
+    let v: serde_json::Value = serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap();
+    assert_eq!(response.status(), Status::Ok);
+    assert!(v["error"].is_string());
+
+    // 6.6 exclude_category_id must be a list of integers.
+    response = client
+        .get(format!(
+            "/category_dist_sums?apikey={}&category_id={}&exclude_category_id=C",
+            &apikey, assets.id
+        ))
+        .dispatch();
+    let v: serde_json::Value = serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap();
+    assert_eq!(response.status(), Status::BadRequest);
+    assert!(v["error"].is_string());
+
+    // 7. Filter by the categories that tag the transactions.  Cash in mattress, tagged with all of Assets and Specific customer, has two transactions (tx1, tx2) that are not tagged with Assets.
+    response = client
+        .get(format!(
+            "/category_dist_sums?apikey={}&category_id={},{}&exclude_trancat_id={}",
+            &apikey, assets.id, customer.id, assets.id
+        ))
+        .dispatch();
+    r = serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(r.sums.len(), 1);
+    assert_eq!(r.sums[0].sum, DFP { amount: vec![9], exp: 0, sign: Sign::Positive });
+
+    // 7.1 Both accounts tagged with Assets have distributions in tx0, which is tagged with Specific customer.
+    response = client
+        .get(format!(
+            "/category_dist_sums?apikey={}&category_id={}&trancat_id={}",
+            &apikey, assets.id, customer.id
+        ))
+        .dispatch();
+    r = serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(r.sums.len(), 2);
+    assert_eq!(dfp_abs(&r.sums[0].sum), DFP { amount: vec![3], exp: 0, sign: Sign::Positive });
+
+    // 7.2 exclude_trancat_id must be a list of integers.
+    response = client
+        .get(format!(
+            "/category_dist_sums?apikey={}&category_id={}&exclude_trancat_id=1,,2",
+            &apikey, assets.id
+        ))
+        .dispatch();
+    let v: serde_json::Value = serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap();
+    assert_eq!(response.status(), Status::BadRequest);
+    assert!(v["error"].is_string());
 }
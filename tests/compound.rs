@@ -0,0 +1,187 @@
+use bookwerx_core_rust::db as D;
+use rocket::http::ContentType;
+use rocket::http::Status;
+use rocket::local::Client;
+
+/* Examine POST and PUT /transactions/compound.  The other tests expect a precise quantity of transactions, distributions, and trancats, so delete everything that we create here before we leave.
+ */
+pub fn compound(
+    client: &Client,
+    apikey: &String,
+    accounts: &Vec<D::AccountJoined>,
+    categories: &Vec<D::Category>,
+) {
+    let account_id0: u32 = (*accounts.get(0).unwrap()).id;
+    let account_id1: u32 = (*accounts.get(1).unwrap()).id;
+    let category_id0: u32 = (*categories.get(0).unwrap()).id;
+
+    let compound = |account_id1: u32, amount: i64| D::CompoundTransaction {
+        apikey: apikey.clone(),
+        notes: String::from("compound"),
+        time: String::from("2020-06"),
+        distributions: vec![
+            D::CompoundDistribution {
+                account_id: account_id0,
+                amount: 7,
+                amount_exp: 0,
+                amountbt: String::from("7"),
+            },
+            D::CompoundDistribution {
+                account_id: account_id1,
+                amount: amount,
+                amount_exp: 0,
+                amountbt: String::from("-7"),
+            },
+        ],
+        category_ids: vec![category_id0],
+    };
+
+    // 1. amount that disagrees with amountbt should fail.
+    let mut response = client
+        .post("/transactions/compound")
+        .body(serde_json::to_string(&compound(account_id1, 7)).unwrap())
+        .header(ContentType::JSON)
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    match serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap() {
+        D::PostCompoundTransactionResponse::Error(_) => assert!(true),
+        _ => assert!(false),
+    }
+
+    // 2. A non-existent account should fail, after the transaction and the 1st distribution have been inserted.
+    response = client
+        .post("/transactions/compound")
+        .body(serde_json::to_string(&compound(666, -7)).unwrap())
+        .header(ContentType::JSON)
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    match serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap() {
+        D::PostCompoundTransactionResponse::Error(_) => assert!(true),
+        _ => assert!(false),
+    }
+
+    // 2.1 So the transaction should have been rolled back.
+    response = client
+        .get(format!("/transactions?apikey={}", &apikey))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    match serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap() {
+        D::GetTransactionResponse::Many(v) => assert_eq!(v.len(), 3),
+        _ => assert!(false),
+    }
+
+    // 3. Successful post.
+    response = client
+        .post("/transactions/compound")
+        .body(serde_json::to_string(&compound(account_id1, -7)).unwrap())
+        .header(ContentType::JSON)
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let ids = match serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap() {
+        D::PostCompoundTransactionResponse::Ids(ids) => ids,
+        _ => panic!("POST /transactions/compound failed"),
+    };
+    assert!(ids.transaction_id > 0);
+    assert_eq!(ids.distribution_ids.len(), 2);
+    assert_eq!(ids.trancat_ids.len(), 1);
+
+    // 3.1 Now verify the distributions for_tx.
+    response = client
+        .get(format!(
+            "/distributions/for_tx?apikey={}&transaction_id={}",
+            &apikey, ids.transaction_id
+        ))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    match serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap() {
+        D::GetDistributionJoinedResponse::Many(v) => assert_eq!(v.len(), 2),
+        _ => assert!(false),
+    }
+
+    // 4. PUT /transactions/compound replaces everything.
+    let update = |id: u32| D::CompoundTransactionUpdate {
+        id: id,
+        apikey: apikey.clone(),
+        notes: String::from("compound replaced"),
+        time: String::from("2020-07"),
+        distributions: vec![
+            D::CompoundDistribution {
+                account_id: account_id0,
+                amount: 9,
+                amount_exp: 0,
+                amountbt: String::from("9"),
+            },
+            D::CompoundDistribution {
+                account_id: account_id1,
+                amount: -9,
+                amount_exp: 0,
+                amountbt: String::from("-9"),
+            },
+        ],
+        category_ids: vec![],
+    };
+
+    // 4.1 A non-existent transaction should fail.
+    response = client
+        .put("/transactions/compound")
+        .body(serde_json::to_string(&update(666666)).unwrap())
+        .header(ContentType::JSON)
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    match serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap() {
+        D::PostCompoundTransactionResponse::Error(_) => assert!(true),
+        _ => assert!(false),
+    }
+
+    // 4.2 Successful put.  The old distributions and trancats are gone.
+    response = client
+        .put("/transactions/compound")
+        .body(serde_json::to_string(&update(ids.transaction_id as u32)).unwrap())
+        .header(ContentType::JSON)
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let new_ids = match serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap() {
+        D::PostCompoundTransactionResponse::Ids(ids) => ids,
+        _ => panic!("PUT /transactions/compound failed"),
+    };
+    assert_eq!(new_ids.transaction_id, ids.transaction_id);
+    assert_eq!(new_ids.trancat_ids.len(), 0);
+
+    response = client
+        .get(format!(
+            "/distributions/for_tx?apikey={}&transaction_id={}",
+            &apikey, ids.transaction_id
+        ))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    match serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap() {
+        D::GetDistributionJoinedResponse::Many(v) => {
+            assert_eq!(v.len(), 2);
+            assert!(v.iter().all(|d| new_ids.distribution_ids.contains(&(d.id as u64))));
+            assert!(v.iter().all(|d| d.tx_notes == "compound replaced"));
+        }
+        _ => assert!(false),
+    }
+
+    // 5. Now clean up.  Deleting the transaction deletes its distributions too.
+    response = client
+        .delete(format!("/transaction/{}/?apikey={}", ids.transaction_id, apikey))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    match serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap() {
+        D::APIResponse::Info(_) => assert!(true),
+        _ => assert!(false),
+    }
+
+    response = client
+        .get(format!(
+            "/distributions/for_tx?apikey={}&transaction_id={}",
+            &apikey, ids.transaction_id
+        ))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    match serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap() {
+        D::GetDistributionJoinedResponse::Many(v) => assert_eq!(v.len(), 0),
+        _ => assert!(false),
+    }
+}
@@ -0,0 +1,100 @@
+use bookwerx_core_rust::db as D;
+use bookwerx_core_rust::dfp::dfp::{DFP, Sign};
+use rocket::http::ContentType;
+use rocket::http::Status;
+use rocket::local::Client;
+use serde_json::Value;
+
+/* Convert the sums of Cash in mattress and Cash in cookie jar, which are QTL, into XAU.  The deletor expects to be able to delete the currencies, so delete the prices that we create here before we leave.
+ */
+pub fn conversions(
+    client: &Client,
+    apikey: &String,
+    currencies: &Vec<D::Currency>,
+    accounts: &Vec<D::AccountJoined>,
+    categories: &Vec<D::Category>,
+) {
+    let qtl: u32 = (currencies.get(0).unwrap()).id;
+    let xau: u32 = (currencies.get(1).unwrap()).id;
+    let account_id: u32 = (accounts.get(0).unwrap()).id;
+    let dfp = |amount: Vec<u8>, exp: i8, sign: Sign| DFP { amount, exp, sign };
+    let get = |url: String| -> Value {
+        let mut response = client.get(url).dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap()
+    };
+    let converted_sum = |v: &Value| -> DFP { serde_json::from_value(v["converted"]["sum"].clone()).unwrap() };
+
+    // 1. 1 QTL = 0.5 XAU in 2020-06, and 0.4 XAU in 2021.
+    let mut lids: Vec<u64> = Vec::new();
+    for (time, rate) in vec![("2020-06", 5), ("2021", 4)] {
+        let mut response = client
+            .post("/prices")
+            .body(format!(
+                "apikey={}&currency_id={}&base_currency_id={}&time={}&rate={}&rate_exp=-1",
+                apikey, qtl, xau, time, rate
+            ))
+            .header(ContentType::Form)
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        match serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap() {
+            D::APIResponse::LastInsertId(lid) => lids.push(lid),
+            _ => panic!("POST /prices failed"),
+        }
+    }
+
+    // 2. account_dist_sum, with no valuation_time, uses the most recent price.  12 * 0.4 = 4.8
+    let url = format!("/account_dist_sum?apikey={}&account_id={}&convert_to={}", apikey, account_id, xau);
+    let v = get(url.clone());
+    assert_eq!(v["converted"]["currency_id"], serde_json::json!(xau));
+    assert_eq!(v["converted"]["rate_missing"], serde_json::json!(false));
+    assert_eq!(converted_sum(&v), dfp(vec![8, 4], -1, Sign::Positive));
+
+    // 2.1 With a valuation_time.  12 * 0.5 = 6
+    let v = get(format!("{}&valuation_time=2020-07", url));
+    assert_eq!(converted_sum(&v), dfp(vec![6], 0, Sign::Positive));
+
+    // 2.2 The valuation_time defaults to time_stop.  7 * 0.5 = 3.5
+    let v = get(format!("{}&time_stop=2020-12", url));
+    assert_eq!(v["converted"]["valuation_time"], serde_json::json!("2020-12"));
+    assert_eq!(converted_sum(&v), dfp(vec![5, 3], -1, Sign::Positive));
+
+    // 2.3 There's no rate before the first price.
+    let v = get(format!("{}&valuation_time=2019", url));
+    assert_eq!(v["converted"]["rate_missing"], serde_json::json!(true));
+    assert_eq!(v["converted"]["sum"], Value::Null);
+
+    // 2.4 Converting into the same currency needs no rate.
+    let v = get(format!("/account_dist_sum?apikey={}&account_id={}&convert_to={}", apikey, account_id, qtl));
+    assert_eq!(converted_sum(&v), dfp(vec![2, 1], 0, Sign::Positive));
+
+    // 2.5 convert_to must be a currency_id.
+    let v = get(format!("/account_dist_sum?apikey={}&account_id={}&convert_to=XAU", apikey, account_id));
+    assert!(v["error"].is_string());
+
+    // 3. category_dist_sums, for the assets, decorated.
+    let v = get(format!(
+        "/category_dist_sums?apikey={}&category_id={}&decorate=true&convert_to={}&valuation_time=2020-07",
+        apikey,
+        (categories.get(0).unwrap()).id,
+        xau
+    ));
+    let sums = v["sums"].as_array().unwrap();
+    assert_eq!(sums.len(), 2);
+    for s in sums {
+        let expected = if s["account"]["account_id"] == serde_json::json!(account_id) {
+            dfp(vec![6], 0, Sign::Positive)
+        } else {
+            dfp(vec![6], 0, Sign::Negative)
+        };
+        assert_eq!(converted_sum(s), expected);
+    }
+
+    // 4. Now clean up.
+    for lid in lids {
+        let response = client
+            .delete(format!("/price/{}/?apikey={}", lid, apikey))
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+    }
+}
@@ -1,4 +1,14 @@
-use bookwerx_core_rust::dfp::dfp::{DFP, Sign, dfp_abs, dfp_add, dfp_from_string_exp};
+use bookwerx_core_rust::dfp::dfp::{DFP, DFPFormat, FormattedJson, ParseDFPError, RoundingMode, Sign, dfp_abs, dfp_add, dfp_allocate, dfp_div, dfp_from_string_exp, dfp_mul, dfp_round, dfp_sub, dfp_to_json, dfp_to_string_exp};
+use serde_json::json;
+
+// Because DFP compare by value, tests that care about the exact representation must compare this instead.
+fn repr( dfp : DFP) -> (Vec<u8>, i8, Sign) {
+    (dfp.amount, dfp.exp, dfp.sign)
+}
+
+fn reprs( v : Vec<DFP>) -> Vec<(Vec<u8>, i8, Sign)> {
+    v.into_iter().map(repr).collect()
+}
 
 #[test]
 fn dfp_abs_test() {
@@ -8,15 +18,15 @@ fn dfp_abs_test() {
 
     input = DFP { amount: vec![1], exp: 0, sign: Sign::Positive };
     output = DFP { amount: vec![1], exp: 0, sign: Sign::Positive };
-    assert_eq!(dfp_abs(&input), output);
+    assert_eq!( repr(dfp_abs(&input)), repr(output));
 
     input = DFP { amount: vec![1], exp: 0, sign: Sign::Negative };
     output = DFP { amount: vec![1], exp: 0, sign: Sign::Positive };
-    assert_eq!(dfp_abs(&input), output);
+    assert_eq!( repr(dfp_abs(&input)), repr(output));
 
     input = DFP { amount: vec![], exp: 0, sign: Sign::Zero };
     output = DFP { amount: vec![], exp: 0, sign: Sign::Zero };
-    assert_eq!(dfp_abs(&input), output);
+    assert_eq!( repr(dfp_abs(&input)), repr(output));
 }
 
 #[test]
@@ -29,212 +39,365 @@ fn dfp_add_test() {
     input1 = DFP{ amount: vec![], exp: 0, sign: Sign::Zero };
     input2 = DFP{ amount: vec![], exp: 0, sign: Sign::Zero };
     output = DFP{ amount: vec![], exp: 0, sign: Sign::Zero };
-    assert_eq!( dfp_add(input1, input2), output);
+    assert_eq!( repr(dfp_add(input1, input2)), repr(output));
 
     input1 = DFP { amount: vec![], exp: 0, sign: Sign::Zero };
     input2 = DFP { amount: vec![1], exp: 0, sign: Sign::Positive };
     output = DFP{ amount: vec![1], exp: 0, sign: Sign::Positive };
-    assert_eq!( dfp_add(input1, input2), output);
+    assert_eq!( repr(dfp_add(input1, input2)), repr(output));
 
     input1 = DFP { amount: vec![1], exp: 0, sign: Sign::Positive };
     input2 = DFP { amount: vec![], exp: 0, sign: Sign::Zero };
     output = DFP{ amount: vec![1], exp: 0, sign: Sign::Positive };
-    assert_eq!( dfp_add(input1, input2), output);
+    assert_eq!( repr(dfp_add(input1, input2)), repr(output));
 
     //-- Positive + Positive
     input1 = DFP { amount: vec![1], exp: 0, sign: Sign::Positive };
     input2 = DFP { amount: vec![2], exp: 0, sign: Sign::Positive };
     output = DFP{ amount: vec![3], exp: 0, sign: Sign::Positive };
-    assert_eq!( dfp_add(input1, input2), output);
+    assert_eq!( repr(dfp_add(input1, input2)), repr(output));
 
     input1 = DFP { amount: vec![1], exp: 0, sign: Sign::Positive };
     input2 = DFP { amount: vec![1, 2], exp: 0, sign: Sign::Positive };
     output = DFP{ amount: vec![2, 2], exp: 0, sign: Sign::Positive };
-    assert_eq!( dfp_add(input1, input2), output);
+    assert_eq!( repr(dfp_add(input1, input2)), repr(output));
 
     input1 = DFP { amount: vec![2, 1], exp: -1, sign: Sign::Positive };
     input2 = DFP { amount: vec![3], exp: 0, sign: Sign::Positive };
     output = DFP{ amount: vec![2, 4], exp: -1, sign: Sign::Positive };
-    assert_eq!( dfp_add(input1, input2), output);
+    assert_eq!( repr(dfp_add(input1, input2)), repr(output));
 
     input1 = DFP { amount: vec![9,4], exp: -1, sign: Sign::Positive };
     input2 = DFP { amount: vec![1], exp: 0, sign: Sign::Positive };
     output = DFP{ amount: vec![9, 5], exp: -1, sign: Sign::Positive };
-    assert_eq!( dfp_add(input1, input2), output);
+    assert_eq!( repr(dfp_add(input1, input2)), repr(output));
 
     input1 = DFP { amount: vec![9,4], exp: 0, sign: Sign::Positive };
     input2 = DFP { amount: vec![1], exp: 0, sign: Sign::Positive };
     output = DFP{ amount: vec![5], exp: 1, sign: Sign::Positive };
-    assert_eq!( dfp_add(input1, input2), output);
+    assert_eq!( repr(dfp_add(input1, input2)), repr(output));
 
     input1 = DFP { amount: vec![9,4], exp: 1, sign: Sign::Positive };
     input2 = DFP { amount: vec![1], exp: 0, sign: Sign::Positive };
     output = DFP{ amount: vec![1, 9, 4], exp: 0, sign: Sign::Positive };
-    assert_eq!( dfp_add(input1, input2), output);
+    assert_eq!( repr(dfp_add(input1, input2)), repr(output));
 
     input1 = DFP { amount: vec![9,4], exp: 1, sign: Sign::Positive };
     input2 = DFP { amount: vec![1], exp: -1, sign: Sign::Positive };
     output = DFP{ amount: vec![1, 0, 9, 4], exp: -1, sign: Sign::Positive };
-    assert_eq!( dfp_add(input1, input2), output);
+    assert_eq!( repr(dfp_add(input1, input2)), repr(output));
 
     //-- Positive + Negative
     input1 = DFP { amount: vec![1], exp: 0, sign: Sign::Positive };
     input2 = DFP { amount: vec![1], exp: 0, sign: Sign::Negative };
     output = DFP{ amount: vec![], exp: 0, sign: Sign::Zero };
-    assert_eq!( dfp_add(input1, input2), output);
+    assert_eq!( repr(dfp_add(input1, input2)), repr(output));
 
     input1 = DFP { amount: vec![1], exp: 0, sign: Sign::Positive };
     input2 = DFP { amount: vec![3], exp: 0, sign: Sign::Negative };
     output = DFP{ amount: vec![2], exp: 0, sign: Sign::Negative };
-    assert_eq!( dfp_add(input1, input2), output);
+    assert_eq!( repr(dfp_add(input1, input2)), repr(output));
 
     input1 = DFP { amount: vec![3], exp: 0, sign: Sign::Positive };
     input2 = DFP { amount: vec![2], exp: 0, sign: Sign::Negative };
     output = DFP{ amount: vec![1], exp: 0, sign: Sign::Positive };
-    assert_eq!( dfp_add(input1, input2), output);
+    assert_eq!( repr(dfp_add(input1, input2)), repr(output));
 
     input1 = DFP { amount: vec![1], exp: 0, sign: Sign::Positive };
     input2 = DFP { amount: vec![1,2], exp: 0, sign: Sign::Negative };
     output = DFP{ amount: vec![2], exp: 1, sign: Sign::Negative };
-    assert_eq!( dfp_add(input1, input2), output);
+    assert_eq!( repr(dfp_add(input1, input2)), repr(output));
 
     input1 = DFP { amount: vec![1,2], exp: 0, sign: Sign::Positive };
     input2 = DFP { amount: vec![1], exp: 0, sign: Sign::Negative };
     output = DFP{ amount: vec![2], exp: 1, sign: Sign::Positive };
-    assert_eq!( dfp_add(input1, input2), output);
+    assert_eq!( repr(dfp_add(input1, input2)), repr(output));
 
     input1 = DFP { amount: vec![2,1], exp: -1, sign: Sign::Positive };
     input2 = DFP { amount: vec![3], exp: 0, sign: Sign::Negative };
     output = DFP{ amount: vec![8, 1], exp: -1, sign: Sign::Negative };
-    assert_eq!( dfp_add(input1, input2), output);
+    assert_eq!( repr(dfp_add(input1, input2)), repr(output));
 
     input1 = DFP { amount: vec![9,4], exp: -1, sign: Sign::Positive };
     input2 = DFP { amount: vec![1], exp: 0, sign: Sign::Negative };
     output = DFP{ amount: vec![9, 3], exp: -1, sign: Sign::Positive };
-    assert_eq!( dfp_add(input1, input2), output);
+    assert_eq!( repr(dfp_add(input1, input2)), repr(output));
 
     input1 = DFP { amount: vec![9,4], exp: 0, sign: Sign::Positive };
     input2 = DFP { amount: vec![1], exp: 0, sign: Sign::Negative };
     output = DFP{ amount: vec![8, 4], exp: 0, sign: Sign::Positive };
-    assert_eq!( dfp_add(input1, input2), output);
+    assert_eq!( repr(dfp_add(input1, input2)), repr(output));
 
     input1 = DFP { amount: vec![9,4], exp: 1, sign: Sign::Positive };
     input2 = DFP { amount: vec![1], exp: 0, sign: Sign::Negative };
     output = DFP{ amount: vec![9, 8, 4], exp: 0, sign: Sign::Positive };
-    assert_eq!( dfp_add(input1, input2), output);
+    assert_eq!( repr(dfp_add(input1, input2)), repr(output));
 
     input1 = DFP { amount: vec![9,4], exp: 1, sign: Sign::Positive };
     input2 = DFP { amount: vec![1], exp: -1, sign: Sign::Negative };
     output = DFP{ amount: vec![9, 9, 8, 4], exp: -1, sign: Sign::Positive };
-    assert_eq!( dfp_add(input1, input2), output);
+    assert_eq!( repr(dfp_add(input1, input2)), repr(output));
 
     //-- Negative + Positive
     input1 = DFP { amount: vec![1], exp: 0, sign: Sign::Negative };
     input2 = DFP { amount: vec![1], exp: 0, sign: Sign::Positive };
     output = DFP{ amount: vec![], exp: 0, sign: Sign::Zero };
-    assert_eq!( dfp_add(input1, input2), output);
+    assert_eq!( repr(dfp_add(input1, input2)), repr(output));
 
     input1 = DFP { amount: vec![1], exp: 0, sign: Sign::Negative };
     input2 = DFP { amount: vec![3], exp: 0, sign: Sign::Positive };
     output = DFP{ amount: vec![2], exp: 0, sign: Sign::Positive };
-    assert_eq!( dfp_add(input1, input2), output);
+    assert_eq!( repr(dfp_add(input1, input2)), repr(output));
 
     input1 = DFP { amount: vec![3], exp: 0, sign: Sign::Negative };
     input2 = DFP { amount: vec![2], exp: 0, sign: Sign::Positive };
     output = DFP{ amount: vec![1], exp: 0, sign: Sign::Negative };
-    assert_eq!( dfp_add(input1, input2), output);
+    assert_eq!( repr(dfp_add(input1, input2)), repr(output));
 
     input1 = DFP { amount: vec![1], exp: 0, sign: Sign::Negative };
     input2 = DFP { amount: vec![1,2], exp: 0, sign: Sign::Positive };
     output = DFP{ amount: vec![2], exp: 1, sign: Sign::Positive };
-    assert_eq!( dfp_add(input1, input2), output);
+    assert_eq!( repr(dfp_add(input1, input2)), repr(output));
 
     input1 = DFP { amount: vec![1, 2], exp: 0, sign: Sign::Negative };
     input2 = DFP { amount: vec![1], exp: 0, sign: Sign::Positive };
     output = DFP{ amount: vec![2], exp: 1, sign: Sign::Negative };
-    assert_eq!( dfp_add(input1, input2), output);
+    assert_eq!( repr(dfp_add(input1, input2)), repr(output));
 
     input1 = DFP { amount: vec![2, 1], exp: -1, sign: Sign::Negative };
     input2 = DFP { amount: vec![3], exp: 0, sign: Sign::Positive };
     output = DFP{ amount: vec![8, 1], exp: -1, sign: Sign::Positive };
-    assert_eq!( dfp_add(input1, input2), output);
+    assert_eq!( repr(dfp_add(input1, input2)), repr(output));
 
     input1 = DFP { amount: vec![9, 4], exp: -1, sign: Sign::Negative };
     input2 = DFP { amount: vec![1], exp: 0, sign: Sign::Positive };
     output = DFP{ amount: vec![9, 3], exp: -1, sign: Sign::Negative };
-    assert_eq!( dfp_add(input1, input2), output);
+    assert_eq!( repr(dfp_add(input1, input2)), repr(output));
 
     input1 = DFP { amount: vec![9, 4], exp: 0, sign: Sign::Negative };
     input2 = DFP { amount: vec![1], exp: 0, sign: Sign::Positive };
     output = DFP{ amount: vec![8, 4], exp: 0, sign: Sign::Negative };
-    assert_eq!( dfp_add(input1, input2), output);
+    assert_eq!( repr(dfp_add(input1, input2)), repr(output));
 
     input1 = DFP { amount: vec![9, 4], exp: 1, sign: Sign::Negative };
     input2 = DFP { amount: vec![1], exp: 0, sign: Sign::Positive };
     output = DFP{ amount: vec![9, 8, 4], exp: 0, sign: Sign::Negative };
-    assert_eq!( dfp_add(input1, input2), output);
+    assert_eq!( repr(dfp_add(input1, input2)), repr(output));
 
     input1 = DFP { amount: vec![9, 4], exp: 1, sign: Sign::Negative };
     input2 = DFP { amount: vec![1], exp: -1, sign: Sign::Positive };
     output = DFP{ amount: vec![9, 9, 8, 4], exp: -1, sign: Sign::Negative };
-    assert_eq!( dfp_add(input1, input2), output);
+    assert_eq!( repr(dfp_add(input1, input2)), repr(output));
 
     //-- Negative + Negative
     input1 = DFP { amount: vec![1], exp: 0, sign: Sign::Negative };
     input2 = DFP { amount: vec![1], exp: 0, sign: Sign::Negative };
     output = DFP{ amount: vec![2], exp: 0, sign: Sign::Negative };
-    assert_eq!( dfp_add(input1, input2), output);
+    assert_eq!( repr(dfp_add(input1, input2)), repr(output));
 
     input1 = DFP { amount: vec![1], exp: 0, sign: Sign::Negative };
     input2 = DFP { amount: vec![3], exp: 0, sign: Sign::Negative };
     output = DFP{ amount: vec![4], exp: 0, sign: Sign::Negative };
-    assert_eq!( dfp_add(input1, input2), output);
+    assert_eq!( repr(dfp_add(input1, input2)), repr(output));
 
     input1 = DFP { amount: vec![3], exp: 0, sign: Sign::Negative };
     input2 = DFP { amount: vec![2], exp: 0, sign: Sign::Negative };
     output = DFP{ amount: vec![5], exp: 0, sign: Sign::Negative };
-    assert_eq!( dfp_add(input1, input2), output);
+    assert_eq!( repr(dfp_add(input1, input2)), repr(output));
 
     input1 = DFP { amount: vec![1], exp: 0, sign: Sign::Negative };
     input2 = DFP { amount: vec![1, 2], exp: 0, sign: Sign::Negative };
     output = DFP{ amount: vec![2, 2], exp: 0, sign: Sign::Negative };
-    assert_eq!( dfp_add(input1, input2), output);
+    assert_eq!( repr(dfp_add(input1, input2)), repr(output));
 
     input1 = DFP { amount: vec![1, 2], exp: 0, sign: Sign::Negative };
     input2 = DFP { amount: vec![1], exp: 0, sign: Sign::Negative };
     output = DFP{ amount: vec![2, 2], exp: 0, sign: Sign::Negative };
-    assert_eq!( dfp_add(input1, input2), output);
+    assert_eq!( repr(dfp_add(input1, input2)), repr(output));
 
     input1 = DFP { amount: vec![2, 1], exp: -1, sign: Sign::Negative };
     input2 = DFP { amount: vec![3], exp: 0, sign: Sign::Negative };
     output = DFP{ amount: vec![2, 4], exp: -1, sign: Sign::Negative };
-    assert_eq!( dfp_add(input1, input2), output);
+    assert_eq!( repr(dfp_add(input1, input2)), repr(output));
 
     input1 = DFP { amount: vec![9, 4], exp: -1, sign: Sign::Negative };
     input2 = DFP { amount: vec![1], exp: 0, sign: Sign::Negative };
     output = DFP{ amount: vec![9, 5], exp: -1, sign: Sign::Negative };
-    assert_eq!( dfp_add(input1, input2), output);
+    assert_eq!( repr(dfp_add(input1, input2)), repr(output));
 
     input1 = DFP { amount: vec![9, 4], exp: 0, sign: Sign::Negative };
     input2 = DFP { amount: vec![1], exp: 0, sign: Sign::Negative };
     output = DFP{ amount: vec![5], exp: 1, sign: Sign::Negative };
-    assert_eq!( dfp_add(input1, input2), output);
+    assert_eq!( repr(dfp_add(input1, input2)), repr(output));
 
     input1 = DFP { amount: vec![9, 4], exp: 1, sign: Sign::Negative };
     input2 = DFP { amount: vec![1], exp: 0, sign: Sign::Negative };
     output = DFP{ amount: vec![1, 9, 4], exp: 0, sign: Sign::Negative };
-    assert_eq!( dfp_add(input1, input2), output);
+    assert_eq!( repr(dfp_add(input1, input2)), repr(output));
 
     input1 = DFP { amount: vec![9, 4], exp: 1, sign: Sign::Negative };
     input2 = DFP { amount: vec![1], exp: -1, sign: Sign::Negative };
     output = DFP{ amount: vec![1, 0, 9, 4], exp: -1, sign: Sign::Negative };
-    assert_eq!( dfp_add(input1, input2), output);
+    assert_eq!( repr(dfp_add(input1, input2)), repr(output));
 
     // other
     input1 = DFP { amount: vec![9, 0, 0, 0, 0 ,0, 0, 8, 4, 1, 2], exp: -8, sign: Sign::Positive };
     input2 = DFP { amount: vec![1], exp: -8, sign: Sign::Positive };
     output = DFP { amount: vec![1, 0, 0, 0 ,0, 0, 8, 4, 1, 2], exp: -7, sign: Sign::Positive };
-    assert_eq!( dfp_add(input1, input2), output);
+    assert_eq!( repr(dfp_add(input1, input2)), repr(output));
+}
+
+#[test]
+fn dfp_allocate_test() {
+
+    fn d(s :&str) -> DFP {
+        s.parse().unwrap()
+    }
+
+    // Bad weights
+    assert_eq!( dfp_allocate(d("100"), &vec![], 0), None);
+    assert_eq!( dfp_allocate(d("100"), &vec![d("0"), d("0")], 0), None);
+    assert_eq!( dfp_allocate(d("100"), &vec![d("1"), d("-1")], 0), None);
+
+    // Even split
+    assert_eq!( dfp_allocate(d("100"), &vec![d("1"), d("1")], 0).map(reprs), Some(reprs(vec![d("50"), d("50")])));
+
+    // The leftover goes first come first serve when the remainders are the same.
+    assert_eq!( dfp_allocate(d("100"), &vec![d("1"), d("1"), d("1")], 0).map(reprs), Some(reprs(vec![d("34"), d("33"), d("33")])));
+    assert_eq!( dfp_allocate(d("0.05"), &vec![d("1"), d("1"), d("1")], -2).map(reprs), Some(reprs(vec![d("0.02"), d("0.02"), d("0.01")])));
+
+    // The leftover goes to the largest remainder. 10 * 0.1 / 0.7 = 1.43 and 10 * 0.6 / 0.7 = 8.57
+    assert_eq!( dfp_allocate(d("10"), &vec![d("0.1"), d("0.6")], 0).map(reprs), Some(reprs(vec![d("1"), d("9")])));
+
+    // Negative amounts. The leftover is negative also.
+    assert_eq!( dfp_allocate(d("-100"), &vec![d("1"), d("1"), d("1")], 0).map(reprs), Some(reprs(vec![d("-34"), d("-33"), d("-33")])));
+
+    // Zero weights get nothing
+    assert_eq!( dfp_allocate(d("7"), &vec![d("0"), d("1")], 0).map(reprs), Some(reprs(vec![d("0"), d("7")])));
+
+    // The amount is rounded to exp first.  The shares always add up to this.
+    let shares = dfp_allocate(d("1234.5678"), &vec![d("3"), d("2.5"), d("7"), d("0.01")], -2).unwrap();
+    let total = shares.iter().fold(d("0"), |acc, s| dfp_add(acc, s.clone()));
+    assert_eq!( repr(total), repr(d("1234.57")));
+}
+
+#[test]
+fn dfp_display_test() {
+
+    assert_eq!( DFP { amount: vec![], exp: 0, sign: Sign::Zero }.to_string(), "0");
+    assert_eq!( DFP { amount: vec![1], exp: 0, sign: Sign::Positive }.to_string(), "1");
+    assert_eq!( DFP { amount: vec![1], exp: 0, sign: Sign::Negative }.to_string(), "-1");
+    assert_eq!( DFP { amount: vec![2, 1], exp: 2, sign: Sign::Positive }.to_string(), "1200");
+    assert_eq!( DFP { amount: vec![6, 5, 4, 3, 2, 1], exp: -2, sign: Sign::Negative }.to_string(), "-1234.56");
+    assert_eq!( DFP { amount: vec![5, 1], exp: -9, sign: Sign::Positive }.to_string(), "0.000000015");
+    assert_eq!( DFP { amount: vec![5, 1], exp: -2, sign: Sign::Positive }.to_string(), "0.15");
+    assert_eq!( DFP { amount: vec![5, 1], exp: -1, sign: Sign::Positive }.to_string(), "1.5");
+
+    // Not normalized, but we display it as if it were.
+    assert_eq!( DFP { amount: vec![0, 5], exp: -2, sign: Sign::Positive }.to_string(), "0.5");
+}
+
+#[test]
+fn dfp_from_str_test() {
+
+    assert_eq!( "0".parse::<DFP>().map(repr), Ok(repr(DFP { amount: vec![], exp: 0, sign: Sign::Zero })));
+    assert_eq!( "-0.000".parse::<DFP>().map(repr), Ok(repr(DFP { amount: vec![], exp: 0, sign: Sign::Zero })));
+    assert_eq!( "1".parse::<DFP>().map(repr), Ok(repr(DFP { amount: vec![1], exp: 0, sign: Sign::Positive })));
+    assert_eq!( "+1".parse::<DFP>().map(repr), Ok(repr(DFP { amount: vec![1], exp: 0, sign: Sign::Positive })));
+    assert_eq!( "-1234.5600".parse::<DFP>().map(repr), Ok(repr(DFP { amount: vec![6, 5, 4, 3, 2, 1], exp: -2, sign: Sign::Negative })));
+    assert_eq!( "1200".parse::<DFP>().map(repr), Ok(repr(DFP { amount: vec![2, 1], exp: 2, sign: Sign::Positive })));
+    assert_eq!( "007.50".parse::<DFP>().map(repr), Ok(repr(DFP { amount: vec![5, 7], exp: -1, sign: Sign::Positive })));
+    assert_eq!( ".5".parse::<DFP>().map(repr), Ok(repr(DFP { amount: vec![5], exp: -1, sign: Sign::Positive })));
+    assert_eq!( "5.".parse::<DFP>().map(repr), Ok(repr(DFP { amount: vec![5], exp: 0, sign: Sign::Positive })));
+    assert_eq!( "1.5e-8".parse::<DFP>().map(repr), Ok(repr(DFP { amount: vec![5, 1], exp: -9, sign: Sign::Positive })));
+    assert_eq!( "15E-9".parse::<DFP>().map(repr), Ok(repr(DFP { amount: vec![5, 1], exp: -9, sign: Sign::Positive })));
+    assert_eq!( "-1.5e+3".parse::<DFP>().map(repr), Ok(repr(DFP { amount: vec![5, 1], exp: 2, sign: Sign::Negative })));
+
+    // Normalization brings an otherwise out of range exponent back into range, but only so far.
+    assert_eq!( "100e125".parse::<DFP>().map(repr), Ok(repr(DFP { amount: vec![1], exp: 127, sign: Sign::Positive })));
+    assert_eq!( "1000e125".parse::<DFP>(), Err(ParseDFPError::ExponentOutOfRange));
+
+    // Errors
+    assert_eq!( "".parse::<DFP>(), Err(ParseDFPError::Empty));
+    assert_eq!( "-".parse::<DFP>(), Err(ParseDFPError::Empty));
+    assert_eq!( ".".parse::<DFP>(), Err(ParseDFPError::Empty));
+    assert_eq!( "12-3".parse::<DFP>(), Err(ParseDFPError::InvalidCharacter('-')));
+    assert_eq!( "1x2".parse::<DFP>(), Err(ParseDFPError::InvalidCharacter('x')));
+    assert_eq!( " 1".parse::<DFP>(), Err(ParseDFPError::InvalidCharacter(' ')));
+    assert_eq!( "1.2.3".parse::<DFP>(), Err(ParseDFPError::MultipleDecimalPoints));
+    assert_eq!( "1.5e".parse::<DFP>(), Err(ParseDFPError::MissingExponent));
+    assert_eq!( "1.5e-".parse::<DFP>(), Err(ParseDFPError::MissingExponent));
+    assert_eq!( "1.5e--8".parse::<DFP>(), Err(ParseDFPError::InvalidCharacter('-')));
+    assert_eq!( "1.5e8x".parse::<DFP>(), Err(ParseDFPError::InvalidCharacter('x')));
+    assert_eq!( "1e128".parse::<DFP>(), Err(ParseDFPError::ExponentOutOfRange));
+    assert_eq!( "1e-129".parse::<DFP>(), Err(ParseDFPError::ExponentOutOfRange));
+    assert_eq!( "1e99999999999".parse::<DFP>(), Err(ParseDFPError::ExponentOutOfRange));
+
+    // Round trip
+    for s in vec!["0", "-1234.56", "0.000000015", "1200", "3.14159"] {
+        assert_eq!( s.parse::<DFP>().unwrap().to_string(), s);
+    }
+}
+
+#[test]
+fn dfp_to_string_exp_test() {
+
+    assert_eq!( dfp_to_string_exp(&DFP { amount: vec![], exp: 0, sign: Sign::Zero }), (String::from("0"), 0));
+    assert_eq!( dfp_to_string_exp(&DFP { amount: vec![3, 2, 1], exp: -2, sign: Sign::Positive }), (String::from("123"), -2));
+    assert_eq!( dfp_to_string_exp(&DFP { amount: vec![0, 3, 2, 1], exp: -2, sign: Sign::Negative }), (String::from("-123"), -1));
+
+    // Round trip
+    let (s, exp) = dfp_to_string_exp(&DFP { amount: vec![5, 1], exp: -9, sign: Sign::Negative });
+    assert_eq!( repr(dfp_from_string_exp(&s, exp)), repr(DFP { amount: vec![5, 1], exp: -9, sign: Sign::Negative }));
+}
+
+#[test]
+fn dfp_to_json_test() {
+
+    let d = DFP { amount: vec![6, 5, 4, 3, 2, 1], exp: -2, sign: Sign::Negative };
+    assert_eq!( dfp_to_json(&d, &DFPFormat::Digits), json!({"amount": [6, 5, 4, 3, 2, 1], "exp": -2, "sign": "Negative"}));
+    assert_eq!( dfp_to_json(&d, &DFPFormat::Decimal), json!("-1234.56"));
+    assert_eq!( dfp_to_json(&d, &DFPFormat::Significand), json!({"significand": "-123456", "exp": -2}));
+
+    let z = DFP { amount: vec![], exp: 0, sign: Sign::Zero };
+    assert_eq!( dfp_to_json(&z, &DFPFormat::Decimal), json!("0"));
+    assert_eq!( dfp_to_json(&z, &DFPFormat::Significand), json!({"significand": "0", "exp": 0}));
+
+    assert_eq!( "decimal".parse::<DFPFormat>(), Ok(DFPFormat::Decimal));
+    assert!( "hex".parse::<DFPFormat>().is_err());
+}
+
+#[test]
+fn dfp_formatted_test() {
+
+    let d = DFP { amount: vec![6, 5, 4, 3, 2, 1], exp: -2, sign: Sign::Negative };
+
+    assert_eq!( d.formatted(&DFPFormat::Digits), json!(d.clone()));
+    assert_eq!( Some(d.clone()).formatted(&DFPFormat::Decimal), json!("-1234.56"));
+    assert_eq!( (None as Option<DFP>).formatted(&DFPFormat::Decimal), json!(null));
+    assert_eq!( vec![d.clone(), d].formatted(&DFPFormat::Significand),
+        json!([{"significand": "-123456", "exp": -2}, {"significand": "-123456", "exp": -2}]));
+}
+
+#[test]
+fn dfp_deserialize_test() {
+
+    let d = DFP { amount: vec![6, 5, 4, 3, 2, 1], exp: -2, sign: Sign::Negative };
+
+    // Every format deserializes into the same value.
+    for fmt in vec![DFPFormat::Digits, DFPFormat::Decimal, DFPFormat::Significand] {
+        let s = dfp_to_json(&d, &fmt).to_string();
+        assert_eq!( repr(serde_json::from_str::<DFP>(&s).unwrap()), repr(d.clone()));
+    }
+
+    // The original Digits format is not normalized.
+    let n :DFP = serde_json::from_str(r#"{"amount":[0,1],"exp":1,"sign":"Positive"}"#).unwrap();
+    assert_eq!( n.amount, vec![0, 1]);
+
+    assert!( serde_json::from_str::<DFP>(r#""1.2.3""#).is_err());
+    assert!( serde_json::from_str::<DFP>(r#"{"significand":"1.5","exp":0}"#).is_err());
+    assert!( serde_json::from_str::<DFP>(r#"{"significand":"1e5","exp":0}"#).is_err());
+    assert!( serde_json::from_str::<DFP>(r#"{"significand":"1","exp":200}"#).is_err());
 }
 
 #[test]
@@ -243,32 +406,340 @@ fn dfp_from_string_exp_test() {
     let mut output;
 
     output = DFP{ amount: vec![], exp: 0, sign: Sign::Zero };
-    assert_eq!(dfp_from_string_exp(&("".to_string()), 99), output);
+    assert_eq!( repr(dfp_from_string_exp(&("".to_string()), 99)), repr(output));
 
     output = DFP{ amount: vec![], exp: 0, sign: Sign::Zero };
-    assert_eq!(dfp_from_string_exp(&("x".to_string()), 99), output);
+    assert_eq!( repr(dfp_from_string_exp(&("x".to_string()), 99)), repr(output));
 
     output = DFP{ amount: vec![], exp: 0, sign: Sign::Zero };
-    assert_eq!(dfp_from_string_exp(&("-x".to_string()), 99), output);
+    assert_eq!( repr(dfp_from_string_exp(&("-x".to_string()), 99)), repr(output));
 
     output = DFP{ amount: vec![1], exp: 5, sign: Sign::Positive };
-    assert_eq!(dfp_from_string_exp(&("1".to_string()), 5), output);
+    assert_eq!( repr(dfp_from_string_exp(&("1".to_string()), 5)), repr(output));
 
     output = DFP{ amount: vec![2, 1], exp: -5, sign: Sign::Positive };
-    assert_eq!(dfp_from_string_exp(&("1x2".to_string()), -5), output);
+    assert_eq!( repr(dfp_from_string_exp(&("1x2".to_string()), -5)), repr(output));
 
     output = DFP{ amount: vec![1], exp: 5, sign: Sign::Negative };
-    assert_eq!(dfp_from_string_exp(&("-1".to_string()), 5), output);
+    assert_eq!( repr(dfp_from_string_exp(&("-1".to_string()), 5)), repr(output));
 
     output = DFP{ amount: vec![2,9,7,9,8,5,3,5,6,2,9,5,1,4,1,3], exp: 0, sign: Sign::Positive };
-    assert_eq!(dfp_from_string_exp(&("3141592653589792".to_string()), 0), output);
+    assert_eq!( repr(dfp_from_string_exp(&("3141592653589792".to_string()), 0)), repr(output));
 
     output = DFP{ amount: vec![2,6,7,8,4,0,8,0,8,1,0,2], exp: -8, sign: Sign::Positive };
-    assert_eq!(dfp_from_string_exp(&("201808048762".to_string()), -8), output);
+    assert_eq!( repr(dfp_from_string_exp(&("201808048762".to_string()), -8)), repr(output));
 
     output = DFP{ amount: vec![5], exp: 2, sign: Sign::Positive };
-    assert_eq!(dfp_from_string_exp(&("500".to_string()), 0), output);
+    assert_eq!( repr(dfp_from_string_exp(&("500".to_string()), 0)), repr(output));
 
     output = DFP{ amount: vec![5], exp: 2, sign: Sign::Negative };
-    assert_eq!(dfp_from_string_exp(&("-500".to_string()), 0), output);
+    assert_eq!( repr(dfp_from_string_exp(&("-500".to_string()), 0)), repr(output));
+}
+
+#[test]
+fn dfp_div_test() {
+
+    let mut input1;
+    let mut input2;
+    let mut output;
+
+    // Division by zero
+    input1 = DFP { amount: vec![1], exp: 0, sign: Sign::Positive };
+    input2 = DFP { amount: vec![], exp: 0, sign: Sign::Zero };
+    assert_eq!( dfp_div(input1, input2, 0, RoundingMode::HalfEven).map(repr), None);
+
+    // Zero / n
+    input1 = DFP { amount: vec![], exp: 0, sign: Sign::Zero };
+    input2 = DFP { amount: vec![3], exp: 0, sign: Sign::Positive };
+    output = DFP { amount: vec![], exp: 0, sign: Sign::Zero };
+    assert_eq!( dfp_div(input1, input2, -2, RoundingMode::HalfEven).map(repr), Some(repr(output)));
+
+    // Exact
+    input1 = DFP { amount: vec![6], exp: 0, sign: Sign::Positive };
+    input2 = DFP { amount: vec![3], exp: 0, sign: Sign::Negative };
+    output = DFP { amount: vec![2], exp: 0, sign: Sign::Negative };
+    assert_eq!( dfp_div(input1, input2, -2, RoundingMode::HalfEven).map(repr), Some(repr(output)));
+
+    // 1 / 3 = 0.33
+    input1 = DFP { amount: vec![1], exp: 0, sign: Sign::Positive };
+    input2 = DFP { amount: vec![3], exp: 0, sign: Sign::Positive };
+    output = DFP { amount: vec![3, 3], exp: -2, sign: Sign::Positive };
+    assert_eq!( dfp_div(input1, input2, -2, RoundingMode::HalfEven).map(repr), Some(repr(output)));
+
+    // 2 / 3 = 0.67
+    input1 = DFP { amount: vec![2], exp: 0, sign: Sign::Positive };
+    input2 = DFP { amount: vec![3], exp: 0, sign: Sign::Positive };
+    output = DFP { amount: vec![7, 6], exp: -2, sign: Sign::Positive };
+    assert_eq!( dfp_div(input1, input2, -2, RoundingMode::HalfEven).map(repr), Some(repr(output)));
+
+    // -2 / 3 = -0.66 when truncated and -0.67 when floored.
+    input1 = DFP { amount: vec![2], exp: 0, sign: Sign::Negative };
+    input2 = DFP { amount: vec![3], exp: 0, sign: Sign::Positive };
+    output = DFP { amount: vec![6, 6], exp: -2, sign: Sign::Negative };
+    assert_eq!( dfp_div(input1.clone(), input2.clone(), -2, RoundingMode::Truncate).map(repr), Some(repr(output)));
+    output = DFP { amount: vec![7, 6], exp: -2, sign: Sign::Negative };
+    assert_eq!( dfp_div(input1, input2, -2, RoundingMode::Floor).map(repr), Some(repr(output)));
+
+    // 1 / 3 = 0.34 when rounded towards the ceiling.
+    input1 = DFP { amount: vec![1], exp: 0, sign: Sign::Positive };
+    input2 = DFP { amount: vec![3], exp: 0, sign: Sign::Positive };
+    output = DFP { amount: vec![4, 3], exp: -2, sign: Sign::Positive };
+    assert_eq!( dfp_div(input1, input2, -2, RoundingMode::Ceiling).map(repr), Some(repr(output)));
+
+    // Exactly halfway. 0.25 / 1 at exp -1 is 0.2 with HalfEven and 0.3 with HalfUp.
+    input1 = DFP { amount: vec![5, 2], exp: -2, sign: Sign::Positive };
+    input2 = DFP { amount: vec![1], exp: 0, sign: Sign::Positive };
+    output = DFP { amount: vec![2], exp: -1, sign: Sign::Positive };
+    assert_eq!( dfp_div(input1.clone(), input2.clone(), -1, RoundingMode::HalfEven).map(repr), Some(repr(output)));
+    output = DFP { amount: vec![3], exp: -1, sign: Sign::Positive };
+    assert_eq!( dfp_div(input1, input2, -1, RoundingMode::HalfUp).map(repr), Some(repr(output)));
+
+    // A result that rounds up into a new digit. 0.999 / 1 at exp -2 = 1.00
+    input1 = DFP { amount: vec![9, 9, 9], exp: -3, sign: Sign::Positive };
+    input2 = DFP { amount: vec![1], exp: 0, sign: Sign::Positive };
+    output = DFP { amount: vec![1], exp: 0, sign: Sign::Positive };
+    assert_eq!( dfp_div(input1, input2, -2, RoundingMode::HalfEven).map(repr), Some(repr(output)));
+
+    // Exponents all over the place. 1500 / 0.03 = 50000
+    input1 = DFP { amount: vec![5, 1], exp: 2, sign: Sign::Positive };
+    input2 = DFP { amount: vec![3], exp: -2, sign: Sign::Positive };
+    output = DFP { amount: vec![5], exp: 4, sign: Sign::Positive };
+    assert_eq!( dfp_div(input1, input2, 0, RoundingMode::HalfEven).map(repr), Some(repr(output)));
+
+    // A requested exp that is coarser than the result.  1234 / 1 rounded to the nearest 100 = 1200
+    input1 = DFP { amount: vec![4, 3, 2, 1], exp: 0, sign: Sign::Positive };
+    input2 = DFP { amount: vec![1], exp: 0, sign: Sign::Positive };
+    output = DFP { amount: vec![2, 1], exp: 2, sign: Sign::Positive };
+    assert_eq!( dfp_div(input1, input2, 2, RoundingMode::HalfEven).map(repr), Some(repr(output)));
+}
+
+#[test]
+fn dfp_mul_test() {
+
+    let mut input1;
+    let mut input2;
+    let mut output;
+
+    input1 = DFP { amount: vec![], exp: 0, sign: Sign::Zero };
+    input2 = DFP { amount: vec![3], exp: 0, sign: Sign::Positive };
+    output = DFP { amount: vec![], exp: 0, sign: Sign::Zero };
+    assert_eq!( dfp_mul(input1, input2).map(repr), Some(repr(output)));
+
+    input1 = DFP { amount: vec![2], exp: 0, sign: Sign::Positive };
+    input2 = DFP { amount: vec![3], exp: 0, sign: Sign::Positive };
+    output = DFP { amount: vec![6], exp: 0, sign: Sign::Positive };
+    assert_eq!( dfp_mul(input1, input2).map(repr), Some(repr(output)));
+
+    input1 = DFP { amount: vec![2], exp: 0, sign: Sign::Negative };
+    input2 = DFP { amount: vec![3], exp: 0, sign: Sign::Positive };
+    output = DFP { amount: vec![6], exp: 0, sign: Sign::Negative };
+    assert_eq!( dfp_mul(input1, input2).map(repr), Some(repr(output)));
+
+    input1 = DFP { amount: vec![2], exp: 0, sign: Sign::Negative };
+    input2 = DFP { amount: vec![3], exp: 0, sign: Sign::Negative };
+    output = DFP { amount: vec![6], exp: 0, sign: Sign::Positive };
+    assert_eq!( dfp_mul(input1, input2).map(repr), Some(repr(output)));
+
+    // 1.5 * 0.2 = 0.3
+    input1 = DFP { amount: vec![5, 1], exp: -1, sign: Sign::Positive };
+    input2 = DFP { amount: vec![2], exp: -1, sign: Sign::Positive };
+    output = DFP { amount: vec![3], exp: -1, sign: Sign::Positive };
+    assert_eq!( dfp_mul(input1, input2).map(repr), Some(repr(output)));
+
+    // 25 * 40 = 1000
+    input1 = DFP { amount: vec![5, 2], exp: 0, sign: Sign::Positive };
+    input2 = DFP { amount: vec![4], exp: 1, sign: Sign::Positive };
+    output = DFP { amount: vec![1], exp: 3, sign: Sign::Positive };
+    assert_eq!( dfp_mul(input1, input2).map(repr), Some(repr(output)));
+
+    // The exponent of the product doesn't fit.
+    input1 = DFP { amount: vec![1], exp: 100, sign: Sign::Positive };
+    input2 = DFP { amount: vec![1], exp: 100, sign: Sign::Positive };
+    assert_eq!( dfp_mul(input1, input2), None);
+
+    input1 = DFP { amount: vec![1], exp: -100, sign: Sign::Positive };
+    input2 = DFP { amount: vec![1], exp: -100, sign: Sign::Negative };
+    assert_eq!( dfp_mul(input1, input2), None);
+
+    // Unless the product is zero.
+    input1 = DFP { amount: vec![], exp: 0, sign: Sign::Zero };
+    input2 = DFP { amount: vec![1], exp: -100, sign: Sign::Positive };
+    output = DFP { amount: vec![], exp: 0, sign: Sign::Zero };
+    assert_eq!( dfp_mul(input1.clone(), input2.clone()).map(repr), Some(repr(output.clone())));
+    assert_eq!( dfp_mul(input2, input1).map(repr), Some(repr(output)));
+
+    // The product fits, but it can't be completely normalized.  5e100 * 2e27 = 10e127
+    input1 = DFP { amount: vec![5], exp: 100, sign: Sign::Positive };
+    input2 = DFP { amount: vec![2], exp: 27, sign: Sign::Positive };
+    output = DFP { amount: vec![0, 1], exp: 127, sign: Sign::Positive };
+    assert_eq!( dfp_mul(input1, input2).map(repr), Some(repr(output)));
+
+    // Enough digits to miss the fast path.
+    input1 = "123456789012345678901234567890".parse().unwrap();
+    input2 = "1e-20".parse().unwrap();
+    output = "1234567890.1234567890123456789".parse().unwrap();
+    assert_eq!( dfp_mul(input1.clone(), input1.clone()).map(|p| p.to_string()), Some(String::from("15241578753238836750495351562536198787501905199875019052100")));
+    assert_eq!( dfp_mul(input1, input2).map(repr), Some(repr(output)));
+}
+
+#[test]
+fn dfp_ord_test() {
+
+    let neg2 = DFP { amount: vec![2], exp: 0, sign: Sign::Negative };
+    let neg_half = DFP { amount: vec![5], exp: -1, sign: Sign::Negative };
+    let zero = DFP { amount: vec![], exp: 0, sign: Sign::Zero };
+    let half = DFP { amount: vec![5], exp: -1, sign: Sign::Positive };
+    let one = DFP { amount: vec![1], exp: 0, sign: Sign::Positive };
+    let thousand = DFP { amount: vec![1], exp: 3, sign: Sign::Positive };
+
+    // Different signs
+    assert!( neg2 < zero);
+    assert!( zero < half);
+    assert!( neg2 < one);
+
+    // Same sign, different magnitudes and exponents
+    assert!( half < one);
+    assert!( one < thousand);
+    assert!( neg2 < neg_half);
+    assert!( DFP { amount: vec![9, 9], exp: -2, sign: Sign::Positive } < one);
+
+    // Equal values with different representations
+    assert_eq!( thousand, DFP { amount: vec![0, 1], exp: 2, sign: Sign::Positive });
+    assert_eq!( thousand, DFP { amount: vec![0, 0, 0, 1], exp: 0, sign: Sign::Positive });
+    assert_eq!( zero, DFP { amount: vec![0], exp: 5, sign: Sign::Positive });
+    assert_eq!( zero, DFP { amount: vec![], exp: -2, sign: Sign::Negative });
+    assert_eq!( one, DFP { amount: vec![1, 0], exp: 0, sign: Sign::Positive });
+    assert_ne!( one, DFP { amount: vec![1], exp: 0, sign: Sign::Negative });
+
+    // Sorting, min and max
+    let mut v = vec![thousand.clone(), neg_half.clone(), one.clone(), zero.clone(), neg2.clone(), half.clone()];
+    v.sort();
+    assert_eq!( v, vec![neg2.clone(), neg_half.clone(), zero.clone(), half.clone(), one.clone(), thousand.clone()]);
+    assert_eq!( v.iter().min(), Some(&neg2));
+    assert_eq!( v.iter().max(), Some(&thousand));
+}
+
+#[test]
+fn dfp_round_test() {
+
+    let mut input;
+    let mut output;
+
+    // Zero stays zero.
+    input = DFP { amount: vec![], exp: 0, sign: Sign::Zero };
+    output = DFP { amount: vec![], exp: 0, sign: Sign::Zero };
+    assert_eq!( repr(dfp_round(input, -2, RoundingMode::HalfEven)), repr(output));
+
+    // Nothing to round.  2.3 to exp -2 is still 2.3
+    input = DFP { amount: vec![3, 2], exp: -1, sign: Sign::Positive };
+    output = DFP { amount: vec![3, 2], exp: -1, sign: Sign::Positive };
+    assert_eq!( repr(dfp_round(input, -2, RoundingMode::HalfEven)), repr(output));
+
+    // 2.345 to exp -2, exactly halfway.
+    input = DFP { amount: vec![5, 4, 3, 2], exp: -3, sign: Sign::Positive };
+    output = DFP { amount: vec![4, 3, 2], exp: -2, sign: Sign::Positive };
+    assert_eq!( repr(dfp_round(input.clone(), -2, RoundingMode::HalfEven)), repr(output));
+    output = DFP { amount: vec![5, 3, 2], exp: -2, sign: Sign::Positive };
+    assert_eq!( repr(dfp_round(input.clone(), -2, RoundingMode::HalfUp)), repr(output));
+    output = DFP { amount: vec![4, 3, 2], exp: -2, sign: Sign::Positive };
+    assert_eq!( repr(dfp_round(input.clone(), -2, RoundingMode::Floor)), repr(output));
+    output = DFP { amount: vec![5, 3, 2], exp: -2, sign: Sign::Positive };
+    assert_eq!( repr(dfp_round(input.clone(), -2, RoundingMode::Ceiling)), repr(output));
+    output = DFP { amount: vec![4, 3, 2], exp: -2, sign: Sign::Positive };
+    assert_eq!( repr(dfp_round(input, -2, RoundingMode::Truncate)), repr(output));
+
+    // 2.355 to exp -2, exactly halfway, but the last kept digit is odd.
+    input = DFP { amount: vec![5, 5, 3, 2], exp: -3, sign: Sign::Positive };
+    output = DFP { amount: vec![6, 3, 2], exp: -2, sign: Sign::Positive };
+    assert_eq!( repr(dfp_round(input, -2, RoundingMode::HalfEven)), repr(output));
+
+    // -2.345 to exp -2
+    input = DFP { amount: vec![5, 4, 3, 2], exp: -3, sign: Sign::Negative };
+    output = DFP { amount: vec![4, 3, 2], exp: -2, sign: Sign::Negative };
+    assert_eq!( repr(dfp_round(input.clone(), -2, RoundingMode::HalfEven)), repr(output));
+    output = DFP { amount: vec![5, 3, 2], exp: -2, sign: Sign::Negative };
+    assert_eq!( repr(dfp_round(input.clone(), -2, RoundingMode::HalfUp)), repr(output));
+    output = DFP { amount: vec![5, 3, 2], exp: -2, sign: Sign::Negative };
+    assert_eq!( repr(dfp_round(input.clone(), -2, RoundingMode::Floor)), repr(output));
+    output = DFP { amount: vec![4, 3, 2], exp: -2, sign: Sign::Negative };
+    assert_eq!( repr(dfp_round(input.clone(), -2, RoundingMode::Ceiling)), repr(output));
+    output = DFP { amount: vec![4, 3, 2], exp: -2, sign: Sign::Negative };
+    assert_eq!( repr(dfp_round(input, -2, RoundingMode::Truncate)), repr(output));
+
+    // More or less than halfway. 1.26 and 1.24 to exp -1
+    input = DFP { amount: vec![6, 2, 1], exp: -2, sign: Sign::Positive };
+    output = DFP { amount: vec![3, 1], exp: -1, sign: Sign::Positive };
+    assert_eq!( repr(dfp_round(input, -1, RoundingMode::HalfEven)), repr(output));
+    input = DFP { amount: vec![4, 2, 1], exp: -2, sign: Sign::Positive };
+    output = DFP { amount: vec![2, 1], exp: -1, sign: Sign::Positive };
+    assert_eq!( repr(dfp_round(input, -1, RoundingMode::HalfUp)), repr(output));
+
+    // Discard more than one digit. 1.2501 to exp -1 is more than halfway.
+    input = DFP { amount: vec![1, 0, 5, 2, 1], exp: -4, sign: Sign::Positive };
+    output = DFP { amount: vec![3, 1], exp: -1, sign: Sign::Positive };
+    assert_eq!( repr(dfp_round(input, -1, RoundingMode::HalfEven)), repr(output));
+
+    // Rounding up carries into a new digit.  9.99 to exp -1 = 10
+    input = DFP { amount: vec![9, 9, 9], exp: -2, sign: Sign::Positive };
+    output = DFP { amount: vec![1], exp: 1, sign: Sign::Positive };
+    assert_eq!( repr(dfp_round(input, -1, RoundingMode::HalfEven)), repr(output));
+
+    // Discard all of the digits. 0.004 and 0.006 to exp -2.
+    input = DFP { amount: vec![4], exp: -3, sign: Sign::Positive };
+    output = DFP { amount: vec![], exp: 0, sign: Sign::Zero };
+    assert_eq!( repr(dfp_round(input.clone(), -2, RoundingMode::HalfEven)), repr(output));
+    output = DFP { amount: vec![1], exp: -2, sign: Sign::Positive };
+    assert_eq!( repr(dfp_round(input, -2, RoundingMode::Ceiling)), repr(output));
+    input = DFP { amount: vec![6], exp: -3, sign: Sign::Negative };
+    output = DFP { amount: vec![1], exp: -2, sign: Sign::Negative };
+    assert_eq!( repr(dfp_round(input, -2, RoundingMode::HalfEven)), repr(output));
+
+    // Discard even more digits than we have. 0.0004 to exp 0.
+    input = DFP { amount: vec![4], exp: -4, sign: Sign::Positive };
+    output = DFP { amount: vec![1], exp: 0, sign: Sign::Positive };
+    assert_eq!( repr(dfp_round(input, 0, RoundingMode::Ceiling)), repr(output));
+
+    // Round to a positive exponent. 1250 to the nearest 100.
+    input = DFP { amount: vec![5, 2, 1], exp: 1, sign: Sign::Positive };
+    output = DFP { amount: vec![2, 1], exp: 2, sign: Sign::Positive };
+    assert_eq!( repr(dfp_round(input, 2, RoundingMode::HalfEven)), repr(output));
+}
+
+#[test]
+fn dfp_sub_test() {
+
+    let mut input1;
+    let mut input2;
+    let mut output;
+
+    input1 = DFP { amount: vec![], exp: 0, sign: Sign::Zero };
+    input2 = DFP { amount: vec![], exp: 0, sign: Sign::Zero };
+    output = DFP { amount: vec![], exp: 0, sign: Sign::Zero };
+    assert_eq!( repr(dfp_sub(input1, input2)), repr(output));
+
+    input1 = DFP { amount: vec![], exp: 0, sign: Sign::Zero };
+    input2 = DFP { amount: vec![1], exp: 0, sign: Sign::Positive };
+    output = DFP { amount: vec![1], exp: 0, sign: Sign::Negative };
+    assert_eq!( repr(dfp_sub(input1, input2)), repr(output));
+
+    input1 = DFP { amount: vec![3], exp: 0, sign: Sign::Positive };
+    input2 = DFP { amount: vec![3], exp: 0, sign: Sign::Positive };
+    output = DFP { amount: vec![], exp: 0, sign: Sign::Zero };
+    assert_eq!( repr(dfp_sub(input1, input2)), repr(output));
+
+    input1 = DFP { amount: vec![3], exp: 0, sign: Sign::Positive };
+    input2 = DFP { amount: vec![5], exp: 0, sign: Sign::Positive };
+    output = DFP { amount: vec![2], exp: 0, sign: Sign::Negative };
+    assert_eq!( repr(dfp_sub(input1, input2)), repr(output));
+
+    input1 = DFP { amount: vec![3], exp: 0, sign: Sign::Positive };
+    input2 = DFP { amount: vec![5], exp: 0, sign: Sign::Negative };
+    output = DFP { amount: vec![8], exp: 0, sign: Sign::Positive };
+    assert_eq!( repr(dfp_sub(input1, input2)), repr(output));
+
+    // 1 - 0.1 = 0.9
+    input1 = DFP { amount: vec![1], exp: 0, sign: Sign::Positive };
+    input2 = DFP { amount: vec![1], exp: -1, sign: Sign::Positive };
+    output = DFP { amount: vec![9], exp: -1, sign: Sign::Positive };
+    assert_eq!( repr(dfp_sub(input1, input2)), repr(output));
 }
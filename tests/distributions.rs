@@ -1,4 +1,5 @@
 use bookwerx_core_rust::db as D;
+use bookwerx_core_rust::routz::distribution::{amount_from_amountbt, check_amounts};
 use rocket::http::ContentType;
 use rocket::http::Status;
 use rocket::local::Client;
@@ -80,6 +81,21 @@ pub fn distributions(
         _ => assert!(false),
     }
 
+    // 2.2.1 amount that disagrees with amountbt should fail.
+    response = client
+        .post("/distributions")
+        .body(format!(
+            "apikey={}&transaction_id={}&account_id={}&amount=3&amount_exp=0&amountbt=4",
+            apikey, transaction_id1, account_id1
+        ))
+        .header(ContentType::Form)
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    match serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap() {
+        D::APIResponse::Error(err) => assert_eq!(err, "amount 3 does not agree with amountbt 4.  amount should be 4."),
+        _ => assert!(false),
+    }
+
     // We will have opportunity to POST using a minus sign later, don't test that here.
 
     // 2.3 Successful post.  The amount is wrong, but we will fix it in a subsequent put.
@@ -118,6 +134,21 @@ pub fn distributions(
         _ => assert!(false),
     }
 
+    // 3.1.1 amount that disagrees with amountbt should fail.
+    response = client
+        .put("/distributions")
+        .body(format!(
+            "&apikey={}&id={}&account_id={}&transaction_id={}&amount=-3&amount_exp=0&amountbt=3",
+            apikey, lid, account_id0, transaction_id0
+        ))
+        .header(ContentType::Form)
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    match serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap() {
+        D::APIResponse::Error(err) => assert_eq!(err, "amount -3 does not agree with amountbt 3.  amount should be 3."),
+        _ => assert!(false),
+    }
+
     // 3.2 Successful put, wrong and negative amount.  Test that we can use a - sign.
     response = client
         .put("/distributions")
@@ -283,6 +314,37 @@ pub fn distributions(
         _ => assert!(false),
     }
 
+    // 7.3 amountbt that is too big for an i64 must be paired with an amount of 0.
+    assert_eq!(amount_from_amountbt(&String::from("-9223372036854775808")), Ok(i64::MIN));
+    assert_eq!(amount_from_amountbt(&String::from("9223372036854775808")), Ok(0));
+    assert!(check_amounts(0, &String::from("123456789012345678901234567890")).is_ok());
+    assert!(check_amounts(1, &String::from("123456789012345678901234567890")).is_err());
+
+    // 7.4 All of the above distributions should have agreeable amounts.
+    response = client
+        .get(format!("/distributions/verify_amounts?apikey={}", &apikey))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    match serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap() {
+        D::GetDistributionResponse::Many(v) => assert_eq!(v.len(), 0),
+        _ => assert!(false),
+    }
+
+    // 7.5 So there is nothing to backfill.
+    response = client
+        .post("/distributions/backfill_amounts")
+        .body(format!("apikey={}", &apikey))
+        .header(ContentType::Form)
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    match serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap() {
+        D::PostBackfillAmountsResponse::Backfill(backfill) => {
+            assert_eq!(backfill.updated, 0);
+            assert_eq!(backfill.bad_amountbt.len(), 0);
+        }
+        _ => assert!(false),
+    }
+
     // 8. Retrieve _all_ distributions because we'll need to delete 'em later.
     response = client
         .get(format!("/distributions?apikey={}", &apikey))
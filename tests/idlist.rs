@@ -0,0 +1,26 @@
+use bookwerx_core_rust::idlist::{IdList, id_placeholders};
+use rocket::http::RawStr;
+use rocket::request::FromFormValue;
+
+#[test]
+fn idlist_parse_test() {
+    assert_eq!(IdList::from_form_value(RawStr::from_str("7")), Ok(IdList(vec![7])));
+    assert_eq!(IdList::from_form_value(RawStr::from_str("1,2,3")), Ok(IdList(vec![1, 2, 3])));
+    assert_eq!(IdList::from_form_value(RawStr::from_str("1,%202")), Ok(IdList(vec![1, 2])));
+
+    assert!(IdList::from_form_value(RawStr::from_str("")).is_err());
+    assert!(IdList::from_form_value(RawStr::from_str("1,")).is_err());
+    assert!(IdList::from_form_value(RawStr::from_str("1,-2")).is_err());
+    assert!(IdList::from_form_value(RawStr::from_str("A")).is_err());
+    assert!(IdList::from_form_value(RawStr::from_str("1)%20OR%20(1%3D1")).is_err());
+}
+
+#[test]
+fn id_placeholders_test() {
+    let mut params = vec![String::from("apikey")];
+    assert_eq!(id_placeholders("category_id", &[3, 5], &mut params), ":category_id_0, :category_id_1");
+    assert_eq!(params, vec!["apikey", "3", "5"]);
+
+    assert_eq!(id_placeholders("category_id", &[], &mut params), "");
+    assert_eq!(params.len(), 3);
+}
@@ -5,17 +5,28 @@
 extern crate rocket;
 
 mod account_dist_sum;
+mod account_register;
 mod accounts;
+mod allocation;
 mod acctcats;
 mod apikey;
+mod balance_series;
 mod categories;
 mod category_dist_sums;
+mod compound;
+mod conversions;
 mod currencies;
 mod deletor;
 mod distributions;
 mod linter;
+mod prices;
+mod reports;
+mod seals;
+mod strict;
 mod trancats;
+mod transaction_search;
 mod transactions;
+mod trial_balance;
 
 use bookwerx_core_rust::constants as C;
 use bookwerx_core_rust::db as D;
@@ -107,10 +118,29 @@ fn kahuna_grande(client: &Client, apikey: &String) {
     // Now test transactions_categories.  In this test we connect various categories to transactions.  Don't worry about any other apparent meaning.
     let trancats = trancats::trancats(&client, &apikey, &transactions, &categories);
 
+    // Split an amount across accounts.  This doesn't write anything to the db.
+    allocation::allocation(&client, &apikey, &accounts, &transactions);
+
+    // Create a transaction, its distributions, and its trancats all at once.  This cleans up after itself.
+    compound::compound(&client, &apikey, &accounts, &categories);
+
+    // Record some prices and look them up.  This cleans up after itself.
+    prices::prices(&client, &apikey, &currencies);
+
+    // Examine strict mode and unbalanced transactions.  This cleans up after itself.
+    strict::strict(&client, &apikey, &accounts, &transactions);
+
     // Do some linting
-    linter::linter(&client, &apikey);
-    let _ = account_dist_sum::account_dist_sum(&client, &apikey, &accounts);
+    linter::linter(&client, &apikey, &accounts, &categories);
+    let _ = account_dist_sum::account_dist_sum(&client, &apikey, &accounts, &categories);
+    account_register::account_register(&client, &apikey, &accounts);
+    balance_series::balance_series(&client, &apikey, &accounts, &categories);
     let _ = category_dist_sums::category_dist_sums(&client, &apikey, &categories);
+    trial_balance::trial_balance(&client, &apikey, &accounts);
+    conversions::conversions(&client, &apikey, &currencies, &accounts, &categories);
+    seals::seals(&client, &apikey, &transactions);
+    reports::reports(&client, &apikey, &categories);
+    transaction_search::transaction_search(&client, &apikey, &accounts, &categories, &transactions);
 
     // Now try to delete things.  Ensure that referential integrity constraints prevent inappropriate deletions.
     deletor::deletor(
@@ -150,6 +180,7 @@ fn startup() -> Client {
     // 4. Now crank up Rocket
     let rocket = rocket::custom(config)
         .attach(D::MyRocketSQLConn::fairing())
+        .manage(Z::seal::SealKey::new(C::TEST_SEAL_KEY).unwrap())
         .mount(
             "/",
             routes![
@@ -157,6 +188,7 @@ fn startup() -> Client {
                 Z::account::delete_account,
                 Z::account::get_account,
                 Z::get_account_dist_sum::get_account_dist_sum,
+                Z::get_account_register::get_account_register,
                 Z::account::get_accounts,
                 Z::account::post_account,
                 Z::account::put_account,
@@ -165,10 +197,14 @@ fn startup() -> Client {
                 Z::acctcat::get_acctcats_for_category,
                 Z::acctcat::post_acctcat,
                 Z::acctcat::put_acctcat,
+                Z::get_allocation::get_allocation,
+                Z::get_balance_series::get_balance_series,
                 R::post_apikey,
+                R::put_apikey,
                 Z::category::delete_category,
                 Z::category::get_category,
                 Z::category::get_categories,
+                Z::category::get_categories_tree,
                 Z::category::get_category_bysym,
                 Z::get_category_dist_sums::get_category_dist_sums,
                 Z::category::post_category,
@@ -183,11 +219,30 @@ fn startup() -> Client {
                 Z::distribution::get_distributions,
                 Z::distribution::get_distributions_for_account,
                 Z::distribution::get_distributions_for_tx,
+                Z::distribution::get_distributions_verify_amounts,
+                Z::distribution::post_distributions_backfill_amounts,
                 Z::distribution::post_distribution,
                 Z::distribution::put_distribution,
+                Z::get_linter::get_linter,
+                Z::get_linter::get_linter_rules,
                 Z::get_linter_accounts::get_linter_accounts,
                 Z::get_linter_categories::get_linter_categories,
                 Z::get_linter_currencies::get_linter_currencies,
+                Z::get_linter_transactions::get_linter_transactions_empty,
+                Z::get_linter_transactions::get_linter_transactions_single,
+                Z::get_linter_transactions::get_linter_transactions_unbalanced,
+                Z::get_reports::get_balance_sheet,
+                Z::get_reports::get_income_statement,
+                Z::get_trial_balance::get_trial_balance,
+                Z::post_linter_fix::post_linter_fix,
+                Z::price::delete_price,
+                Z::price::get_price,
+                Z::price::get_prices,
+                Z::price::get_prices_lookup,
+                Z::price::post_price,
+                Z::price::put_price,
+                Z::seal::get_seals_verify,
+                Z::seal::post_seals,
                 Z::trancat::delete_trancat,
                 Z::trancat::get_trancat,
                 Z::trancat::get_trancats_for_category,
@@ -196,7 +251,10 @@ fn startup() -> Client {
                 Z::transaction::delete_transaction,
                 Z::transaction::get_transaction,
                 Z::transaction::get_transactions,
+                Z::transaction::get_transactions_unbalanced,
                 Z::transaction::post_transaction,
+                Z::transaction::post_transaction_compound,
+                Z::transaction::put_transaction_compound,
                 Z::transaction::put_transaction
             ],
         );
@@ -1,8 +1,15 @@
 use bookwerx_core_rust::db as D;
+use bookwerx_core_rust::dfp::dfp::{DFP, Sign};
+use rocket::http::ContentType;
 use rocket::http::Status;
 use rocket::local::Client;
 
-pub fn linter(client: &Client, apikey: &String) {
+pub fn linter(
+    client: &Client,
+    apikey: &String,
+    accounts: &Vec<D::AccountJoined>,
+    categories: &Vec<D::Category>,
+) {
     // 1. GET /linter/accounts.
     let mut response = client
         .get(format!("/linter/accounts?apikey={}", &apikey))
@@ -29,4 +36,217 @@ pub fn linter(client: &Client, apikey: &String) {
         serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap();
     assert_eq!(response.status(), Status::Ok);
     assert_eq!(v.len(), 1);
+
+    // 4. Transactions.  All of the existing transactions have two balancing distributions, so there's nothing to find.
+    for linter in vec!["empty", "single", "unbalanced"] {
+        response = client
+            .get(format!("/linter/transactions/{}?apikey={}", linter, &apikey))
+            .dispatch();
+        let v: Vec<D::LinterTransaction> =
+            serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap();
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(v.len(), 0);
+    }
+    response = client
+        .get(format!("/transactions/unbalanced?apikey={}", &apikey))
+        .dispatch();
+    match serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap() {
+        D::GetUnbalancedResponse::Many(v) => assert_eq!(v.len(), 0),
+        _ => assert!(false),
+    }
+
+    // 4.1 So make an empty transaction...
+    response = client
+        .post("/transactions")
+        .body(format!("apikey={}&notes=linter&time=2020", apikey))
+        .header(ContentType::Form)
+        .dispatch();
+    let transaction_id = match serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap() {
+        D::APIResponse::LastInsertId(lid) => lid,
+        _ => panic!("POST /transactions failed"),
+    };
+
+    response = client
+        .get(format!("/linter/transactions/empty?apikey={}", &apikey))
+        .dispatch();
+    let v: Vec<D::LinterTransaction> =
+        serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap();
+    assert_eq!(v.len(), 1);
+    assert_eq!(v[0].id as u64, transaction_id);
+    assert_eq!(v[0].sums.len(), 0);
+
+    // 4.2 ... and give it a single distribution.
+    response = client
+        .post("/distributions")
+        .body(format!(
+            "apikey={}&transaction_id={}&account_id={}&amount=2&amount_exp=0&amountbt=2",
+            apikey, transaction_id, (accounts.get(0).unwrap()).id
+        ))
+        .header(ContentType::Form)
+        .dispatch();
+    let distribution_id = match serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap() {
+        D::APIResponse::LastInsertId(lid) => lid,
+        _ => panic!("POST /distributions failed"),
+    };
+
+    for linter in vec!["single", "unbalanced"] {
+        response = client
+            .get(format!("/linter/transactions/{}?apikey={}", linter, &apikey))
+            .dispatch();
+        let v: Vec<D::LinterTransaction> =
+            serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap();
+        assert_eq!(v.len(), 1);
+        assert_eq!(v[0].id as u64, transaction_id);
+        assert_eq!(v[0].sums.len(), 1);
+        assert_eq!(v[0].sums[0].sum, DFP { amount: vec![2], exp: 0, sign: Sign::Positive });
+    }
+
+    response = client
+        .get(format!("/transactions/unbalanced?apikey={}", &apikey))
+        .dispatch();
+    match serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap() {
+        D::GetUnbalancedResponse::Many(v) => {
+            assert_eq!(v.len(), 1);
+            assert_eq!(v[0].transaction_id as u64, transaction_id);
+            assert_eq!(v[0].sum, DFP { amount: vec![2], exp: 0, sign: Sign::Positive });
+        }
+        _ => assert!(false),
+    }
+
+    // 4.3 Now clean up.
+    response = client
+        .delete(format!("/distribution/{}/?apikey={}", distribution_id, apikey))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    response = client
+        .delete(format!("/transaction/{}/?apikey={}", transaction_id, apikey))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+
+    // 5. GET /linter/rules.
+    response = client.get("/linter/rules").dispatch();
+    let v: Vec<D::LinterRule> =
+        serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(v.len(), 6);
+    assert_eq!(v.iter().filter(|r| r.fixable).count(), 4);
+
+    // 5.1 GET /linter, all rules.  We should find the same unused account, category, and currency as above.
+    response = client
+        .get(format!("/linter?apikey={}", &apikey))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    match serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap() {
+        D::GetLinterResponse::Many(v) => {
+            assert_eq!(v.len(), 3);
+            assert_eq!(v[0].rule, "unused_accounts");
+            assert_eq!(v[1].rule, "unused_categories");
+            assert_eq!(v[2].rule, "unused_currencies");
+        }
+        _ => assert!(false),
+    }
+
+    // 5.2 GET /linter, selected rules.
+    response = client
+        .get(format!("/linter?apikey={}&rules=unused_currencies,unbalanced_transactions", &apikey))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    match serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap() {
+        D::GetLinterResponse::Many(v) => {
+            assert_eq!(v.len(), 1);
+            assert_eq!(v[0].table, "currencies");
+            assert_eq!(v[0].severity, D::Severity::Info);
+        }
+        _ => assert!(false),
+    }
+
+    // 5.3 GET /linter, unknown rule.
+    response = client
+        .get(format!("/linter?apikey={}&rules=catfood", &apikey))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    match serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap() {
+        D::GetLinterResponse::Error(err) => assert_eq!(err, "catfood is not a linter rule."),
+        _ => assert!(false),
+    }
+
+    // 6. POST /linter/fix, dry-run.  We should get a mutation for each of the unused account, category, and currency.  But don't apply these because the other tests expect these records to exist.
+    response = client
+        .post("/linter/fix")
+        .body(format!("apikey={}", apikey))
+        .header(ContentType::Form)
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    match serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap() {
+        D::PostLinterFixResponse::Many(v) => {
+            assert_eq!(v.len(), 3);
+            assert_eq!(v[0].table, "accounts");
+            assert_eq!(v[0].action, D::MutationAction::Delete);
+            assert_eq!(v[0].id, (accounts.get(3).unwrap()).id);
+        }
+        _ => assert!(false),
+    }
+
+    // 6.1 A rule that is not fixable.
+    response = client
+        .post("/linter/fix")
+        .body(format!("apikey={}&rules=unbalanced_transactions", apikey))
+        .header(ContentType::Form)
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    match serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap() {
+        D::PostLinterFixResponse::Error(err) => assert_eq!(err, "unbalanced_transactions is not fixable."),
+        _ => assert!(false),
+    }
+
+    // 6.2 Make an empty transaction, tag it with a category, and then apply the fix.
+    response = client
+        .post("/transactions")
+        .body(format!("apikey={}&notes=linter&time=2020", apikey))
+        .header(ContentType::Form)
+        .dispatch();
+    let transaction_id = match serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap() {
+        D::APIResponse::LastInsertId(lid) => lid,
+        _ => panic!("POST /transactions failed"),
+    };
+
+    response = client
+        .post("/trancats")
+        .body(format!(
+            "apikey={}&transaction_id={}&category_id={}",
+            apikey,
+            transaction_id,
+            (categories.get(0).unwrap()).id
+        ))
+        .header(ContentType::Form)
+        .dispatch();
+    let trancat_id = match serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap() {
+        D::APIResponse::LastInsertId(lid) => lid,
+        _ => panic!("POST /trancats failed"),
+    };
+
+    response = client
+        .post("/linter/fix")
+        .body(format!("apikey={}&rules=empty_transactions&apply=true", apikey))
+        .header(ContentType::Form)
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    match serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap() {
+        D::PostLinterFixResponse::Many(v) => {
+            assert_eq!(v.len(), 2);
+            assert_eq!(v[0].table, "transactions_categories");
+            assert_eq!(v[0].id as u64, trancat_id);
+            assert_eq!(v[1].table, "transactions");
+            assert_eq!(v[1].id as u64, transaction_id);
+        }
+        _ => assert!(false),
+    }
+
+    // 6.3 And now it's gone.
+    response = client
+        .get(format!("/linter/transactions/empty?apikey={}", &apikey))
+        .dispatch();
+    let v: Vec<D::LinterTransaction> =
+        serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap();
+    assert_eq!(v.len(), 0);
 }
@@ -0,0 +1,148 @@
+use bookwerx_core_rust::db as D;
+use rocket::http::ContentType;
+use rocket::http::Status;
+use rocket::local::Client;
+
+/* Record some prices of Gold in Quatloos and look them up.  The deletor expects to be able to delete the currencies, so delete everything that we create here before we leave.
+ */
+pub fn prices(client: &Client, apikey: &String, currencies: &Vec<D::Currency>) {
+    let qtl: u32 = (currencies.get(0).unwrap()).id;
+    let xau: u32 = (currencies.get(1).unwrap()).id;
+    let gas: u32 = (currencies.get(2).unwrap()).id;
+
+    // 1. GET /prices. sb 200, empty array
+    let mut response = client
+        .get(format!("/prices?apikey={}", &apikey))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    match serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap() {
+        D::GetPriceResponse::Many(v) => assert_eq!(v.len(), 0),
+        _ => assert!(false),
+    }
+
+    // 2. Try to post some prices.
+    let post = |time: &str, rate: &str, rate_exp: i8| {
+        let mut response = client
+            .post("/prices")
+            .body(format!(
+                "apikey={}&currency_id={}&base_currency_id={}&time={}&rate={}&rate_exp={}",
+                apikey, xau, qtl, time, rate, rate_exp
+            ))
+            .header(ContentType::Form)
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap()
+    };
+
+    // 2.1 A rate must be nothing but digits, and it can't be zero.
+    for rate in vec!["-15", "0", "000"] {
+        match post("2020-01", rate, 2) {
+            D::APIResponse::Error(err) => assert!(err.contains("rate")),
+            _ => assert!(false),
+        }
+    }
+
+    // 2.2 Successful posts. 1 XAU = 1500 QTL, and later 1600 QTL.
+    let lid1 = match post("2020-01", "15", 2) {
+        D::APIResponse::LastInsertId(lid) => lid,
+        _ => panic!("POST /prices failed"),
+    };
+    let lid2 = match post("2020-06", "16", 2) {
+        D::APIResponse::LastInsertId(lid) => lid,
+        _ => panic!("POST /prices failed"),
+    };
+
+    // 3. Successful put. 1 XAU = 1650 QTL.
+    response = client
+        .put("/prices")
+        .body(format!(
+            "apikey={}&id={}&currency_id={}&base_currency_id={}&time=2020-06&rate=165&rate_exp=1",
+            apikey, lid2, xau, qtl
+        ))
+        .header(ContentType::Form)
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    match serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap() {
+        D::APIResponse::Info(_) => assert!(true),
+        _ => assert!(false),
+    }
+
+    // 4. GET /price/{id}
+    response = client
+        .get(format!("/price/{}/?apikey={}", lid1, apikey))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    match serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap() {
+        D::GetPriceResponse::One(p) => {
+            assert_eq!(p.rate, "15");
+            assert_eq!(p.rate_exp, 2);
+        }
+        _ => assert!(false),
+    }
+
+    // 5. Look up the most recent price as of a variety of times.
+    let lookup = |currency_id: u32, base_currency_id: u32, time: &str| {
+        let mut response = client
+            .get(format!(
+                "/prices/lookup?apikey={}&currency_id={}&base_currency_id={}&time={}",
+                apikey, currency_id, base_currency_id, time
+            ))
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap()
+    };
+
+    match lookup(xau, qtl, "2020-03") {
+        D::GetPriceResponse::One(p) => assert_eq!(p.id as u64, lid1),
+        _ => assert!(false),
+    }
+    match lookup(xau, qtl, "2020-06") {
+        D::GetPriceResponse::One(p) => {
+            assert_eq!(p.id as u64, lid2);
+            assert_eq!(p.rate, "165");
+        }
+        _ => assert!(false),
+    }
+
+    // 5.1 Nothing before the first price.
+    match lookup(xau, qtl, "2019") {
+        D::GetPriceResponse::Error(_) => assert!(true),
+        _ => assert!(false),
+    }
+
+    // 5.2 A price is not its own inverse.
+    match lookup(qtl, xau, "2020-06") {
+        D::GetPriceResponse::Error(_) => assert!(true),
+        _ => assert!(false),
+    }
+
+    // 6. The General Atomic Shekel is not used by any accounts, but now that it has a price, the linter must not call it unused.
+    response = client
+        .post("/prices")
+        .body(format!(
+            "apikey={}&currency_id={}&base_currency_id={}&time=2020-01&rate=2&rate_exp=0",
+            apikey, gas, qtl
+        ))
+        .header(ContentType::Form)
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let lid3 = match serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap() {
+        D::APIResponse::LastInsertId(lid) => lid,
+        _ => panic!("POST /prices failed"),
+    };
+
+    response = client
+        .get(format!("/linter/currencies?apikey={}", &apikey))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let v: Vec<D::LinterLong> = serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap();
+    assert_eq!(v.len(), 0);
+
+    // 7. Now clean up.
+    for lid in vec![lid1, lid2, lid3] {
+        response = client
+            .delete(format!("/price/{}/?apikey={}", lid, apikey))
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+    }
+}
@@ -0,0 +1,103 @@
+use bookwerx_core_rust::db as D;
+use bookwerx_core_rust::dfp::dfp::{DFP, Sign};
+use rocket::http::Status;
+use rocket::local::Client;
+
+/* Cash in mattress and Cash in cookie jar are QTL assets and have all the distributions.  Cash in mattress is also a specific customer.  Bank of Mises is an XAU liability and doesn't have any.  Nothing is equity.
+ */
+pub fn reports(client: &Client, apikey: &String, categories: &Vec<D::Category>) -> () {
+    let zero = DFP { amount: vec![], exp: 0, sign: Sign::Zero };
+    let liabilities_id: u32 = (categories.get(1).unwrap()).id;
+
+    // 1. GET /balance_sheet, mixing category symbols and ids.
+    let mut response = client
+        .get(format!(
+            "/balance_sheet?apikey={}&assets=A&liabilities={}&equity=Eq",
+            &apikey, liabilities_id
+        ))
+        .dispatch();
+    let r: D::Report = serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(r.currencies.len(), 2);
+
+    let qtl = &r.currencies[0];
+    assert_eq!(qtl.currency.symbol, "QTL");
+    assert_eq!(qtl.sections.len(), 3);
+    assert_eq!(qtl.sections[0].section, "assets");
+    assert_eq!(qtl.sections[0].accounts.len(), 2);
+    assert_eq!(qtl.sections[0].accounts[0].sum, DFP { amount: vec![2, 1], exp: 0, sign: Sign::Positive });
+    assert_eq!(qtl.sections[0].subtotal, zero);
+    assert_eq!(qtl.sections[1].category_id, liabilities_id);
+    assert_eq!(qtl.sections[1].accounts.len(), 0);
+    assert_eq!(qtl.sections[2].accounts.len(), 0);
+    assert_eq!(qtl.total, zero);
+
+    let xau = &r.currencies[1];
+    assert_eq!(xau.currency.symbol, "XAU");
+    assert_eq!(xau.sections[0].accounts.len(), 0);
+    assert_eq!(xau.sections[1].accounts.len(), 1);
+    assert_eq!(xau.sections[1].subtotal, zero);
+
+    // 2. GET /balance_sheet, time_stop.
+    response = client
+        .get(format!(
+            "/balance_sheet?apikey={}&assets=A&liabilities=L&equity=Eq&time_stop=2020-12",
+            &apikey
+        ))
+        .dispatch();
+    let r: D::Report = serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(r.currencies[0].sections[0].accounts[0].sum, DFP { amount: vec![7], exp: 0, sign: Sign::Positive });
+    assert_eq!(r.currencies[0].sections[0].accounts[1].sum, DFP { amount: vec![7], exp: 0, sign: Sign::Negative });
+
+    // 3. GET /balance_sheet, with a category that doesn't exist.
+    response = client
+        .get(format!(
+            "/balance_sheet?apikey={}&assets=nope&liabilities=L&equity=Eq",
+            &apikey
+        ))
+        .dispatch();
+    let v: serde_json::Value = serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap();
+    assert_eq!(response.status(), Status::Ok);
+    assert!(v["error"].as_str().unwrap().contains("is not a category"));
+
+    // 3.1 GET /balance_sheet, with an account in more than one section.  Cash in mattress is both an asset and a specific customer.
+    response = client
+        .get(format!(
+            "/balance_sheet?apikey={}&assets=A&liabilities=C&equity=Eq",
+            &apikey
+        ))
+        .dispatch();
+    let v: serde_json::Value = serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap();
+    assert_eq!(response.status(), Status::BadRequest);
+    let e = v["error"].as_str().unwrap();
+    assert!(e.contains("in more than one section"));
+    assert!(e.contains("Cash in mattress"));
+    assert!(!e.contains("Cash in cookie jar"));
+
+    // 3.2 GET /balance_sheet, with the same category for more than one section.
+    response = client
+        .get(format!(
+            "/balance_sheet?apikey={}&assets=L&liabilities={}&equity=Eq",
+            &apikey, liabilities_id
+        ))
+        .dispatch();
+    let v: serde_json::Value = serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap();
+    assert_eq!(response.status(), Status::BadRequest);
+    assert!(v["error"].as_str().unwrap().contains("is used for more than one section"));
+
+    // 4. GET /income_statement, time_start, time_stop, decimal.
+    response = client
+        .get(format!(
+            "/income_statement?apikey={}&revenue=C&expense=L&time_start=2020-12&time_stop=2020-12&format=decimal",
+            &apikey
+        ))
+        .dispatch();
+    let v: serde_json::Value = serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(v["currencies"][0]["sections"][0]["section"], serde_json::json!("revenue"));
+    assert_eq!(v["currencies"][0]["sections"][0]["subtotal"], serde_json::json!("4"));
+    assert_eq!(v["currencies"][0]["sections"][1]["subtotal"], serde_json::json!("0"));
+    assert_eq!(v["currencies"][0]["total"], serde_json::json!("4"));
+    assert_eq!(v["currencies"][1]["sections"][1]["accounts"][0]["sum"], serde_json::json!("0"));
+}
@@ -0,0 +1,211 @@
+use bookwerx_core_rust::constants as C;
+use bookwerx_core_rust::db as D;
+use rocket::http::ContentType;
+use rocket::http::Status;
+use rocket::local::Client;
+use rocket_contrib::databases::mysql;
+
+/* Seal the transactions, and then tamper with one of them.  Then tamper with it directly in the db, and recompute the seals from there on, as anybody with access to the db but without the seal key could.  Finally, ensure that the linter won't fix a sealed transaction.  Leave the transactions and the seals the way we found them, but the seals remain.
+ */
+pub fn seals(client: &Client, apikey: &String, transactions: &Vec<D::Transaction>) {
+    let verify = || -> D::SealVerification {
+        let mut response = client
+            .get(format!("/seals/verify?apikey={}", apikey))
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap()
+    };
+    let seal = || -> D::APIResponse {
+        let mut response = client
+            .post("/seals")
+            .body(format!("apikey={}", apikey))
+            .header(ContentType::Form)
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap()
+    };
+    let put_transaction = |t: &D::Transaction, notes: &str| {
+        let response = client
+            .put("/transactions")
+            .body(format!(
+                "apikey={}&id={}&notes={}&time={}",
+                apikey, t.id, notes, t.time
+            ))
+            .header(ContentType::Form)
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+    };
+
+    // 1. Nothing is sealed yet.
+    let v = verify();
+    assert_eq!(v.seal_cnt, 0);
+    assert!(v.broken.is_none());
+
+    // 2. Seal all of the transactions.
+    match seal() {
+        D::APIResponse::Info(info) => assert_eq!(info, "3 transactions sealed."),
+        _ => assert!(false),
+    }
+    let v = verify();
+    assert_eq!(v.seal_cnt, 3);
+    assert!(v.broken.is_none());
+
+    // 2.1 There's nothing else to seal.
+    match seal() {
+        D::APIResponse::Info(info) => assert_eq!(info, "0 transactions sealed."),
+        _ => assert!(false),
+    }
+
+    // 3. Tamper with a sealed transaction.
+    let t = transactions.get(1).unwrap();
+    put_transaction(t, "tampered");
+    match verify().broken {
+        Some(b) => assert_eq!(b.transaction_id, t.id),
+        None => assert!(false),
+    }
+
+    // 4. Put it back the way it was.
+    put_transaction(t, &t.notes);
+    assert!(verify().broken.is_none());
+
+    // 5. Now tamper with it directly in the db, and forge the seals from there on.
+    let pool = mysql::Pool::new(format!("{}/{}", C::TEST_CONN, C::TEST_DBNAME)).unwrap();
+    let seals: Vec<(u32, u32, String)> = pool
+        .prep_exec(
+            "SELECT id, transaction_id, hash FROM seals WHERE apikey = :apikey ORDER BY id",
+            vec![apikey.clone()],
+        )
+        .unwrap()
+        .map(|x| mysql::from_row(x.unwrap()))
+        .collect();
+    pool.prep_exec(
+        "UPDATE transactions SET notes = 'tampered' WHERE id = :id",
+        vec![t.id.to_string()],
+    )
+    .unwrap();
+    forge_seals(&pool, apikey, &seals, t.id);
+
+    // 5.1 Without the seal key, the forged seals don't verify.
+    match verify().broken {
+        Some(b) => assert_eq!(b.transaction_id, t.id),
+        None => assert!(false),
+    }
+
+    // 5.2 Put it and the seals back the way they were.
+    pool.prep_exec(
+        "UPDATE transactions SET notes = :notes WHERE id = :id",
+        (&t.notes, &t.id),
+    )
+    .unwrap();
+    for (seal_id, _, hash) in &seals {
+        pool.prep_exec(
+            "UPDATE seals SET hash = :hash WHERE id = :id",
+            (hash, seal_id),
+        )
+        .unwrap();
+    }
+    assert!(verify().broken.is_none());
+
+    // 6. Seal an empty transaction.  The linter will find it, but it won't fix it.
+    let mut response = client
+        .post("/transactions")
+        .body(format!("apikey={}&notes=sealed&time=2020", apikey))
+        .header(ContentType::Form)
+        .dispatch();
+    let transaction_id = match serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap()
+    {
+        D::APIResponse::LastInsertId(lid) => lid,
+        _ => panic!("POST /transactions failed"),
+    };
+    match seal() {
+        D::APIResponse::Info(info) => assert_eq!(info, "1 transactions sealed."),
+        _ => assert!(false),
+    }
+
+    response = client
+        .post("/linter/fix")
+        .body(format!(
+            "apikey={}&rules=empty_transactions&apply=true",
+            apikey
+        ))
+        .header(ContentType::Form)
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    match serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap() {
+        D::PostLinterFixResponse::Many(v) => assert_eq!(v.len(), 0),
+        _ => assert!(false),
+    }
+    assert!(verify().broken.is_none());
+
+    // 6.1 Now clean up, seal and all.
+    pool.prep_exec(
+        "DELETE FROM seals WHERE transaction_id = :transaction_id",
+        vec![transaction_id.to_string()],
+    )
+    .unwrap();
+    response = client
+        .delete(format!(
+            "/transaction/{}/?apikey={}",
+            transaction_id, apikey
+        ))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert!(verify().broken.is_none());
+}
+
+/* Recompute the seals, starting with the seal of the given transaction, using a plain SHA-256 hash of the same payload that crate::routz::seal builds.
+ */
+fn forge_seals(
+    pool: &mysql::Pool,
+    apikey: &String,
+    seals: &Vec<(u32, u32, String)>,
+    transaction_id: u32,
+) {
+    let mut prior_hash = String::from(bookwerx_core_rust::routz::seal::GENESIS_HASH);
+    let mut forging = false;
+    for (seal_id, seal_transaction_id, hash) in seals {
+        forging = forging || *seal_transaction_id == transaction_id;
+        if !forging {
+            prior_hash = hash.clone();
+            continue;
+        }
+
+        let (id, time, notes): (u32, String, String) = pool
+            .prep_exec(
+                "SELECT id, time, notes FROM transactions WHERE id = :id",
+                vec![seal_transaction_id.to_string()],
+            )
+            .unwrap()
+            .map(|x| mysql::from_row(x.unwrap()))
+            .next()
+            .unwrap();
+        let distributions: Vec<serde_json::Value> = pool
+            .prep_exec(
+                "SELECT id, account_id, amountbt, amount_exp FROM distributions WHERE transaction_id = :transaction_id AND apikey = :apikey ORDER BY id",
+                vec![seal_transaction_id.to_string(), apikey.clone()],
+            )
+            .unwrap()
+            .map(|x| {
+                let (id, account_id, amountbt, amount_exp): (u32, u32, String, i8) =
+                    mysql::from_row(x.unwrap());
+                serde_json::json!([id, account_id, amountbt, amount_exp])
+            })
+            .collect();
+        let payload = serde_json::json!([prior_hash, id, time, notes, distributions]).to_string();
+
+        pool.prep_exec(
+            "UPDATE seals SET hash = LOWER(SHA2(:payload, 256)) WHERE id = :id",
+            (&payload, seal_id),
+        )
+        .unwrap();
+        prior_hash = pool
+            .prep_exec(
+                "SELECT hash FROM seals WHERE id = :id",
+                vec![seal_id.to_string()],
+            )
+            .unwrap()
+            .map(|x| mysql::from_row(x.unwrap()))
+            .next()
+            .unwrap();
+    }
+}
@@ -0,0 +1,212 @@
+use bookwerx_core_rust::db as D;
+use bookwerx_core_rust::dfp::dfp::{DFP, Sign};
+use rocket::http::ContentType;
+use rocket::http::Status;
+use rocket::local::Client;
+
+/* Examine strict mode and the search for unbalanced transactions.  The other tests expect a precise quantity of distributions, so delete everything that we create here before we leave.  Also turn strict mode off again.
+ */
+pub fn strict(
+    client: &Client,
+    apikey: &String,
+    accounts: &Vec<D::AccountJoined>,
+    transactions: &Vec<D::Transaction>,
+) {
+    let account_id0: u32 = (*accounts.get(0).unwrap()).id;
+    let account_id1: u32 = (*accounts.get(1).unwrap()).id;
+    let transaction_id0: u32 = (*transactions.get(0).unwrap()).id;
+
+    // 1. All of the existing transactions are balanced.
+    let mut response = client
+        .get(format!("/transactions/unbalanced?apikey={}", &apikey))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    match serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap() {
+        D::GetUnbalancedResponse::Many(v) => assert_eq!(v.len(), 0),
+        _ => assert!(false),
+    }
+
+    // 2. Turn on strict mode.
+    response = client
+        .put("/apikeys")
+        .body(format!("apikey={}&strict=true", apikey))
+        .header(ContentType::Form)
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    match serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap() {
+        D::APIResponse::Info(_) => assert!(true),
+        _ => assert!(false),
+    }
+
+    // 2.1 A single distribution unbalances the transaction, so it should fail.
+    response = client
+        .post("/distributions")
+        .body(format!(
+            "apikey={}&transaction_id={}&account_id={}&amount=1&amount_exp=0&amountbt=1",
+            apikey, transaction_id0, account_id0
+        ))
+        .header(ContentType::Form)
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    match serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap() {
+        D::APIResponse::Error(err) => assert!(err.contains("unbalanced")),
+        _ => assert!(false),
+    }
+
+    // 2.2 So should an unbalanced compound transaction.
+    let compound = |amount: i64| D::CompoundTransaction {
+        apikey: apikey.clone(),
+        notes: String::from("strict"),
+        time: String::from("2020-06"),
+        distributions: vec![
+            D::CompoundDistribution {
+                account_id: account_id0,
+                amount: 7,
+                amount_exp: 0,
+                amountbt: String::from("7"),
+            },
+            D::CompoundDistribution {
+                account_id: account_id1,
+                amount: amount,
+                amount_exp: 0,
+                amountbt: amount.to_string(),
+            },
+        ],
+        category_ids: vec![],
+    };
+
+    response = client
+        .post("/transactions/compound")
+        .body(serde_json::to_string(&compound(-6)).unwrap())
+        .header(ContentType::JSON)
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    match serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap() {
+        D::PostCompoundTransactionResponse::Error(err) => assert!(err.contains("unbalanced")),
+        _ => assert!(false),
+    }
+
+    // 2.3 But a balanced compound transaction is OK.
+    response = client
+        .post("/transactions/compound")
+        .body(serde_json::to_string(&compound(-7)).unwrap())
+        .header(ContentType::JSON)
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let ids = match serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap() {
+        D::PostCompoundTransactionResponse::Ids(ids) => ids,
+        _ => panic!("POST /transactions/compound failed"),
+    };
+
+    // 2.4 Deleting either one of its distributions would unbalance it, so that should fail.
+    response = client
+        .delete(format!("/distribution/{}/?apikey={}", ids.distribution_ids[0], apikey))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    match serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap() {
+        D::APIResponse::Error(err) => assert!(err.contains("unbalanced")),
+        _ => assert!(false),
+    }
+
+    // 2.5 But replacing all of its distributions at once is OK, as long as the result is balanced.
+    let update = |amount: i64| D::CompoundTransactionUpdate {
+        id: ids.transaction_id as u32,
+        apikey: apikey.clone(),
+        notes: String::from("strict"),
+        time: String::from("2020-06"),
+        distributions: vec![
+            D::CompoundDistribution {
+                account_id: account_id0,
+                amount: 8,
+                amount_exp: 0,
+                amountbt: String::from("8"),
+            },
+            D::CompoundDistribution {
+                account_id: account_id1,
+                amount: amount,
+                amount_exp: 0,
+                amountbt: amount.to_string(),
+            },
+        ],
+        category_ids: vec![],
+    };
+
+    response = client
+        .put("/transactions/compound")
+        .body(serde_json::to_string(&update(-7)).unwrap())
+        .header(ContentType::JSON)
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    match serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap() {
+        D::PostCompoundTransactionResponse::Error(err) => assert!(err.contains("unbalanced")),
+        _ => assert!(false),
+    }
+
+    response = client
+        .put("/transactions/compound")
+        .body(serde_json::to_string(&update(-8)).unwrap())
+        .header(ContentType::JSON)
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    match serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap() {
+        D::PostCompoundTransactionResponse::Ids(v) => assert_eq!(v.distribution_ids.len(), 2),
+        _ => assert!(false),
+    }
+
+    // 2.6 Deleting the transaction deletes its distributions too, so nothing is left unbalanced.
+    response = client
+        .delete(format!("/transaction/{}/?apikey={}", ids.transaction_id, apikey))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    match serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap() {
+        D::APIResponse::Info(_) => assert!(true),
+        _ => assert!(false),
+    }
+
+    // 3. Turn off strict mode.
+    response = client
+        .put("/apikeys")
+        .body(format!("apikey={}&strict=false", apikey))
+        .header(ContentType::Form)
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    match serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap() {
+        D::APIResponse::Info(_) => assert!(true),
+        _ => assert!(false),
+    }
+
+    // 3.1 Now a single distribution is OK.
+    response = client
+        .post("/distributions")
+        .body(format!(
+            "apikey={}&transaction_id={}&account_id={}&amount=1&amount_exp=0&amountbt=1",
+            apikey, transaction_id0, account_id0
+        ))
+        .header(ContentType::Form)
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let lid = match serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap() {
+        D::APIResponse::LastInsertId(lid) => lid,
+        _ => panic!("POST /distributions failed"),
+    };
+
+    // 3.2 But we can find it.
+    response = client
+        .get(format!("/transactions/unbalanced?apikey={}", &apikey))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    match serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap() {
+        D::GetUnbalancedResponse::Many(v) => {
+            assert_eq!(v.len(), 1);
+            assert_eq!(v[0].transaction_id, transaction_id0);
+            assert_eq!(v[0].sum, DFP { amount: vec![1], exp: 0, sign: Sign::Positive });
+        }
+        _ => assert!(false),
+    }
+
+    // 4. Now clean up.
+    response = client
+        .delete(format!("/distribution/{}/?apikey={}", lid, apikey))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+}
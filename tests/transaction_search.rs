@@ -0,0 +1,155 @@
+use bookwerx_core_rust::db as D;
+use rocket::http::Status;
+use rocket::local::Client;
+
+// GET /transactions and return the transactions, in the order given.
+fn get_many(client: &Client, url: String) -> Vec<D::Transaction> {
+    let mut response = client.get(url).dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    match serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap() {
+        D::GetTransactionResponse::Many(v) => v,
+        _ => panic!("expected a list of transactions"),
+    }
+}
+
+// GET /transactions and return the page.
+fn get_page(client: &Client, url: String) -> D::TransactionPage {
+    let mut response = client.get(url).dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    match serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap() {
+        D::GetTransactionResponse::Page(p) => p,
+        _ => panic!("expected a page of transactions"),
+    }
+}
+
+// GET /transactions and expect an error.
+fn get_error(client: &Client, url: String) {
+    let mut response = client.get(url).dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    match serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap() {
+        D::GetTransactionResponse::Error(_) => assert!(true),
+        _ => assert!(false),
+    }
+}
+
+fn ids(v: &Vec<D::Transaction>) -> Vec<u32> {
+    v.iter().map(|t| t.id).collect()
+}
+
+/* Search the transactions.  Recall that:
+
+tx  | time       | cash in mattress | cash in cookie jar | tagged with
+tx0 | 2020       | 3                | -3                 | Assets, Specific customer
+tx1 | 2020-12    | 4                | -4                 |
+tx2 | 2020-12-31 | 5                | -5                 |
+
+All of them have the notes "notes".
+ */
+pub fn transaction_search(
+    client: &Client,
+    apikey: &String,
+    accounts: &Vec<D::AccountJoined>,
+    categories: &Vec<D::Category>,
+    transactions: &Vec<D::Transaction>,
+) {
+    let tx0 = transactions.get(0).unwrap().id;
+    let tx1 = transactions.get(1).unwrap().id;
+    let tx2 = transactions.get(2).unwrap().id;
+    let mattress = accounts.get(0).unwrap().id;
+    let cookie_jar = accounts.get(1).unwrap().id;
+
+    // 1. No filters at all.
+    assert_eq!(ids(&get_many(client, format!("/transactions?apikey={}", apikey))), vec![tx0, tx1, tx2]);
+
+    // 2. time_start and time_stop.
+    assert_eq!(
+        ids(&get_many(client, format!("/transactions?apikey={}&time_start=2020-12", apikey))),
+        vec![tx1, tx2]
+    );
+    assert_eq!(
+        ids(&get_many(client, format!("/transactions?apikey={}&time_start=2020-12&time_stop=2020-12", apikey))),
+        vec![tx1]
+    );
+
+    // 3. notes
+
+    // 3.1 A substring.
+    assert_eq!(get_many(client, format!("/transactions?apikey={}&notes=ote", apikey)).len(), 3);
+    assert_eq!(get_many(client, format!("/transactions?apikey={}&notes=nope", apikey)).len(), 0);
+
+    // 3.2 The wildcards of LIKE are just characters.
+    assert_eq!(get_many(client, format!("/transactions?apikey={}&notes=%25", apikey)).len(), 0);
+    assert_eq!(get_many(client, format!("/transactions?apikey={}&notes=n_tes", apikey)).len(), 0);
+
+    // 3.3 Full-text.
+    assert_eq!(get_many(client, format!("/transactions?apikey={}&notes=notes&fulltext=true", apikey)).len(), 3);
+    assert_eq!(get_many(client, format!("/transactions?apikey={}&notes=ote&fulltext=true", apikey)).len(), 0);
+    get_error(client, format!("/transactions?apikey={}&notes=notes&fulltext=maybe", apikey));
+
+    // 4. account_id and amounts.
+
+    // 4.1 Every transaction has a distribution for Cash in mattress.
+    assert_eq!(get_many(client, format!("/transactions?apikey={}&account_id={}", apikey, mattress)).len(), 3);
+
+    // 4.2 amount_min and amount_max
+    assert_eq!(
+        ids(&get_many(client, format!("/transactions?apikey={}&account_id={}&amount_min=4", apikey, mattress))),
+        vec![tx1, tx2]
+    );
+    assert_eq!(
+        ids(&get_many(client, format!("/transactions?apikey={}&account_id={}&amount_min=3.5&amount_max=4.5", apikey, mattress))),
+        vec![tx1]
+    );
+    assert_eq!(
+        ids(&get_many(client, format!("/transactions?apikey={}&amount_max=-4.5", apikey))),
+        vec![tx2]
+    );
+
+    // 4.3 The same distribution must satisfy the account and the amounts.  Cash in cookie jar has no positive distributions.
+    assert_eq!(
+        get_many(client, format!("/transactions?apikey={}&account_id={}&amount_min=0", apikey, cookie_jar)).len(),
+        0
+    );
+
+    // 4.4 Bad params.
+    get_error(client, format!("/transactions?apikey={}&amount_min=lots", apikey));
+    get_error(client, format!("/transactions?apikey={}&account_id=A", apikey));
+
+    // 4.5 The amounts must fit into DECIMAL(65,30).
+    get_error(client, format!("/transactions?apikey={}&amount_min=1e35", apikey));
+    get_error(client, format!("/transactions?apikey={}&amount_max=1e-31", apikey));
+    assert_eq!(get_many(client, format!("/transactions?apikey={}&amount_min=-1e34&amount_max=1e34", apikey)).len(), 3);
+
+    // 5. category_id
+    assert_eq!(
+        ids(&get_many(client, format!("/transactions?apikey={}&category_id={}", apikey, categories.get(3).unwrap().id))),
+        vec![tx0]
+    );
+
+    // 6. sort
+    assert_eq!(ids(&get_many(client, format!("/transactions?apikey={}&sort=-time", apikey))), vec![tx2, tx1, tx0]);
+    assert_eq!(ids(&get_many(client, format!("/transactions?apikey={}&sort=-id", apikey))), vec![tx2, tx1, tx0]);
+    get_error(client, format!("/transactions?apikey={}&sort=notes", apikey));
+
+    // 7. Paging.
+
+    // 7.1 Follow the cursors through all of the pages.
+    let mut page = get_page(client, format!("/transactions?apikey={}&sort=-time&limit=2", apikey));
+    assert_eq!(ids(&page.transactions), vec![tx2, tx1]);
+    let cursor = page.next_cursor.unwrap();
+
+    page = get_page(client, format!("/transactions?apikey={}&sort=-time&limit=2&cursor={}", apikey, cursor));
+    assert_eq!(ids(&page.transactions), vec![tx0]);
+    assert_eq!(page.next_cursor, None);
+
+    // 7.2 A page that is exactly full has no next page.
+    page = get_page(client, format!("/transactions?apikey={}&time_start=2020-12&limit=2", apikey));
+    assert_eq!(ids(&page.transactions), vec![tx1, tx2]);
+    assert_eq!(page.next_cursor, None);
+
+    // 7.3 A cursor only works with the sort that made it.
+    get_error(client, format!("/transactions?apikey={}&sort=id&limit=2&cursor={}", apikey, cursor));
+    get_error(client, format!("/transactions?apikey={}&limit=2&cursor=zz", apikey));
+    get_error(client, format!("/transactions?apikey={}&limit=1001", apikey));
+    get_error(client, format!("/transactions?apikey={}&limit=0", apikey));
+}
@@ -0,0 +1,45 @@
+use bookwerx_core_rust::db as D;
+use bookwerx_core_rust::dfp::dfp::{DFP, Sign};
+use rocket::http::Status;
+use rocket::local::Client;
+
+/* Cash in mattress and Cash in cookie jar are QTL and have all the distributions.  Bank of Mises and Boats n hos are XAU and don't have any.
+ */
+pub fn trial_balance(client: &Client, apikey: &String, accounts: &Vec<D::AccountJoined>) -> () {
+    let zero = DFP { amount: vec![], exp: 0, sign: Sign::Zero };
+
+    // 1. GET /trial_balance, no time_*.
+    let mut response = client
+        .get(format!("/trial_balance?apikey={}", &apikey))
+        .dispatch();
+    let r: D::TrialBalance = serde_json::from_str(&(response.body_string().unwrap())[..]).unwrap();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(r.currencies.len(), 2);
+
+    let qtl = &r.currencies[0];
+    assert_eq!(qtl.currency.symbol, "QTL");
+    assert_eq!(qtl.accounts.len(), 2);
+    assert_eq!(qtl.accounts[0].account_id, (accounts.get(0).unwrap()).id);
+    assert_eq!(qtl.accounts[0].sum, DFP { amount: vec![2, 1], exp: 0, sign: Sign::Positive });
+    assert_eq!(qtl.accounts[1].sum, DFP { amount: vec![2, 1], exp: 0, sign: Sign::Negative });
+    assert_eq!(qtl.total, zero);
+
+    let xau = &r.currencies[1];
+    assert_eq!(xau.currency.symbol, "XAU");
+    assert_eq!(xau.accounts.len(), 2);
+    assert_eq!(xau.accounts[0].sum, zero);
+    assert_eq!(xau.total, zero);
+
+    // 2. GET /trial_balance, time_stop, decimal.
+    response = client
+        .get(format!("/trial_balance?apikey={}&time_stop=2020-12&format=decimal", &apikey))
+        .dispatch();
+    let body = response.body_string().unwrap();
+    let v: serde_json::Value = serde_json::from_str(&body[..]).unwrap();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(v["currencies"][0]["accounts"][0]["sum"], serde_json::json!("7"));
+    assert_eq!(v["currencies"][0]["total"], serde_json::json!("0"));
+
+    let r: D::TrialBalance = serde_json::from_str(&body[..]).unwrap();
+    assert_eq!(r.currencies[0].accounts[1].sum, DFP { amount: vec![7], exp: 0, sign: Sign::Negative });
+}